@@ -37,6 +37,34 @@ pub enum FundError {
     InvalidStakePercent,
     InvalidSigner,
     InvalidSwapDetails,
+    InvalidOracleAccount,
+    StalePrice,
+    MathOverflow,
+    InvalidOrderDetails,
+    OrderNotTriggered,
+    InvalidStakeAccount,
+    ActionAlreadyExecuted,
+    InvalidPosition,
+    InvalidFeeConfig,
+    MissingPriceFeed,
+    InvalidProtocolConfig,
+    MissingPermission,
+    DepositLocked,
+    InvalidWithdrawalRequest,
+    WithdrawalNotClaimable,
+    UnsupportedMint,
+    SharesNotTransferable,
+    FundWindingDown,
+    ProposalNotFinalized,
+    InvalidProposalState,
+    MintNotWhitelisted,
+    InvalidConfigAction,
+    TimelockActive,
+    NotAllowlisted,
+    DepositBelowMinimum,
+    InvalidSwapProgram,
+    FundHasNoValue,
+    DepositTooSmall,
 }
 
 impl From<FundError> for ProgramError {
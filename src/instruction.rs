@@ -1,7 +1,7 @@
 use solana_program::{
     program_error::ProgramError, msg
 };
-use crate::{errors::FundError, state::MerkleRoot};
+use crate::{errors::FundError, state::{ConfigAction, MerkleRoot, PriceFeed}};
 use borsh::{BorshSerialize, BorshDeserialize};
 
 const BYTE_SIZE_8: usize = 8;
@@ -22,6 +22,7 @@ pub enum FundInstruction {
         deadline: i64,
         fund_name: String,
         merkel_bytes: MerkleRoot,
+        actions: u8,
     },
 
     // tag = 22
@@ -42,7 +43,6 @@ pub enum FundInstruction {
     // tag = 3
     AddFundMember {
         fund_name: String,
-        vec_index: u8,
     },
 
     // tag = 4
@@ -70,12 +70,19 @@ pub enum FundInstruction {
         is_unwrapped_sol: u8,
         amount: u64,
         mint_amount: u64,
+        num_of_tokens: u8,
         fund_name: String,
         fund_type: u8,
     },
 
     // tag = 8
-    DeleteFund {},
+    DeleteFund {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        merkel_proof: Vec<[u8; 32]>,
+    },
 
     // tag = 9
     LeaveFund{fund_name: String },
@@ -83,19 +90,18 @@ pub enum FundInstruction {
     // tag = 10
     InitJoinProposal {
         fund_name: String,
+        deadline: i64,
     },
 
     // tag = 11
     JoinVote {
         vote: u8,
         fund_name: String,
-        proposal_index: u8,
     },
 
     // tag = 12
     CancelJoinProposal {
         fund_name: String,
-        proposal_index: u8,
     },
 
     // tag = 13
@@ -110,6 +116,7 @@ pub enum FundInstruction {
         fund_name: String,
         new_size: u32,
         refund_type: u8,
+        deadline: i64,
     },
 
     // tag = 15
@@ -133,9 +140,11 @@ pub enum FundInstruction {
     InitLightFundAccount {
         fund_name: String,
         num_of_members: u8,
-        max_num_members: u8,
+        max_num_members: u32,
         tags: u32,
         add_members_later: u8,
+        expected_members: u32,
+        governance_symbol: String, // empty -> no governance mint
     },
 
     // tag = 19
@@ -156,7 +165,276 @@ pub enum FundInstruction {
         task: u8,
         stake_percent: u64,
         num_of_tokens: u8,
-    }
+        num_of_stakes: u8,
+        num_of_positions: u8,
+    },
+
+    // tag = 23
+    CreateConditionalOrder {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        order_index: u8,
+        comparison: u8,
+        threshold_price: u64,
+        expiry: i64,
+        feed_id: [u8; 32],
+        swap_leaf: [u8; 32],
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 24
+    TriggerConditionalOrder {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        order_index: u8,
+        amount: u64,
+        slippage: u16,
+    },
+
+    // tag = 25
+    DelegateVaultStake {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        stake_index: u8,
+        amount: u64,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 26
+    DeactivateVaultStake {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        stake_index: u8,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 27
+    WithdrawVaultStake {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        stake_index: u8,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 28
+    DepositToPosition {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        position_index: u8,
+        protocol: u8,
+        amount: u64,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 29
+    WithdrawFromPosition {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        position_index: u8,
+        amount: u64,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 30
+    SetFeeConfig {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        recipient: [u8; 32],
+        management_fee_bps: u16,
+        performance_fee_bps: u16,
+        payout_mode: u8,
+        price_feeds: Vec<PriceFeed>,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 31
+    CrystallizeFees {
+        fund_name: String,
+        num_of_tokens: u8,
+    },
+
+    // tag = 32
+    InitProtocolConfig {
+        multisig: [u8; 32],
+        deposit_fee_bps: u16,
+        withdraw_fee_bps: u16,
+        swap_fee_bps: u16,
+    },
+
+    // tag = 33
+    UpdateProtocolConfig {
+        new_admin: [u8; 32],
+        multisig: [u8; 32],
+        deposit_fee_bps: u16,
+        withdraw_fee_bps: u16,
+        swap_fee_bps: u16,
+    },
+
+    // tag = 34
+    WithdrawProtocolFees {
+        amount: u64,
+    },
+
+    // tag = 35
+    InitRemoveMemberProposal {
+        fund_name: String,
+        deadline: i64,
+    },
+
+    // tag = 36
+    VoteOnRemoveMember {
+        fund_name: String,
+        vote: u8,
+    },
+
+    // tag = 37
+    ExecuteRemoveMember {
+        fund_name: String,
+        num_of_tokens: u8,
+        num_of_stakes: u8,
+        num_of_positions: u8,
+    },
+
+    // tag = 38
+    SetMemberRoles {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        member: [u8; 32],
+        roles: u8,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 39
+    RequestWithdrawal {
+        fund_name: String,
+        task: u8,
+        stake_percent: u64,
+    },
+
+    // tag = 40
+    CancelWithdrawalRequest {
+        fund_name: String,
+    },
+
+    // tag = 41
+    SetLiquidityTerms {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        lockup_period: i64,
+        notice_period: i64,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 42
+    WithdrawSingleAssetFromLightFund {
+        fund_name: String,
+        task: u8,
+        stake_percent: u64,
+        num_of_tokens: u8,
+        num_of_stakes: u8,
+        num_of_positions: u8,
+        target_index: u8,
+        tick_arrays_per_swap: u8,
+        min_amounts_out: Vec<u64>, // per holding: minimum out of its swap leg, the target's entry is the minimum of the whole redemption
+    },
+
+    // tag = 43
+    SetShareTransferPolicy {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        share_transfer_policy: u8,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 44
+    MigrateMember {
+        num_of_aggregators: u8,
+        num_of_remove_proposals: u8,
+        num_of_join_proposals: u8,
+        num_of_orders: u8,
+        num_of_positions: u8,
+    },
+
+    // tag = 45
+    DistributeFundAssets {
+        fund_name: String,
+        num_of_members: u8,
+        num_of_tokens: u8,
+    },
+
+    // tag = 46
+    CloseFund {
+        fund_name: String,
+        num_of_tokens: u8,
+        num_of_join_proposals: u8,
+        num_of_remove_proposals: u8,
+        num_of_requests: u8,
+        num_of_orders: u8,
+    },
+
+    // tag = 47
+    CloseProposal {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+    },
+
+    // tag = 48
+    FinalizeProposal {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+    },
+
+    // tag = 49
+    ExecuteConfigProposal {
+        fund_name: String,
+        proposal_index: u8,
+        vec_index: u16,
+        leaf_index: u8,
+        actions: Vec<ConfigAction>,
+        merkel_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 50
+    JoinPublicFund {
+        is_unwrapped_sol: u8,
+        amount: u64,
+        mint_amount: u64,
+        num_of_tokens: u8,
+        fund_name: String,
+        allowlist_proof: Vec<[u8; 32]>,
+    },
+
+    // tag = 51
+    CloseRemoveMemberProposal {
+        fund_name: String,
+    },
+
+    // tag = 52
+    CloseIncrementProposal {
+        fund_name: String,
+    },
 
 }
 
@@ -172,7 +450,7 @@ impl FundInstruction {
             0 => {
                 let (privacy, rest) = Self::unpack_members(rest)?;
                 let (expected_members, rest) = Self::unpack_expected(rest)?;
-                let (symbol_bytes, rest) = rest.split_at(5 as usize);
+                let (symbol_bytes, rest) = rest.split_at(5);
                 let symbol = symbol_bytes
                     .iter()
                     .take_while(|&&b| b != 0)
@@ -190,7 +468,7 @@ impl FundInstruction {
             1 => {
                 let (deadline, rest) = Self::unpack_deadline(rest)?;
 
-                let (cid_bytes, rest) = rest.split_at(59 as usize);
+                let (cid_bytes, rest) = rest.split_at(59);
                 let cid_raw = cid_bytes
                     .iter()
                     .take_while(|&&b| b != 0)
@@ -198,8 +476,9 @@ impl FundInstruction {
                     .collect::<Vec<u8>>();
                 let cid = String::from_utf8(cid_raw).unwrap();
 
-                let (merkel_bytes, rest) = rest.split_at(32 as usize);
+                let (merkel_bytes, rest) = rest.split_at(32);
                 let merkel_root = MerkleRoot(merkel_bytes.try_into().unwrap());
+                let (&actions, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
 
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
 
@@ -208,12 +487,13 @@ impl FundInstruction {
                     deadline,
                     fund_name,
                     merkel_bytes: merkel_root,
+                    actions,
                 }
             }
             2 => {
                 let (&vote, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (vec_index_bytes, rest) = rest.split_at(2 as usize);
+                let (vec_index_bytes, rest) = rest.split_at(2);
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
                 let vec_index = u16::from_le_bytes(vec_index_bytes.try_into().expect("Wrong Vec Index"));
                 Self::Vote {
@@ -224,9 +504,8 @@ impl FundInstruction {
                 }
             }
             3 => {
-                let (&vec_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
-                Self::AddFundMember { fund_name, vec_index }
+                Self::AddFundMember { fund_name }
             }
             4 => {
                 let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
@@ -265,7 +544,7 @@ impl FundInstruction {
                 Self::InitRentAccount {  }
             }
             6 => {
-                let (cid_bytes, _rest) = rest.split_at(59 as usize);
+                let (cid_bytes, _rest) = rest.split_at(59);
                 let cid_raw = cid_bytes
                     .iter()
                     .take_while(|&&b| b != 0)
@@ -279,17 +558,25 @@ impl FundInstruction {
                 let (&fund_type, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let (amount, rest) = Self::unpack_amount(rest)?;
                 let (mint_amount, rest) = Self::unpack_amount(rest)?;
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
                 Self::InitDepositToken {
                     is_unwrapped_sol,
                     amount,
                     mint_amount,
+                    num_of_tokens,
                     fund_name,
                     fund_type
                 }
             }
             8 => {
-                Self::DeleteFund { }
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::DeleteFund { fund_name, proposal_index, vec_index, leaf_index, merkel_proof }
             }
             9 => {
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
@@ -297,27 +584,25 @@ impl FundInstruction {
                 Self::LeaveFund { fund_name }
             }
             10 => {
+                let (deadline, rest) = Self::unpack_deadline(rest)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
-                Self::InitJoinProposal { fund_name }
+                Self::InitJoinProposal { fund_name, deadline }
             }
             11 => {
                 let (&vote, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
                 Self::JoinVote {
                     vote,
-                    fund_name,
-                    proposal_index
+                    fund_name
                 }
             }
             12 => {
-                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
-                Self::CancelJoinProposal { fund_name, proposal_index }
+                Self::CancelJoinProposal { fund_name }
             }
             13 => {
                 let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (vec_index_bytes, rest) = rest.split_at(2 as usize);
+                let (vec_index_bytes, rest) = rest.split_at(2);
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
                 let vec_index = u16::from_le_bytes(vec_index_bytes.try_into().expect("Invalid Vec Index"));
 
@@ -325,11 +610,11 @@ impl FundInstruction {
             }
             14 => {
                 let (&refund_type, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (new_size_bytes, rest) = rest.split_at(4 as usize);
+                let (new_size, rest) = Self::unpack_expected(rest)?;
+                let (deadline, rest) = Self::unpack_deadline(rest)?;
                 let fund_name = std::str::from_utf8(rest).map_err(|_| ProgramError::InvalidInstructionData)?.to_string();
-                let new_size = u32::from_le_bytes(new_size_bytes.try_into().expect("Invalid New Size"));
 
-                Self::InitIncrementProposal { fund_name, new_size, refund_type }
+                Self::InitIncrementProposal { fund_name, new_size, refund_type, deadline }
             }
             15 => {
                 let (&vote, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
@@ -351,12 +636,19 @@ impl FundInstruction {
             18 => {
                 let (&add_members_later, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let (&num_of_members, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (&max_num_members, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
-                let (tag_bytes, rest) = rest.split_at(4 as usize);
+                let (max_num_members, rest) = Self::unpack_expected(rest)?;
+                let (tag_bytes, rest) = rest.split_at(4);
+                let (expected_members, rest) = Self::unpack_expected(rest)?;
+                let (&symbol_len, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                if rest.len() < symbol_len as usize {
+                    return Err(FundError::InstructionUnpackError.into());
+                }
+                let (symbol_bytes, rest) = rest.split_at(symbol_len as usize);
+                let governance_symbol = std::str::from_utf8(symbol_bytes).map_err(|_| FundError::InstructionUnpackError)?.to_string();
                 let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
                 let tags = u32::from_be_bytes(tag_bytes.try_into().expect("Invalid tags"));
 
-                Self::InitLightFundAccount { fund_name, num_of_members, max_num_members, tags, add_members_later }
+                Self::InitLightFundAccount { fund_name, num_of_members, max_num_members, tags, add_members_later, expected_members, governance_symbol }
             }
             19 => {
                 let (&response, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
@@ -372,12 +664,14 @@ impl FundInstruction {
             }
             21 => {
                 let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_stakes, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_positions, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let (&task, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
                 let (percent_bytes, rest) = rest.split_at(8);
                 let stake_percent = u64::from_le_bytes(percent_bytes.try_into().expect("Wrong Stake Percent"));
                 let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
 
-                Self::WithdrawOrLeaveFromLightFund { fund_name, task, stake_percent, num_of_tokens }
+                Self::WithdrawOrLeaveFromLightFund { fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions }
             }
             22 => {
                 let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
@@ -387,6 +681,296 @@ impl FundInstruction {
 
                 Self::SetExecuting { proposal_index, vec_index, fund_name }
             }
+            23 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&order_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&comparison, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (threshold_price, rest) = Self::unpack_amount(rest)?;
+                let (expiry, rest) = Self::unpack_deadline(rest)?;
+                let (feed_id, rest) = Self::unpack_hash(rest)?;
+                let (swap_leaf, rest) = Self::unpack_hash(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CreateConditionalOrder {
+                    fund_name,
+                    proposal_index,
+                    vec_index,
+                    order_index,
+                    comparison,
+                    threshold_price,
+                    expiry,
+                    feed_id,
+                    swap_leaf,
+                    merkel_proof
+                }
+            }
+            24 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&order_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (amount, rest) = Self::unpack_amount(rest)?;
+                let (slippage, rest) = Self::unpack_u16(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::TriggerConditionalOrder { fund_name, proposal_index, vec_index, order_index, amount, slippage }
+            }
+            25 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&stake_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (amount, rest) = Self::unpack_amount(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::DelegateVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, amount, merkel_proof }
+            }
+            26 | 27 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&stake_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                if *tag == 26 {
+                    Self::DeactivateVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof }
+                } else {
+                    Self::WithdrawVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof }
+                }
+            }
+            28 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&position_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&protocol, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (amount, rest) = Self::unpack_amount(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::DepositToPosition { fund_name, proposal_index, vec_index, leaf_index, position_index, protocol, amount, merkel_proof }
+            }
+            29 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&position_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (amount, rest) = Self::unpack_amount(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::WithdrawFromPosition { fund_name, proposal_index, vec_index, leaf_index, position_index, amount, merkel_proof }
+            }
+            30 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (recipient, rest) = Self::unpack_hash(rest)?;
+                let (management_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (performance_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (&payout_mode, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_feeds, mut rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let mut price_feeds: Vec<PriceFeed> = vec![];
+                for _i in 0..num_of_feeds {
+                    let (mint, next) = Self::unpack_hash(rest)?;
+                    let (feed_id, next) = Self::unpack_hash(next)?;
+                    price_feeds.push(PriceFeed { mint: mint.into(), feed_id });
+                    rest = next;
+                }
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::SetFeeConfig {
+                    fund_name,
+                    proposal_index,
+                    vec_index,
+                    leaf_index,
+                    recipient,
+                    management_fee_bps,
+                    performance_fee_bps,
+                    payout_mode,
+                    price_feeds,
+                    merkel_proof
+                }
+            }
+            31 => {
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CrystallizeFees { fund_name, num_of_tokens }
+            }
+            32 => {
+                let (multisig, rest) = Self::unpack_hash(rest)?;
+                let (deposit_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (withdraw_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (swap_fee_bps, _rest) = Self::unpack_u16(rest)?;
+
+                Self::InitProtocolConfig { multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps }
+            }
+            33 => {
+                let (new_admin, rest) = Self::unpack_hash(rest)?;
+                let (multisig, rest) = Self::unpack_hash(rest)?;
+                let (deposit_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (withdraw_fee_bps, rest) = Self::unpack_u16(rest)?;
+                let (swap_fee_bps, _rest) = Self::unpack_u16(rest)?;
+
+                Self::UpdateProtocolConfig { new_admin, multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps }
+            }
+            34 => {
+                let (amount, _rest) = Self::unpack_amount(rest)?;
+
+                Self::WithdrawProtocolFees { amount }
+            }
+            35 => {
+                let (deadline, rest) = Self::unpack_deadline(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::InitRemoveMemberProposal { fund_name, deadline }
+            }
+            36 => {
+                let (&vote, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::VoteOnRemoveMember { fund_name, vote }
+            }
+            37 => {
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_stakes, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_positions, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::ExecuteRemoveMember { fund_name, num_of_tokens, num_of_stakes, num_of_positions }
+            }
+            38 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (member, rest) = Self::unpack_hash(rest)?;
+                let (&roles, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::SetMemberRoles { fund_name, proposal_index, vec_index, leaf_index, member, roles, merkel_proof }
+            }
+            39 => {
+                let (&task, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (stake_percent, rest) = Self::unpack_amount(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::RequestWithdrawal { fund_name, task, stake_percent }
+            }
+            40 => {
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CancelWithdrawalRequest { fund_name }
+            }
+            41 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (lockup_period, rest) = Self::unpack_deadline(rest)?;
+                let (notice_period, rest) = Self::unpack_deadline(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::SetLiquidityTerms { fund_name, proposal_index, vec_index, leaf_index, lockup_period, notice_period, merkel_proof }
+            }
+            42 => {
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_stakes, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_positions, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&task, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (stake_percent, rest) = Self::unpack_amount(rest)?;
+                let (&target_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&tick_arrays_per_swap, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (min_amounts_out, rest) = Self::unpack_amounts(rest, num_of_tokens)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::WithdrawSingleAssetFromLightFund { fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions, target_index, tick_arrays_per_swap, min_amounts_out }
+            }
+            43 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&share_transfer_policy, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::SetShareTransferPolicy { fund_name, proposal_index, vec_index, leaf_index, share_transfer_policy, merkel_proof }
+            }
+            44 => {
+                let (&num_of_aggregators, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_remove_proposals, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_join_proposals, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_orders, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_positions, _rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+
+                Self::MigrateMember { num_of_aggregators, num_of_remove_proposals, num_of_join_proposals, num_of_orders, num_of_positions }
+            }
+            45 => {
+                let (&num_of_members, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::DistributeFundAssets { fund_name, num_of_members, num_of_tokens }
+            }
+            46 => {
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_join_proposals, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_remove_proposals, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_requests, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (&num_of_orders, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CloseFund { fund_name, num_of_tokens, num_of_join_proposals, num_of_remove_proposals, num_of_requests, num_of_orders }
+            }
+            47 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CloseProposal { fund_name, proposal_index, vec_index }
+            }
+            48 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::FinalizeProposal { fund_name, proposal_index, vec_index }
+            }
+            49 => {
+                let (&proposal_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (vec_index, rest) = Self::unpack_u16(rest)?;
+                let (&leaf_index, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (actions, rest) = Self::unpack_config_actions(rest)?;
+                let (merkel_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::ExecuteConfigProposal { fund_name, proposal_index, vec_index, leaf_index, actions, merkel_proof }
+            }
+            50 => {
+                let (&is_unwrapped_sol, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (amount, rest) = Self::unpack_amount(rest)?;
+                let (mint_amount, rest) = Self::unpack_amount(rest)?;
+                let (&num_of_tokens, rest) = rest.split_first().ok_or(FundError::InstructionUnpackError)?;
+                let (allowlist_proof, rest) = Self::unpack_proof(rest)?;
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::JoinPublicFund { is_unwrapped_sol, amount, mint_amount, num_of_tokens, fund_name, allowlist_proof }
+            }
+            51 => {
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CloseRemoveMemberProposal { fund_name }
+            }
+            52 => {
+                let fund_name = std::str::from_utf8(rest).map_err(|_| FundError::InstructionUnpackError)?.to_string();
+
+                Self::CloseIncrementProposal { fund_name }
+            }
             _ => {
                 return Err(FundError::InstructionUnpackError.into());
             }
@@ -403,16 +987,56 @@ impl FundInstruction {
     }
 
     fn unpack_expected(input: &[u8]) -> Result<(u32, &[u8]), ProgramError> {
-        if input.len() < (4 as usize) {
+        if input.len() < 4 {
             return Err(FundError::InstructionUnpackError.into());
         }
 
-        let (expected_bytes, rest) = input.split_at(4 as usize);
+        let (expected_bytes, rest) = input.split_at(4);
         let expected_members = u32::from_le_bytes(expected_bytes.try_into().expect("Invalid members length"));
 
         Ok((expected_members, rest))
     }
 
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            return Err(FundError::InstructionUnpackError.into());
+        }
+        let (value_bytes, rest) = input.split_at(2);
+        let value = u16::from_le_bytes(value_bytes.try_into().expect("Invalid u16 length"));
+
+        Ok((value, rest))
+    }
+
+    fn unpack_hash(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        if input.len() < 32 {
+            return Err(FundError::InstructionUnpackError.into());
+        }
+        let (hash_bytes, rest) = input.split_at(32);
+
+        Ok((hash_bytes.try_into().expect("Invalid hash length"), rest))
+    }
+
+    fn unpack_proof(input: &[u8]) -> Result<(Vec<[u8; 32]>, &[u8]), ProgramError> {
+        let (&num_of_proofs, rest) = input.split_first().ok_or(FundError::InstructionUnpackError)?;
+        let mut merkel_proof: Vec<[u8; 32]> = Vec::new();
+        let mut merkel_data = rest;
+        for _i in 0..num_of_proofs {
+            let (proof_hash, rest) = Self::unpack_hash(merkel_data)?;
+            merkel_proof.push(proof_hash);
+            merkel_data = rest;
+        }
+
+        Ok((merkel_proof, merkel_data))
+    }
+
+    // Config actions are borsh encoded, a u32 count followed by the actions
+    fn unpack_config_actions(input: &[u8]) -> Result<(Vec<ConfigAction>, &[u8]), ProgramError> {
+        let mut rest = input;
+        let actions = Vec::<ConfigAction>::deserialize(&mut rest).map_err(|_| FundError::InstructionUnpackError)?;
+
+        Ok((actions, rest))
+    }
+
     fn unpack_amount(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         if input.len() < BYTE_SIZE_8 {
             return Err(FundError::InstructionUnpackError.into());
@@ -424,21 +1048,21 @@ impl FundInstruction {
         Ok((amount, rest))
     }
 
-    // fn unpack_amounts(input: &[u8], num_of_swaps: u8) -> Result<(Vec<u64>, &[u8]), ProgramError> {
-    //     if input.len() < BYTE_SIZE_8*(num_of_swaps as usize) {
-    //         return Err(FundError::InstructionUnpackError.into());
-    //     }
+    fn unpack_amounts(input: &[u8], num_of_amounts: u8) -> Result<(Vec<u64>, &[u8]), ProgramError> {
+        if input.len() < BYTE_SIZE_8*(num_of_amounts as usize) {
+            return Err(FundError::InstructionUnpackError.into());
+        }
 
-    //     let mut amounts: Vec<u64> = Vec::new();
-    //     let mut input_slice = input;
-    //     for _i in 0..num_of_swaps {
-    //         let (amount, rest) = Self::unpack_amount(input_slice)?;
-    //         amounts.push(amount);
-    //         input_slice = rest;
-    //     }
+        let mut amounts: Vec<u64> = Vec::new();
+        let mut input_slice = input;
+        for _i in 0..num_of_amounts {
+            let (amount, rest) = Self::unpack_amount(input_slice)?;
+            amounts.push(amount);
+            input_slice = rest;
+        }
 
-    //     Ok((amounts, input_slice))
-    // }
+        Ok((amounts, input_slice))
+    }
 
     // fn unpack_slippage(input: &[u8], num_of_swaps: u8) -> Result<(Vec<u16>, &[u8]), ProgramError> {
     //     if input.len() < 2*(num_of_swaps as usize) {
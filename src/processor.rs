@@ -1,36 +1,80 @@
-use std::io::Write;
-use std::str::FromStr;
 use std::vec;
 // use sha2::{Digest, Sha256};
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::instruction::{Instruction, AccountMeta};
-use solana_program::{pubkey, hash::hashv};
+use bytemuck::Zeroable;
+use solana_program::pubkey;
+use solana_program::log::sol_log_data;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo}, clock::Clock, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_pack::Pack, pubkey::Pubkey, system_instruction, sysvar::{rent::Rent, Sysvar}
+    account_info::{next_account_info, AccountInfo}, clock::Clock, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, system_instruction, sysvar::{rent::Rent, Sysvar}
+};
+use solana_program::stake::{
+    instruction as stake_instruction,
+    state::{Authorized, Lockup, StakeAuthorize, StakeStateV2},
+    tools::get_minimum_delegation,
 };
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 use spl_token::state::Account as TokenAccount;
 use spl_associated_token_account::instruction::create_associated_token_account;
-// use spl_token_2022::extension::{
-//     ExtensionType,
-//     metadata_pointer,
-// };
+use spl_token_2022::extension::{transfer_hook::TransferHookAccount, BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+// use spl_token_2022::extension::metadata_pointer;
 // use spl_token_metadata_interface;
-// use spl_token_2022::state::Mint;
-use crate::state::{IncrementProposalAccount, LightFundAccount, MerkleRoot, UserSpecific};
+use spl_token_2022::state::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::{
+    collect_extra_account_metas_signer_seeds, get_extra_account_metas_address_and_bump_seed,
+    instruction::{ExecuteInstruction, TransferHookInstruction}
+};
+use crate::state::{ConditionalOrder, ConfigAction, FeeAccount, FundMembership, IncrementProposalAccount, JoinProposal, LightFundAccount, MerkleRoot, Position, PriceFeed, ProtocolConfig, Holding, RemoveMemberProposal, VaultHoldings, WithdrawalRequest, ProposalArchive, ProposalState, DEFAULT_MEMBER_ROLES, PROPOSALS_PER_AGGREGATOR, ROLE_ADMIN, ROLE_INVITER, ROLE_OBSERVER, ROLE_PROPOSER, ROLE_VOTER};
+use crate::utils::{
+    collect_protocol_fee, conditional_order_leaf, consume_approved_action, fee_config_leaf, has_role, lending_deposit_liquidity,
+    lending_redeem_collateral, liquidity_terms_leaf, load_protocol_config, member_roles_leaf, normalize_price, position_leaf, raydium_clmm_swap, read_oracle_price, slippage_min_amount_out, stake_leaf, stake_pool_deposit_sol, stake_pool_withdraw_sol, share_policy_leaf, delete_fund_leaf, supported_mint_decimals, swap_leaf,
+    token_value, transfer_tokens, verify_merkle_proof, burn_governance_tokens, mint_governance_tokens, check_stake_pool_accounts, check_lending_reserve_accounts, GOVERNANCE_DECIMALS, voting_rules, vote_weight, votes_decisive, votes_passed, check_executor, check_mint_whitelisted, config_actions_leaf, allowlist_leaf, verify_allowlist_proof, CONFIG_TIMELOCK, SHARE_PRICE_SCALE, crystallization_fees, fee_shares, shares_for_value, withdrawal_claimable_at, creation_cost_share,
+    load_aggregator, load_proposal, load_proposal_mut, mark_voted, proposal_slot_mut, proposal_slots, record_executed_action, voters_bitmap_space, finalize_proposal, proposal_closable, proposal_state, transition_proposal, AGGREGATOR_HEADER_SPACE, PROPOSAL_SPACE, PROPOSAL_EXPIRY, PEERFUNDS_OPERATOR
+};
 use crate::{
     errors::FundError,
     instruction::FundInstruction,
-    state::{FundAccount, Proposal, ProposalAggregatorAccount, UserAccount, VaultAccount}
+    state::{Proposal, ProposalAggregatorAccount, UserAccount, VaultAccount}
 };
 
 pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
 
+// Hard caps on what governance can set as fund fees
+pub const MAX_MANAGEMENT_FEE_BPS: u16 = 500;
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 3_000;
+
+// Voting config of new funds, a quorum of half the voting power and a simple majority
+pub const DEFAULT_QUORUM_BPS: u16 = 5_000;
+pub const DEFAULT_APPROVAL_BPS: u16 = 5_000;
+
+// Cap on a fund's whitelisted mints, which live in the fund account
+pub const MAX_WHITELISTED_MINTS: usize = 32;
+
+// Hard cap on the entry fee a public fund charges on first deposits
+pub const MAX_ENTRY_FEE_BPS: u16 = 1_000;
+
+// Hard caps on the protocol fees the admin can set
+pub const MAX_PROTOCOL_DEPOSIT_FEE_BPS: u16 = 100;
+pub const MAX_PROTOCOL_WITHDRAW_FEE_BPS: u16 = 100;
+pub const MAX_PROTOCOL_SWAP_FEE_BPS: u16 = 50;
+
+// Size of a FundMembership PDA
+pub const MEMBERSHIP_SPACE: usize = 142;
+
+// Room a (wallet, vote) entry takes in the voters list of a proposal
+pub const VOTE_SPACE: usize = 33;
+
 pub fn process_instruction<'a>(
     program_id: &Pubkey,
     accounts: &'a[AccountInfo<'a>],
     data: &[u8]
 ) -> ProgramResult {
+    // Token-2022 calls back into the program on every governance token transfer
+    if let Ok(TransferHookInstruction::Execute { amount }) = TransferHookInstruction::unpack(data) {
+        msg!("Instruction: Transfer Hook Execute");
+        return process_share_transfer(program_id, accounts, amount);
+    }
+
     let instruction = FundInstruction::unpack(data)?;
     match instruction {
 
@@ -44,19 +88,24 @@ pub fn process_instruction<'a>(
             process_init_user_account(program_id, accounts, cid)
         }
 
-        // FundInstruction::AddFundMember { fund_name, vec_index } => {
-        //     msg!("Instruction: Add Fund Member");
-        //     process_add_member(program_id, accounts, fund_name, vec_index)
-        // }
+        FundInstruction::AddFundMember { fund_name } => {
+            msg!("Instruction: Add Fund Member");
+            process_add_member(program_id, accounts, fund_name)
+        }
 
-        FundInstruction::InitDepositToken { is_unwrapped_sol, amount, mint_amount, fund_name, fund_type } => {
+        FundInstruction::InitDepositToken { is_unwrapped_sol, amount, mint_amount, num_of_tokens, fund_name, fund_type } => {
             msg!("Instruction: Init Deposit Token");
-            process_init_deposit_token(program_id, accounts,is_unwrapped_sol, amount, mint_amount, fund_name, fund_type)
+            process_init_deposit_token(program_id, accounts,is_unwrapped_sol, amount, mint_amount, num_of_tokens, fund_name, fund_type, None)
+        }
+
+        FundInstruction::JoinPublicFund { is_unwrapped_sol, amount, mint_amount, num_of_tokens, fund_name, allowlist_proof } => {
+            msg!("Instruction: Join Public Fund");
+            process_init_deposit_token(program_id, accounts, is_unwrapped_sol, amount, mint_amount, num_of_tokens, fund_name, 0, Some(allowlist_proof))
         }
 
-        FundInstruction::InitProposalInvestment { cid, deadline, fund_name, merkel_bytes} => {
+        FundInstruction::InitProposalInvestment { cid, deadline, fund_name, merkel_bytes, actions} => {
             msg!("Instruction: Init Proposal");
-            process_init_investment_proposal(program_id, accounts, fund_name, cid, deadline, merkel_bytes)
+            process_init_investment_proposal(program_id, accounts, fund_name, cid, deadline, merkel_bytes, actions)
         }
 
         FundInstruction::Vote {vote, proposal_index, vec_index, fund_name} => {
@@ -64,39 +113,39 @@ pub fn process_instruction<'a>(
             process_vote_on_proposal(program_id, accounts, vote, proposal_index, vec_index, fund_name)
         }
 
-        // FundInstruction::InitRentAccount {  } => {
-        //     msg!("Instruction: Init Rent Account");
-        //     process_init_rent_account(program_id, accounts)
-        // }
+        FundInstruction::InitRentAccount {  } => {
+            msg!("Instruction: Init Rent Account");
+            process_init_rent_account(program_id, accounts)
+        }
 
         FundInstruction::ExecuteProposalInvestment { fund_name, proposal_index, vec_index, swap_index, no_of_swaps, merkel_proof, amount, slippage} => {
             msg!("Instruction: Execute Proposal");
             process_execute_proposal(program_id, accounts, fund_name, proposal_index, vec_index, swap_index, no_of_swaps, merkel_proof, amount, slippage)
         }
 
-        // FundInstruction::InitJoinProposal { fund_name } => {
-        //     msg!("Instruction: Init Join Proposal");
-        //     process_init_join_proposal(program_id, accounts, fund_name)
-        // }
+        FundInstruction::InitJoinProposal { fund_name, deadline } => {
+            msg!("Instruction: Init Join Proposal");
+            process_init_join_proposal(program_id, accounts, fund_name, deadline)
+        }
 
-        // FundInstruction::JoinVote { vote, fund_name, proposal_index } => {
-        //     msg!("Instruction: Voting on Join Proposal");
-        //     process_vote_on_join_proposal(program_id, accounts, vote, fund_name, proposal_index)
-        // }
+        FundInstruction::JoinVote { vote, fund_name } => {
+            msg!("Instruction: Voting on Join Proposal");
+            process_vote_on_join_proposal(program_id, accounts, vote, fund_name)
+        }
 
-        // FundInstruction::CancelJoinProposal { fund_name, proposal_index } => {
-        //     msg!("Instruction: Cancel Join Proposal");
-        //     process_cancel_join_proposal(program_id, accounts, fund_name, proposal_index)
-        // }
+        FundInstruction::CancelJoinProposal { fund_name } => {
+            msg!("Instruction: Cancel Join Proposal");
+            process_cancel_join_proposal(program_id, accounts, fund_name)
+        }
 
         FundInstruction::CancelInvestmentProposal { fund_name, proposal_index, vec_index } => {
             msg!("Instruction: Cancel Investment Proposal");
             process_cancel_investment_proposal(program_id, accounts, fund_name, proposal_index, vec_index)
         }
 
-        FundInstruction::InitIncrementProposal { fund_name, new_size, refund_type } => {
+        FundInstruction::InitIncrementProposal { fund_name, new_size, refund_type, deadline } => {
             msg!("Instruction: Init Increment Proposal");
-            process_init_increment_proposal(program_id, accounts, fund_name, new_size, refund_type)
+            process_init_increment_proposal(program_id, accounts, fund_name, new_size, refund_type, deadline)
         }
 
         FundInstruction::VoteOnIncrement { fund_name, vote } => {
@@ -114,9 +163,9 @@ pub fn process_instruction<'a>(
             process_toggle_refund_type(program_id, accounts, fund_name, refund_type)
         }
 
-        FundInstruction::InitLightFundAccount { fund_name, num_of_members, max_num_members, tags, add_members_later } => {
+        FundInstruction::InitLightFundAccount { fund_name, num_of_members, max_num_members, tags, add_members_later, expected_members, governance_symbol } => {
             msg!("Instruction: Init Light Fund Account");
-            process_init_light_fund(program_id, accounts, fund_name, num_of_members, tags, add_members_later, max_num_members)
+            process_init_light_fund(program_id, accounts, fund_name, num_of_members, tags, add_members_later, max_num_members, expected_members, governance_symbol)
         }
 
         FundInstruction::HandleInvition { fund_name, response, inviter_exists } => {
@@ -129,9 +178,9 @@ pub fn process_instruction<'a>(
             process_invite_to_fund(program_id, accounts, fund_name)
         }
 
-        FundInstruction::WithdrawOrLeaveFromLightFund { fund_name, task, stake_percent, num_of_tokens } => {
+        FundInstruction::WithdrawOrLeaveFromLightFund { fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions } => {
             msg!("Instruction: Withdraw from Light Fund");
-            process_withdraw_or_leave_from_light_fund(program_id, accounts, fund_name, task, stake_percent, num_of_tokens)
+            process_withdraw_or_leave_from_light_fund(program_id, accounts, fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions, None)
         }
 
         FundInstruction::SetExecuting { proposal_index, vec_index, fund_name } => {
@@ -139,10 +188,160 @@ pub fn process_instruction<'a>(
             process_set_executing(program_id, accounts, proposal_index, vec_index, fund_name)
         }
 
+        FundInstruction::CreateConditionalOrder { fund_name, proposal_index, vec_index, order_index, comparison, threshold_price, expiry, feed_id, swap_leaf, merkel_proof } => {
+            msg!("Instruction: Create Conditional Order");
+            process_create_conditional_order(program_id, accounts, fund_name, proposal_index, vec_index, order_index, comparison, threshold_price, expiry, feed_id, swap_leaf, merkel_proof)
+        }
+
+        FundInstruction::TriggerConditionalOrder { fund_name, proposal_index, vec_index, order_index, amount, slippage } => {
+            msg!("Instruction: Trigger Conditional Order");
+            process_trigger_conditional_order(program_id, accounts, fund_name, proposal_index, vec_index, order_index, amount, slippage)
+        }
+
+        FundInstruction::DelegateVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, amount, merkel_proof } => {
+            msg!("Instruction: Delegate Vault Stake");
+            process_delegate_vault_stake(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, stake_index, amount, merkel_proof)
+        }
+
+        FundInstruction::DeactivateVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof } => {
+            msg!("Instruction: Deactivate Vault Stake");
+            process_deactivate_vault_stake(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof)
+        }
+
+        FundInstruction::WithdrawVaultStake { fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof } => {
+            msg!("Instruction: Withdraw Vault Stake");
+            process_withdraw_vault_stake(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, stake_index, merkel_proof)
+        }
+
+        FundInstruction::DepositToPosition { fund_name, proposal_index, vec_index, leaf_index, position_index, protocol, amount, merkel_proof } => {
+            msg!("Instruction: Deposit To Position");
+            process_deposit_to_position(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, position_index, protocol, amount, merkel_proof)
+        }
+
+        FundInstruction::WithdrawFromPosition { fund_name, proposal_index, vec_index, leaf_index, position_index, amount, merkel_proof } => {
+            msg!("Instruction: Withdraw From Position");
+            process_withdraw_from_position(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, position_index, amount, merkel_proof)
+        }
+
+        FundInstruction::SetFeeConfig { fund_name, proposal_index, vec_index, leaf_index, recipient, management_fee_bps, performance_fee_bps, payout_mode, price_feeds, merkel_proof } => {
+            msg!("Instruction: Set Fee Config");
+            process_set_fee_config(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, recipient, management_fee_bps, performance_fee_bps, payout_mode, price_feeds, merkel_proof)
+        }
+
+        FundInstruction::CrystallizeFees { fund_name, num_of_tokens } => {
+            msg!("Instruction: Crystallize Fees");
+            process_crystallize_fees(program_id, accounts, fund_name, num_of_tokens)
+        }
+
+        FundInstruction::InitProtocolConfig { multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps } => {
+            msg!("Instruction: Init Protocol Config");
+            process_init_protocol_config(program_id, accounts, multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps)
+        }
+
+        FundInstruction::UpdateProtocolConfig { new_admin, multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps } => {
+            msg!("Instruction: Update Protocol Config");
+            process_update_protocol_config(program_id, accounts, new_admin, multisig, deposit_fee_bps, withdraw_fee_bps, swap_fee_bps)
+        }
+
+        FundInstruction::WithdrawProtocolFees { amount } => {
+            msg!("Instruction: Withdraw Protocol Fees");
+            process_withdraw_protocol_fees(program_id, accounts, amount)
+        }
+
+        FundInstruction::InitRemoveMemberProposal { fund_name, deadline } => {
+            msg!("Instruction: Init Remove Member Proposal");
+            process_init_remove_member_proposal(program_id, accounts, fund_name, deadline)
+        }
+
+        FundInstruction::VoteOnRemoveMember { fund_name, vote } => {
+            msg!("Instruction: Vote On Remove Member");
+            process_vote_on_remove_member(program_id, accounts, fund_name, vote)
+        }
+
+        FundInstruction::ExecuteRemoveMember { fund_name, num_of_tokens, num_of_stakes, num_of_positions } => {
+            msg!("Instruction: Execute Remove Member");
+            process_execute_remove_member(program_id, accounts, fund_name, num_of_tokens, num_of_stakes, num_of_positions)
+        }
+
+        FundInstruction::SetMemberRoles { fund_name, proposal_index, vec_index, leaf_index, member, roles, merkel_proof } => {
+            msg!("Instruction: Set Member Roles");
+            process_set_member_roles(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, member, roles, merkel_proof)
+        }
+
+        FundInstruction::RequestWithdrawal { fund_name, task, stake_percent } => {
+            msg!("Instruction: Request Withdrawal");
+            process_request_withdrawal(program_id, accounts, fund_name, task, stake_percent)
+        }
+
+        FundInstruction::CancelWithdrawalRequest { fund_name } => {
+            msg!("Instruction: Cancel Withdrawal Request");
+            process_cancel_withdrawal_request(program_id, accounts, fund_name)
+        }
+
+        FundInstruction::SetLiquidityTerms { fund_name, proposal_index, vec_index, leaf_index, lockup_period, notice_period, merkel_proof } => {
+            msg!("Instruction: Set Liquidity Terms");
+            process_set_liquidity_terms(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, lockup_period, notice_period, merkel_proof)
+        }
+
+        FundInstruction::WithdrawSingleAssetFromLightFund { fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions, target_index, tick_arrays_per_swap, min_amounts_out } => {
+            msg!("Instruction: Withdraw Single Asset from Light Fund");
+            process_withdraw_or_leave_from_light_fund(program_id, accounts, fund_name, task, stake_percent, num_of_tokens, num_of_stakes, num_of_positions, Some((target_index, tick_arrays_per_swap, min_amounts_out)))
+        }
+
+        FundInstruction::SetShareTransferPolicy { fund_name, proposal_index, vec_index, leaf_index, share_transfer_policy, merkel_proof } => {
+            msg!("Instruction: Set Share Transfer Policy");
+            process_set_share_transfer_policy(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, share_transfer_policy, merkel_proof)
+        }
+
+        FundInstruction::MigrateMember { num_of_aggregators, num_of_remove_proposals, num_of_join_proposals, num_of_orders, num_of_positions } => {
+            msg!("Instruction: Migrate Member");
+            process_migrate_member(program_id, accounts, num_of_aggregators, num_of_remove_proposals, num_of_join_proposals, num_of_orders, num_of_positions)
+        }
+
+        FundInstruction::DeleteFund { fund_name, proposal_index, vec_index, leaf_index, merkel_proof } => {
+            msg!("Instruction: Delete Fund");
+            process_delete_fund(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, merkel_proof)
+        }
+
+        FundInstruction::DistributeFundAssets { fund_name, num_of_members, num_of_tokens } => {
+            msg!("Instruction: Distribute Fund Assets");
+            process_distribute_fund_assets(program_id, accounts, fund_name, num_of_members, num_of_tokens)
+        }
+
+        FundInstruction::CloseFund { fund_name, num_of_tokens, num_of_join_proposals, num_of_remove_proposals, num_of_requests, num_of_orders } => {
+            msg!("Instruction: Close Fund");
+            process_close_fund(program_id, accounts, fund_name, num_of_tokens, num_of_join_proposals, num_of_remove_proposals, num_of_requests, num_of_orders)
+        }
+
+        FundInstruction::FinalizeProposal { fund_name, proposal_index, vec_index } => {
+            msg!("Instruction: Finalize Proposal");
+            process_finalize_proposal(program_id, accounts, fund_name, proposal_index, vec_index)
+        }
+        FundInstruction::ExecuteConfigProposal { fund_name, proposal_index, vec_index, leaf_index, actions, merkel_proof } => {
+            msg!("Instruction: Execute Config Proposal");
+            process_execute_config_proposal(program_id, accounts, fund_name, proposal_index, vec_index, leaf_index, actions, merkel_proof)
+        }
+
+        FundInstruction::CloseProposal { fund_name, proposal_index, vec_index } => {
+            msg!("Instruction: Close Proposal");
+            process_close_proposal(program_id, accounts, fund_name, proposal_index, vec_index)
+        }
+
+        FundInstruction::CloseRemoveMemberProposal { fund_name } => {
+            msg!("Instruction: Close Remove Member Proposal");
+            process_close_remove_member_proposal(program_id, accounts, fund_name)
+        }
+
+        FundInstruction::CloseIncrementProposal { fund_name } => {
+            msg!("Instruction: Close Increment Proposal");
+            process_close_increment_proposal(program_id, accounts, fund_name)
+        }
+
         _ => Err(FundError::InvalidInstruction.into()),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_init_light_fund(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -150,7 +349,9 @@ fn process_init_light_fund(
     num_of_members: u8,
     tags: u32,
     add_members_later: u8,
-    max_num_members: u8,
+    max_num_members: u32,
+    expected_members: u32,
+    governance_symbol: String,
 ) -> ProgramResult {
     let current_time = Clock::get()?.unix_timestamp;
 
@@ -161,6 +362,7 @@ fn process_init_light_fund(
     let system_program_info = next_account_info(accounts_iter)?; // System Program ...........................
     let fund_account_info = next_account_info(accounts_iter)?; // Fund PDA Account ...........................
     let proposal_aggregator_info = next_account_info(accounts_iter)?; // first proposal aggregator ...........
+    let creator_membership_info = next_account_info(accounts_iter)?; // Creator's Membership PDA .............
 
     if !creator_wallet_info.is_signer {
         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
@@ -169,7 +371,7 @@ fn process_init_light_fund(
 
     msg!("{}", fund_name);
 
-    let current_index = 0 as u8;
+    let current_index = 0;
     let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
     let (creator_pda, _creator_bump) = Pubkey::find_program_address(&[b"user", creator_wallet_info.key.as_ref()], program_id);
     let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_account_info.key.as_ref()], program_id);
@@ -193,10 +395,16 @@ fn process_init_light_fund(
         return Err(FundError::InvalidFundDetails.into());
     }
 
+    // Creator counts on `expected_members` joiners to share these creation costs (0 opts out)
+    if expected_members >= max_num_members {
+        return Err(FundError::InvalidMemberInfo.into());
+    }
+
     let rent = Rent::get()?;
-    let fund_space = 128 as usize;
-    let vault_space = 8 as usize;
-    let aggregator_space = 5 as usize;
+    let fund_space = 291;
+    let vault_space = 24;
+    let aggregator_space = AGGREGATOR_HEADER_SPACE;
+    let mut creation_cost = rent.minimum_balance(fund_space) + rent.minimum_balance(vault_space) + rent.minimum_balance(aggregator_space);
     
     invoke_signed(
         &system_instruction::create_account(
@@ -234,13 +442,160 @@ fn process_init_light_fund(
         &[&[b"proposal-aggregator", &[current_index], fund_account_info.key.as_ref(), &[proposal_aggregator_bump]]]
     )?;
 
+    // Optional governance token, members hold their shares in their wallets
+    let mut governance_mint = Pubkey::default();
+    if !governance_symbol.is_empty() {
+        let governance_mint_info = next_account_info(accounts_iter)?; // Governance Mint .........................
+        let metadata_info = next_account_info(accounts_iter)?; // Metaplex metadata of the governance mint .......
+        let extra_account_metas_info = next_account_info(accounts_iter)?; // Transfer hook's extra account metas .
+        let token_program_2022_info = next_account_info(accounts_iter)?; // Token Program (2022) .................
+        let token_metadata_program_info = next_account_info(accounts_iter)?; // Metaplex Token Metadata Program ..
+        let rent_sysvar_info = next_account_info(accounts_iter)?; // Rent Sysvar .................................
+
+        if governance_symbol.len() > mpl_token_metadata::MAX_SYMBOL_LENGTH {
+            return Err(FundError::InvalidFundDetails.into());
+        }
+
+        let (governance_pda, governance_bump) = Pubkey::find_program_address(&[b"governance", fund_pda.as_ref()], program_id);
+        let (metadata_pda, _metadata_bump) = mpl_token_metadata::accounts::Metadata::find_pda(&governance_pda);
+        let (extra_account_metas_pda, extra_account_metas_bump) = get_extra_account_metas_address_and_bump_seed(&governance_pda, program_id);
+        if *governance_mint_info.key != governance_pda ||
+           *metadata_info.key != metadata_pda ||
+           *extra_account_metas_info.key != extra_account_metas_pda ||
+           *token_program_2022_info.key != spl_token_2022::id() ||
+           *token_metadata_program_info.key != TOKEN_METADATA_PROGRAM_ID {
+            return Err(FundError::InvalidAccountData.into());
+        }
+
+        // Transfers go through the fund's share policy, the fund burns shares as permanent delegate
+        let extensions = vec![ExtensionType::TransferHook, ExtensionType::PermanentDelegate];
+        let mint_space = ExtensionType::try_calculate_account_len::<Mint>(&extensions)?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                creator_wallet_info.key,
+                governance_mint_info.key,
+                rent.minimum_balance(mint_space),
+                mint_space as u64,
+                token_program_2022_info.key
+            ),
+            &[creator_wallet_info.clone(), governance_mint_info.clone(), system_program_info.clone()],
+            &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]]
+        )?;
+
+        invoke(
+            &spl_token_2022::extension::transfer_hook::instruction::initialize(
+                token_program_2022_info.key,
+                governance_mint_info.key,
+                Some(*fund_account_info.key),
+                Some(*program_id)
+            )?,
+            &[governance_mint_info.clone(), token_program_2022_info.clone()]
+        )?;
+
+        invoke(
+            &spl_token_2022::instruction::initialize_permanent_delegate(
+                token_program_2022_info.key,
+                governance_mint_info.key,
+                fund_account_info.key
+            )?,
+            &[governance_mint_info.clone(), token_program_2022_info.clone()]
+        )?;
+
+        invoke(
+            &spl_token_2022::instruction::initialize_mint2(
+                token_program_2022_info.key,
+                governance_mint_info.key,
+                fund_account_info.key,
+                Some(fund_account_info.key),
+                GOVERNANCE_DECIMALS
+            )?,
+            &[governance_mint_info.clone(), token_program_2022_info.clone()]
+        )?;
+
+        let create_metadata = mpl_token_metadata::instructions::CreateMetadataAccountV3 {
+            metadata: *metadata_info.key,
+            mint: *governance_mint_info.key,
+            mint_authority: *fund_account_info.key,
+            payer: *creator_wallet_info.key,
+            update_authority: (*fund_account_info.key, true),
+            system_program: *system_program_info.key,
+            rent: Some(*rent_sysvar_info.key),
+        };
+
+        invoke_signed(
+            &create_metadata.instruction(mpl_token_metadata::instructions::CreateMetadataAccountV3InstructionArgs {
+                data: mpl_token_metadata::types::DataV2 {
+                    name: fund_name.clone(),
+                    symbol: governance_symbol.clone(),
+                    uri: String::new(),
+                    seller_fee_basis_points: 0,
+                    creators: None,
+                    collection: None,
+                    uses: None,
+                },
+                is_mutable: true,
+                collection_details: None,
+            }),
+            &[
+                metadata_info.clone(),
+                governance_mint_info.clone(),
+                fund_account_info.clone(),
+                creator_wallet_info.clone(),
+                system_program_info.clone(),
+                rent_sysvar_info.clone(),
+                token_metadata_program_info.clone(),
+            ],
+            &[&[b"light-fund", fund_name.as_bytes(), &[fund_bump]]]
+        )?;
+
+        // Hook gets the fund, the receiver's user account, both wallets' memberships and the rent reserve, owners read from the token accounts
+        let extra_account_metas = vec![
+            ExtraAccountMeta::new_with_pubkey(fund_account_info.key, false, true)?,
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"user".to_vec() }, Seed::AccountData { account_index: 2, data_index: 32, length: 32 }],
+                false,
+                true
+            )?,
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"member".to_vec() }, Seed::AccountKey { index: 5 }, Seed::AccountData { account_index: 0, data_index: 32, length: 32 }],
+                false,
+                true
+            )?,
+            ExtraAccountMeta::new_with_seeds(
+                &[Seed::Literal { bytes: b"member".to_vec() }, Seed::AccountKey { index: 5 }, Seed::AccountData { account_index: 2, data_index: 32, length: 32 }],
+                false,
+                true
+            )?,
+            ExtraAccountMeta::new_with_pubkey(system_program_info.key, false, false)?,
+            ExtraAccountMeta::new_with_seeds(&[Seed::Literal { bytes: b"rent".to_vec() }], false, true)?,
+        ];
+        let extra_account_metas_space = ExtraAccountMetaList::size_of(extra_account_metas.len())?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                creator_wallet_info.key,
+                extra_account_metas_info.key,
+                rent.minimum_balance(extra_account_metas_space),
+                extra_account_metas_space as u64,
+                program_id
+            ),
+            &[creator_wallet_info.clone(), extra_account_metas_info.clone(), system_program_info.clone()],
+            &[&collect_extra_account_metas_signer_seeds(&governance_pda, &[extra_account_metas_bump])]
+        )?;
+
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut extra_account_metas_info.data.borrow_mut(), &extra_account_metas)?;
+
+        creation_cost += governance_mint_info.lamports() + metadata_info.lamports() + extra_account_metas_info.lamports();
+        governance_mint = governance_pda;
+    }
+
     // Converting the fund_name to an array of u8 of fixed size 32
     let bytes = fund_name.as_bytes();
     let mut array = [0u8; 32];
     let len = bytes.len().min(32);
     array[..len].copy_from_slice(&bytes[..len]);
 
-    let members: Vec<(Pubkey, u32)> = vec![(*creator_wallet_info.key, 0 as u32)];
     if num_of_members == 0 && max_num_members < 1 {
         return Err(FundError::InvalidMemberInfo.into());
     }
@@ -248,73 +603,72 @@ fn process_init_light_fund(
         return Err(FundError::InvalidMemberInfo.into());
     }
 
-    let fund_data = LightFundAccount {
+    let mut fund_data = LightFundAccount {
         name: array,
-        fund_type: 0 as u8,
+        fund_type: 0,
         creator_exists: true,
-        total_deposit: 0 as u64,
+        total_deposit: 0,
         vault: *vault_account_info.key,
+        governance_mint,
+        share_transfer_policy: 0,
         current_proposal_index: current_index,
         created_at: current_time,
         tags,
         max_members: max_num_members,
-        members
+        expected_members,
+        is_refunded: expected_members == 0,
+        creation_cost,
+        rent_collected: 0,
+        lockup_period: 0,
+        notice_period: 0,
+        winding_down: false,
+        rent_recipient: Pubkey::default(),
+        creator: *creator_wallet_info.key,
+        member_count: 0,
+        voter_count: 0,
+        next_member_index: 0,
+        privacy: 0,
+        voting_mode: 0,
+        quorum_bps: DEFAULT_QUORUM_BPS,
+        approval_bps: DEFAULT_APPROVAL_BPS,
+        executor_policy: 0,
+        min_first_deposit: 0,
+        allowlist_root: [0u8; 32],
+        entry_fee_bps: 0,
+        whitelisted_mints: vec![],
     };
 
+    let mut creator_membership = pending_membership(fund_account_info.key, creator_wallet_info.key, &Pubkey::default(), 0, current_time);
+    creator_membership.roles = ROLE_ADMIN | DEFAULT_MEMBER_ROLES;
+    admit_member(&mut fund_data, &mut creator_membership, current_time)?;
+    create_membership(program_id, creator_wallet_info, creator_membership_info, system_program_info, &creator_membership)?;
+
     fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
     let vault_data = VaultAccount {
-        last_deposit_time: current_time
+        last_deposit_time: current_time,
+        staked_lamports: 0,
+        stake_accounts: vec![],
+        positions: vec![],
     };
 
     vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
 
-    let proposals: Vec<Proposal> = vec![];
-
     let proposal_data = ProposalAggregatorAccount {
         index: current_index,
-        proposals
+        _padding: [0u8; 7],
+        opener: Pubkey::default(),
     };
 
-    proposal_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+    proposal_aggregator_info.data.borrow_mut().copy_from_slice(bytemuck::bytes_of(&proposal_data));
 
     let mut creator_data = UserAccount::try_from_slice(&creator_account_info.data.borrow())?;
-    creator_data.funds.push(UserSpecific {
-        fund: *fund_account_info.key,
-        fund_type: 0 as u8,
-        governance_token_balance: 0 as u64,
-        is_pending: false,
-        is_eligible: 0 as u8,
-        inviter_index: 0 as u32,
-        join_time: current_time
-    });
-
-    let current_creator_space = creator_account_info.data_len();
-    let new_creator_space = current_creator_space + 55;
-    let current_creator_rent = creator_account_info.lamports();
-    let new_creator_rent = rent.minimum_balance(new_creator_space);
-
-    if new_creator_rent > current_creator_rent {
-        invoke(
-            &system_instruction::transfer(
-                creator_wallet_info.key,
-                creator_account_info.key,
-                new_creator_rent - current_creator_rent
-            ),
-            &[creator_wallet_info.clone(), creator_account_info.clone(), system_program_info.clone()]
-        )?;
-    }
-
-    creator_account_info.realloc(new_creator_space, false)?;
+    creator_data.funds_joined += 1;
     creator_data.serialize(&mut &mut creator_account_info.data.borrow_mut()[..])?;
 
     if add_members_later == 1 && max_num_members >= 1 {
         return Ok(());
-    } else if add_members_later == 1 && max_num_members < 1 {
-        return Err(FundError::InvalidMemberInfo.into());
-    } else if add_members_later == 0 && num_of_members == 0 {
-        return Err(FundError::InvalidMemberInfo.into());
-    } else if add_members_later == 0 && max_num_members != 20 {
+    } else if add_members_later == 1 || num_of_members == 0 || max_num_members != 20 {
         return Err(FundError::InvalidMemberInfo.into());
     }
 
@@ -334,6 +688,13 @@ fn process_init_light_fund(
     }
     let members_pda_pubkey: Vec<Pubkey> = members_pda_info.iter().map(|m| *m.key).collect();
 
+    let memberships_info: Vec<&AccountInfo> = accounts_iter
+        .take(num_of_members as usize)
+        .collect();
+    if memberships_info.len() != num_of_members as usize {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
     for i in 0..num_of_members {
         let (pda, _bump) = Pubkey::find_program_address(&[b"user", members_pubkey[i as usize].as_ref()], program_id);
         if members_pda_pubkey[i as usize] != pda || members_pda_info[i as usize].data_is_empty() {
@@ -341,37 +702,14 @@ fn process_init_light_fund(
         }
     }
 
+    // Creator invites the initial members and pays for their pending memberships
     for i in 0..num_of_members {
         let member_pda_info = members_pda_info[i as usize];
-        let mut pda_data = UserAccount::try_from_slice(&member_pda_info.data.borrow())?;
-        pda_data.funds.push(UserSpecific {
-            fund: *fund_account_info.key,
-            fund_type: 0 as u8,
-            governance_token_balance: 0 as u64,
-            is_pending: true,
-            is_eligible: 1 as u8,
-            inviter_index: 0 as u32,
-            join_time: current_time
-        });
-        
-        let current_user_size = member_pda_info.data_len();
-        let new_user_size = current_user_size + 55;
-        let current_user_rent = member_pda_info.lamports();
-        let new_user_rent = rent.minimum_balance(new_user_size);
-
-        if new_user_rent > current_user_rent {
-            invoke(
-                &system_instruction::transfer(
-                    creator_wallet_info.key,
-                    member_pda_info.key,
-                    new_user_rent - current_user_rent
-                ),
-                &[creator_wallet_info.clone(), member_pda_info.clone(), system_program_info.clone()]
-            )?;
-        }
-
-        member_pda_info.realloc(new_user_size, false)?;
+        let invitation = pending_membership(fund_account_info.key, &members_pubkey[i as usize], creator_wallet_info.key, creator_membership.member_index, current_time);
+        create_membership(program_id, creator_wallet_info, memberships_info[i as usize], system_program_info, &invitation)?;
 
+        let mut pda_data = UserAccount::try_from_slice(&member_pda_info.data.borrow())?;
+        pda_data.pending_invites += 1;
         pda_data.serialize(&mut &mut member_pda_info.data.borrow_mut()[..])?;
     }
 
@@ -391,6 +729,8 @@ fn process_invite_to_fund(
     let joiner_account_info = next_account_info(accounts_iter)?;
     let fund_account_info = next_account_info(accounts_iter)?;
     let system_program_info = next_account_info(accounts_iter)?;
+    let inviter_membership_info = next_account_info(accounts_iter)?;
+    let joiner_membership_info = next_account_info(accounts_iter)?;
 
     if !inviter_wallet_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
@@ -404,61 +744,28 @@ fn process_invite_to_fund(
     }
 
     let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-    let inviter_exists = fund_data
-        .members
-        .iter()
-        .any(|member| member.0 == *inviter_wallet_info.key);
+    let inviter_info = load_member(program_id, inviter_membership_info, fund_account_info.key, inviter_wallet_info.key)?;
 
-    if !inviter_exists {
-        return Err(FundError::NotAFundMember.into());
+    if !has_role(&inviter_info, ROLE_INVITER) {
+        return Err(FundError::MissingPermission.into());
     }
 
-    let inviter_info = fund_data
-        .members
-        .iter()
-        .find(|member| member.0 == *inviter_wallet_info.key)
-        .ok_or(FundError::InvalidAccountData)?;
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
 
-    let inviter_index = inviter_info.1;
+    let inviter_index = inviter_info.member_index;
 
     let mut joiner_data = UserAccount::try_from_slice(&joiner_account_info.data.borrow())?;
-    let already_invited = joiner_data
-        .funds
-        .iter()
-        .any(|user_specific| user_specific.fund == *fund_account_info.key);
-
-    if already_invited {
+    if load_membership(program_id, joiner_membership_info, fund_account_info.key, joiner_wallet_info.key)?.is_some() {
         return Err(FundError::AlreadyInvited.into());
     }
 
-    joiner_data.funds.push(UserSpecific {
-        fund: *fund_account_info.key,
-        fund_type: 0 as u8,
-        governance_token_balance: 0 as u64,
-        is_pending: true,
-        is_eligible: 1 as u8,
-        inviter_index,
-        join_time: current_time
-    });
-
-    let rent = Rent::get()?;
-    let current_joiner_space = joiner_account_info.data_len();
-    let new_joiner_space = current_joiner_space + 55;
-    let current_joiner_rent = joiner_account_info.lamports();
-    let new_joiner_rent = rent.minimum_balance(new_joiner_space);
-
-    if new_joiner_rent > current_joiner_rent {
-        invoke(
-            &system_instruction::transfer(
-                inviter_wallet_info.key,
-                joiner_account_info.key,
-                new_joiner_rent - current_joiner_rent
-            ),
-            &[inviter_wallet_info.clone(), joiner_account_info.clone(), system_program_info.clone()]
-        )?;
-    }
+    // Inviter pays for the pending membership, the joiner pays it back on accepting
+    let invitation = pending_membership(fund_account_info.key, joiner_wallet_info.key, inviter_wallet_info.key, inviter_index, current_time);
+    create_membership(program_id, inviter_wallet_info, joiner_membership_info, system_program_info, &invitation)?;
 
-    joiner_account_info.realloc(new_joiner_space, false)?;
+    joiner_data.pending_invites += 1;
     joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
 
     Ok(())
@@ -479,6 +786,7 @@ fn process_handle_invitation(
     let fund_account_info = next_account_info(accounts_iter)?;
     let system_program_info = next_account_info(accounts_iter)?;
     let rent_reserve_info = next_account_info(accounts_iter)?;
+    let joiner_membership_info = next_account_info(accounts_iter)?;
 
     if !joiner_wallet_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
@@ -495,2835 +803,6877 @@ fn process_handle_invitation(
     let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
     let mut joiner_data = UserAccount::try_from_slice(&joiner_account_info.data.borrow())?;
 
-    if fund_data.members.iter().any(|member| member.0 == *joiner_wallet_info.key) {
-        return Err(FundError::AlreadyMember.into());
-    }
-
-    let is_invited =  joiner_data.funds.iter().any(|fund| fund.fund == *fund_account_info.key);
-    if !is_invited {
+    let Some(mut membership) = load_membership(program_id, joiner_membership_info, fund_account_info.key, joiner_wallet_info.key)? else {
         return Err(FundError::NotInvited.into());
-    }
-
-    let user_specific = joiner_data
-        .funds
-        .iter()
-        .find(|user_specific| user_specific.fund == *fund_account_info.key)
-        .ok_or(FundError::InvalidAccountData)?;
+    };
 
-    if !user_specific.is_pending {
+    if !membership.is_pending {
         return Err(FundError::AlreadyMember.into());
     }
 
-    let rent = Rent::get()?;
+    if response == 1 && fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
 
-    if inviter_exists == 1 {
+    // Whoever paid for the pending membership, the rent reserve once the inviter is gone
+    let rent_payer_info = if inviter_exists == 1 {
         // Inviter Verification
         let inviter_wallet_info = next_account_info(accounts_iter)?;
-        let inviter_correct = fund_data
-            .members
-            .iter()
-            .any(|member| member.0 == *inviter_wallet_info.key && member.1 == user_specific.inviter_index);
+        let inviter_membership_info = next_account_info(accounts_iter)?;
+        let inviter_correct = load_membership(program_id, inviter_membership_info, fund_account_info.key, inviter_wallet_info.key)?
+            .is_some_and(|inviter| !inviter.is_pending && inviter.member_index == membership.inviter_index);
 
         if !inviter_correct {
             return Err(FundError::InvalidInviter.into());
         }
 
-        if response == 0 {
-            joiner_data.funds.retain(|fund| fund.fund != *fund_account_info.key);
-            let current_joiner_space = joiner_account_info.data_len();
-            let new_joiner_space = current_joiner_space - 55;
-            let current_joiner_rent = joiner_account_info.lamports();
-            let new_joiner_rent = rent.minimum_balance(new_joiner_space);
-
-            if new_joiner_rent < current_joiner_rent {
-                **joiner_account_info.lamports.borrow_mut() -= current_joiner_rent - new_joiner_rent;
-                **inviter_wallet_info.lamports.borrow_mut() += current_joiner_rent - new_joiner_rent;
-            }
-
-            joiner_account_info.realloc(new_joiner_space, false)?;
-            joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
-        } else {
-            if let Some(user_specific) = joiner_data
-                .funds
-                .iter_mut()
-                .find(|entry| entry.fund == *fund_account_info.key) {
-                    user_specific.is_pending = false;
-                    user_specific.join_time = current_time;
-                } else {
-                    msg!("User is not a member in this fund");
-                    return Err(FundError::InvalidAccountData.into());
-                }
+        inviter_wallet_info
+    } else {
+        check_inviter_gone(program_id, next_account_info(accounts_iter)?, fund_account_info.key, &membership)?;
+        rent_reserve_info
+    };
 
-            joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
+    joiner_data.pending_invites -= 1;
 
-            let x = rent.minimum_balance(0 as usize);
-            let y = rent.minimum_balance(55 as usize);
-            invoke(
-                &system_instruction::transfer(
-                    joiner_wallet_info.key,
-                    inviter_wallet_info.key,
-                    y - x
-                ),
-                &[joiner_wallet_info.clone(), inviter_wallet_info.clone(), system_program_info.clone()]
-            )?;
-        }
+    if response == 0 {
+        close_membership(joiner_membership_info, &membership, rent_payer_info, &mut fund_data);
+        joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
     } else {
-        if response == 0 {
-            joiner_data.funds.retain(|fund| fund.fund != *fund_account_info.key);
-            let current_joiner_space = joiner_account_info.data_len();
-            let new_joiner_space = current_joiner_space - 55;
-            let current_joiner_rent = joiner_account_info.lamports();
-            let new_joiner_rent = rent.minimum_balance(new_joiner_space);
-
-            if new_joiner_rent < current_joiner_rent {
-                **joiner_account_info.lamports.borrow_mut() -= current_joiner_rent - new_joiner_rent;
-                **rent_reserve_info.lamports.borrow_mut() += current_joiner_rent - new_joiner_rent;
-            }
-
-            joiner_account_info.realloc(new_joiner_space, false)?;
-            joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
-        } else {
-            if let Some(user_specific) = joiner_data
-                .funds
-                .iter_mut()
-                .find(|entry| entry.fund == *fund_account_info.key) {
-                    user_specific.is_pending = false;
-                    user_specific.join_time = current_time;
-                } else {
-                    msg!("User is not a member in this fund");
-                    return Err(FundError::InvalidAccountData.into());
-                }
+        let rent = Rent::get()?;
+        invoke(
+            &system_instruction::transfer(
+                joiner_wallet_info.key,
+                rent_payer_info.key,
+                rent.minimum_balance(MEMBERSHIP_SPACE)
+            ),
+            &[joiner_wallet_info.clone(), rent_payer_info.clone(), system_program_info.clone()]
+        )?;
 
-            joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
+        joiner_data.funds_joined += 1;
+        joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
 
-            let x = rent.minimum_balance(0 as usize);
-            let y = rent.minimum_balance(55 as usize);
+        // Joiner's share of the fund's creation cost goes to the rent reserve
+        let fee_per_member = creation_cost_share(&fund_data);
+        if fee_per_member != 0 {
             invoke(
                 &system_instruction::transfer(
                     joiner_wallet_info.key,
                     rent_reserve_info.key,
-                    y - x
+                    fee_per_member
                 ),
                 &[joiner_wallet_info.clone(), rent_reserve_info.clone(), system_program_info.clone()]
             )?;
+            fund_data.rent_collected += fee_per_member;
         }
-    }
 
-    if response == 1 {
-        fund_data.members.push((*joiner_wallet_info.key, fund_data.members[fund_data.members.len() - 1].1 + 1));
-        let current_fund_size = fund_account_info.data_len();
-        let new_fund_size = current_fund_size + 36;
-        let current_fund_rent = fund_account_info.lamports();
-        let new_fund_rent = rent.minimum_balance(new_fund_size);
+        admit_member(&mut fund_data, &mut membership, current_time)?;
+        membership.serialize(&mut &mut joiner_membership_info.data.borrow_mut()[..])?;
 
-        if new_fund_rent > current_fund_rent {
-            invoke(
-                &system_instruction::transfer(
-                    joiner_wallet_info.key,
-                    fund_account_info.key,
-                    new_fund_rent - current_fund_rent
-                ),
-                &[joiner_wallet_info.clone(), fund_account_info.clone(), system_program_info.clone()]
-            )?;
-        }
+        refund_fund_creator(accounts_iter, fund_account_info, &mut fund_data, rent_reserve_info, &fund_name, current_time)?;
 
-        fund_account_info.realloc(new_fund_size, false)?;
         fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
     }
 
     Ok(())
 }
 
-fn process_withdraw_or_leave_from_light_fund(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    fund_name: String,
-    task: u8,
-    stake_percent: u64,
-    num_of_tokens: u8,
-) -> ProgramResult {
-    // let current_time = Clock::get()?.unix_timestamp;
-
-    let accounts_iter = &mut accounts.iter();
-    let member_wallet_info = next_account_info(accounts_iter)?;
-    let member_account_info = next_account_info(accounts_iter)?;
-    let fund_account_info = next_account_info(accounts_iter)?;
-    let vault_account_info = next_account_info(accounts_iter)?;
-    let token_program_info = next_account_info(accounts_iter)?;
-    let system_program_info = next_account_info(accounts_iter)?;
-    let ata_program_info = next_account_info(accounts_iter)?;
-    let rent_sysvar_info = next_account_info(accounts_iter)?;
-
-    msg!("Stake Percent: {}", stake_percent);
+/// The member who sent a pending invite is no longer in the fund, so the invite's rent is settled
+/// with the rent reserve instead of them. Takes the membership PDA of the invite's inviter wallet.
+fn check_inviter_gone(program_id: &Pubkey, inviter_membership_info: &AccountInfo, fund: &Pubkey, invitation: &FundMembership) -> ProgramResult {
+    let inviter_present = load_membership(program_id, inviter_membership_info, fund, &invitation.inviter)?
+        .is_some_and(|inviter| !inviter.is_pending && inviter.member_index == invitation.inviter_index);
 
-    if stake_percent > 100_000_000 {
-        return Err(FundError::InvalidStakePercent.into());
+    if inviter_present {
+        msg!("Inviter is still a member of the fund");
+        return Err(FundError::InvalidInviter.into());
     }
 
-    if !member_wallet_info.is_signer {
-        return Err(FundError::MissingRequiredSignature.into());
+    Ok(())
+}
+
+/// Once the expected members have joined, the creator gets back what was collected from them.
+/// The creator's wallet is the next account while they are still a member.
+fn refund_fund_creator<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    fund_account_info: &AccountInfo<'a>,
+    fund_data: &mut LightFundAccount,
+    rent_reserve_info: &AccountInfo<'a>,
+    fund_name: &str,
+    current_time: i64,
+) -> ProgramResult {
+    if fund_data.is_refunded || fund_data.member_count <= fund_data.expected_members {
+        return Ok(());
     }
 
-    if task == 1 && stake_percent != 100_000_000 {
-        msg!("Task does not match the data provided.");
-        return Err(FundError::InvalidInstruction.into());
+    if fund_data.creator_exists {
+        let fund_creator_info = next_account_info(accounts_iter)?;
+        if *fund_creator_info.key != fund_data.creator {
+            return Err(FundError::InvalidFundCreator.into());
+        }
+
+        **rent_reserve_info.lamports.borrow_mut() -= fund_data.rent_collected;
+        **fund_creator_info.lamports.borrow_mut() += fund_data.rent_collected;
+        msg!("[FUND-ACTIVITY] {} {} {} Creator refunded: {}", fund_account_info.key.to_string(), current_time, fund_name, fund_data.rent_collected);
     }
+    fund_data.is_refunded = true;
 
-    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    let (member_pda, _joiner_bump) = Pubkey::find_program_address(&[b"user", member_wallet_info.key.as_ref()], program_id);
-    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_account_info.key.as_ref()], program_id);
+    Ok(())
+}
 
-    if *fund_account_info.key != fund_pda || *member_account_info.key != member_pda || *vault_account_info.key != vault_pda {
-        return Err(FundError::InvalidAccountData.into());
+/// Closes a proposal whose voters are kept by wallet. Each of `paying_voters` paid for the room
+/// their vote took and gets that rent back, their wallets being the next accounts in order, and
+/// `recipient_info` gets the rest.
+fn close_voted_proposal<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    proposal_info: &AccountInfo<'a>,
+    recipient_info: &AccountInfo<'a>,
+    paying_voters: &[(Pubkey, u8)],
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let vote_rent = rent.minimum_balance(VOTE_SPACE) - rent.minimum_balance(0);
+
+    for voter in paying_voters.iter() {
+        let voter_info = next_account_info(accounts_iter)?;
+        if *voter_info.key != voter.0 {
+            return Err(FundError::InvalidVoteAccount.into());
+        }
+
+        let refund = vote_rent.min(proposal_info.lamports());
+        **proposal_info.lamports.borrow_mut() -= refund;
+        **voter_info.lamports.borrow_mut() += refund;
     }
 
-    let mut mint_account_infos: Vec<&AccountInfo> = vec![];
-    for _i in 0..num_of_tokens {
-        let mint_account_info = next_account_info(accounts_iter)?;
-        mint_account_infos.push(mint_account_info);
+    let proposal_lamports = proposal_info.lamports();
+    **proposal_info.lamports.borrow_mut() = 0;
+    **recipient_info.lamports.borrow_mut() += proposal_lamports;
+    proposal_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+/// Loads a wallet's membership of the fund after checking its address, pending invitations
+/// included. None if the wallet was never invited or has left.
+fn load_membership(program_id: &Pubkey, membership_info: &AccountInfo, fund: &Pubkey, wallet: &Pubkey) -> Result<Option<FundMembership>, ProgramError> {
+    let (membership_pda, _membership_bump) = Pubkey::find_program_address(&[b"member", fund.as_ref(), wallet.as_ref()], program_id);
+    if *membership_info.key != membership_pda {
+        return Err(FundError::InvalidAccountData.into());
     }
 
-    let mut member_ata_infos: Vec<&AccountInfo> = vec![];
-    for _i in 0..num_of_tokens {
-        let member_ata_info = next_account_info(accounts_iter)?;
-        member_ata_infos.push(member_ata_info);
+    if membership_info.data_is_empty() || membership_info.lamports() == 0 {
+        return Ok(None);
     }
 
-    let mut vault_ata_infos: Vec<&AccountInfo> = vec![];
-    for _i in 0..num_of_tokens {
-        let vault_ata_info = next_account_info(accounts_iter)?;
-        vault_ata_infos.push(vault_ata_info);
+    Ok(Some(FundMembership::try_from_slice(&membership_info.data.borrow())?))
+}
+
+/// In share-weighted funds the voter's shares stay put until the voting they took part in ends,
+/// so they can't be moved to another wallet and counted a second time.
+fn lock_voting_shares(fund_data: &LightFundAccount, membership_info: &AccountInfo, membership: &mut FundMembership, voting_end: i64) -> ProgramResult {
+    if fund_data.voting_mode != 1 || membership.votes_locked_until >= voting_end {
+        return Ok(());
     }
 
-    let mut member_data = UserAccount::try_from_slice(&member_account_info.data.borrow())?;
-    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    membership.votes_locked_until = voting_end;
+    membership.serialize(&mut &mut membership_info.data.borrow_mut()[..])?;
 
-    let (matched_index, user_specific) = member_data
-        .funds
-        .iter()
-        .enumerate()
-        .find(|(_i, user_specific)| user_specific.fund == *fund_account_info.key)
-        .ok_or(FundError::InvalidAccountData)?;
+    Ok(())
+}
 
-    let member_deposit = user_specific.governance_token_balance;
-    let total_deposit = fund_data.total_deposit;
-    msg!("Fund's Total Deposit before Withdrawal: {}", total_deposit);
-    msg!("Member Deposit before Withdrawal: {}", member_deposit);
-    if member_deposit > total_deposit {
+/// Same as `load_membership`, failing for wallets that aren't members yet.
+fn load_member(program_id: &Pubkey, membership_info: &AccountInfo, fund: &Pubkey, wallet: &Pubkey) -> Result<FundMembership, ProgramError> {
+    match load_membership(program_id, membership_info, fund, wallet)? {
+        Some(membership) if !membership.is_pending => Ok(membership),
+        _ => Err(FundError::NotAFundMember.into()),
+    }
+}
+
+/// Membership of a wallet invited by the member with `inviter_index`, still to be accepted.
+fn pending_membership(fund: &Pubkey, wallet: &Pubkey, inviter: &Pubkey, inviter_index: u32, current_time: i64) -> FundMembership {
+    FundMembership {
+        fund: *fund,
+        wallet: *wallet,
+        is_pending: true,
+        inviter_index,
+        inviter: *inviter,
+        member_index: 0,
+        roles: DEFAULT_MEMBER_ROLES,
+        deposit: 0,
+        join_time: current_time,
+        last_deposit_time: 0,
+        stake_payouts: 0,
+        votes_locked_until: 0,
+    }
+}
+
+/// Creates the membership PDA holding `membership`, paid for by `payer`.
+fn create_membership<'a>(
+    program_id: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    membership_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    membership: &FundMembership,
+) -> ProgramResult {
+    let (membership_pda, membership_bump) = Pubkey::find_program_address(&[b"member", membership.fund.as_ref(), membership.wallet.as_ref()], program_id);
+    if *membership_info.key != membership_pda {
         return Err(FundError::InvalidAccountData.into());
     }
+    if !membership_info.data_is_empty() {
+        return Err(FundError::AlreadyInvited.into());
+    }
 
-    let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            membership_info.key,
+            rent.minimum_balance(MEMBERSHIP_SPACE),
+            MEMBERSHIP_SPACE as u64,
+            program_id
+        ),
+        &[payer_info.clone(), membership_info.clone(), system_program_info.clone()],
+        &[&[b"member", membership.fund.as_ref(), membership.wallet.as_ref(), &[membership_bump]]]
+    )?;
 
-    if total_deposit != 0 {
-        let withdraw_percent_u128: u128 = ((stake_percent as u128) * (member_deposit as u128))/(total_deposit as u128);
-        let withdraw_percent = withdraw_percent_u128 as u64;
-        msg!("Withdraw percent overall: {}", withdraw_percent);
+    membership.serialize(&mut &mut membership_info.data.borrow_mut()[..])?;
 
-        if withdraw_percent != 0 {
-            for i in 0..num_of_tokens {
-                let token_account = spl_token::state::Account::unpack(&vault_ata_infos[i as usize].data.borrow())?;
-                let vault_balance = token_account.amount;
-                msg!("Vault Balance: {}", vault_balance);
-                let amount_to_transfer_u128: u128 = ((vault_balance as u128) * (withdraw_percent as u128))/(100_000_000 as u128);
-                let amount_to_transfer = amount_to_transfer_u128 as u64;
-                msg!("Amount to Transfer: {}", amount_to_transfer);
-
-                if *mint_account_infos[i as usize].key == wsol_mint {
-                    msg!("Withdrawing SOL");
-                    if member_ata_infos[i as usize].data_is_empty() {
-                        invoke(
-                            &spl_associated_token_account::instruction::create_associated_token_account(
-                                member_wallet_info.key,
-                                member_wallet_info.key,
-                                mint_account_infos[i as usize].key,
-                                token_program_info.key,
-                            ),
-                            &[
-                                member_wallet_info.clone(),
-                                member_ata_infos[i as usize].clone(),
-                                token_program_info.clone(),
-                                mint_account_infos[i as usize].clone(),
-                                system_program_info.clone(),
-                                ata_program_info.clone(),
-                                rent_sysvar_info.clone(),
-                            ]
-                        )?;
-                    }
+    Ok(())
+}
 
-                    invoke_signed(
-                        &spl_token::instruction::transfer(
-                            token_program_info.key,
-                            vault_ata_infos[i as usize].key,
-                            member_ata_infos[i as usize].key,
-                            vault_account_info.key,
-                            &[],
-                            amount_to_transfer
-                        )?,
-                        &[
-                            token_program_info.clone(),
-                            vault_ata_infos[i as usize].clone(),
-                            member_ata_infos[i as usize].clone(),
-                            vault_account_info.clone()
-                        ],
-                        &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
-                    )?;
-
-                    invoke(
-                        &spl_token::instruction::close_account(
-                            token_program_info.key,
-                            member_ata_infos[i as usize].key,
-                            member_wallet_info.key,
-                            member_wallet_info.key,
-                            &[]
-                        )?,
-                        &[
-                            token_program_info.clone(),
-                            member_wallet_info.clone(),
-                            member_ata_infos[i as usize].clone(),
-                            system_program_info.clone(),
-                            ata_program_info.clone(),
-                            rent_sysvar_info.clone()
-                        ]
-                    )?;
-                } else {
-                    msg!("Withdrawing SPL Token");
-                    if member_ata_infos[i as usize].data_is_empty() {
-                        invoke(
-                            &spl_associated_token_account::instruction::create_associated_token_account(
-                                member_wallet_info.key,
-                                member_wallet_info.key,
-                                mint_account_infos[i as usize].key,
-                                token_program_info.key,
-                            ),
-                            &[
-                                member_wallet_info.clone(),
-                                member_ata_infos[i as usize].clone(),
-                                token_program_info.clone(),
-                                mint_account_infos[i as usize].clone(),
-                                system_program_info.clone(),
-                                ata_program_info.clone(),
-                                rent_sysvar_info.clone(),
-                            ]
-                        )?;
-                    }
+/// Makes a pending membership a full one, giving it the next member index and counting it in.
+fn admit_member(fund_data: &mut LightFundAccount, membership: &mut FundMembership, current_time: i64) -> ProgramResult {
+    if fund_data.member_count >= fund_data.max_members {
+        return Err(FundError::FundAlreadyFull.into());
+    }
 
-                    invoke_signed(
-                        &spl_token::instruction::transfer(
-                            token_program_info.key,
-                            vault_ata_infos[i as usize].key,
-                            member_ata_infos[i as usize].key,
-                            vault_account_info.key,
-                            &[],
-                            amount_to_transfer
-                        )?,
-                        &[
-                            token_program_info.clone(),
-                            vault_ata_infos[i as usize].clone(),
-                            member_ata_infos[i as usize].clone(),
-                            vault_account_info.clone()
-                        ],
-                        &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
-                    )?;
-                }
-            }
+    membership.is_pending = false;
+    membership.member_index = fund_data.next_member_index;
+    membership.join_time = current_time;
+
+    fund_data.member_count += 1;
+    fund_data.next_member_index += 1;
+    if has_role(membership, ROLE_VOTER) {
+        fund_data.voter_count += 1;
+    }
+
+    Ok(())
+}
+
+/// Closes a membership PDA, its rent going to `recipient`, and counts a leaving member out.
+fn close_membership(membership_info: &AccountInfo, membership: &FundMembership, recipient_info: &AccountInfo, fund_data: &mut LightFundAccount) {
+    if !membership.is_pending {
+        fund_data.member_count -= 1;
+        if has_role(membership, ROLE_VOTER) {
+            fund_data.voter_count -= 1;
         }
     }
 
-    let rent = Rent::get()?;
-    
-    fund_data.total_deposit -= (((member_data.funds[matched_index].governance_token_balance as u128) * (stake_percent as u128))/(100_000_000 as u128)) as u64;
-    msg!("Fund's Total Deposit after Withdrawal: {}", fund_data.total_deposit);
+    let lamports = membership_info.lamports();
+    **membership_info.lamports.borrow_mut() = 0;
+    **recipient_info.lamports.borrow_mut() += lamports;
 
-    if task == 1 {
-        fund_data.members.retain(|member| member.0 != *member_wallet_info.key);
-        member_data.funds.retain(|user_specific| user_specific.fund != *fund_account_info.key);
+    let mut data = membership_info.data.borrow_mut();
+    for byte in data.iter_mut() {
+        *byte = 0;
+    }
+}
 
-        if fund_data.creator_exists && fund_data.members[0 as usize].0 == *member_wallet_info.key {
-            fund_data.creator_exists = false;
-        }
+/// Loads the fund's holdings registry after checking its address, empty if nothing was ever deposited.
+fn load_vault_holdings(program_id: &Pubkey, holdings_info: &AccountInfo, fund: &Pubkey) -> Result<VaultHoldings, ProgramError> {
+    let (holdings_pda, _holdings_bump) = Pubkey::find_program_address(&[b"holdings", fund.as_ref()], program_id);
+    if *holdings_info.key != holdings_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-        let current_fund_size = fund_account_info.data_len();
-        let new_fund_size = current_fund_size - 36;
-        let current_fund_rent = fund_account_info.lamports();
-        let new_fund_rent = rent.minimum_balance(new_fund_size);
-        
+    if holdings_info.data_is_empty() {
+        return Ok(VaultHoldings { fund: *fund, holdings: vec![] });
+    }
+
+    Ok(VaultHoldings::try_from_slice(&holdings_info.data.borrow())?)
+}
 
-        let current_user_size = member_account_info.data_len();
-        let new_user_size = current_user_size - 55;
-        let current_user_rent = member_account_info.lamports();
-        let new_user_rent = rent.minimum_balance(new_user_size);
+/// Balance of a vault token account, whichever token program owns it.
+fn token_balance(token_account_info: &AccountInfo) -> Result<u64, ProgramError> {
+    let token_account_data = token_account_info.try_borrow_data()?;
+    let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
+    Ok(token_account.base.amount)
+}
 
-        fund_account_info.realloc(new_fund_size, false)?;
-        member_account_info.realloc(new_user_size, false)?;
+/// Records the vault's token account for a mint in the fund's holdings registry along with
+/// its current balance. The registry is created on first use and the payer covers the rent
+/// of every new entry.
+fn record_holding<'a>(
+    program_id: &Pubkey,
+    holdings_info: &AccountInfo<'a>,
+    fund_account_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    mint_info: &AccountInfo<'a>,
+    vault_ata_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let mut holdings_data = load_vault_holdings(program_id, holdings_info, fund_account_info.key)?;
 
-        if current_fund_rent > new_fund_rent  {
-            **fund_account_info.lamports.borrow_mut() -= current_fund_rent - new_fund_rent;
-            **member_wallet_info.lamports.borrow_mut() += current_fund_rent - new_fund_rent;
-        }
+    if holdings_info.data_is_empty() {
+        let (_holdings_pda, holdings_bump) = Pubkey::find_program_address(&[b"holdings", fund_account_info.key.as_ref()], program_id);
+        let holdings_space = 36;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                holdings_info.key,
+                rent.minimum_balance(holdings_space),
+                holdings_space as u64,
+                program_id
+            ),
+            &[payer_info.clone(), holdings_info.clone(), system_program_info.clone()],
+            &[&[b"holdings", fund_account_info.key.as_ref(), &[holdings_bump]]]
+        )?;
+    }
 
-        if current_user_rent > new_user_rent  {
-            **member_account_info.lamports.borrow_mut() -= current_user_rent - new_user_rent;
-            **member_wallet_info.lamports.borrow_mut() += current_user_rent - new_user_rent;
+    let balance = token_balance(vault_ata_info)?;
+    if let Some(holding) = holdings_data.holdings.iter_mut().find(|holding| holding.mint == *mint_info.key) {
+        if holding.ata != *vault_ata_info.key {
+            return Err(FundError::InvalidTokenAccount.into());
         }
+        holding.last_balance = balance;
     } else {
-        if total_deposit != 0 && stake_percent != 0 {
-            member_data.funds[matched_index].governance_token_balance -= (((member_data.funds[matched_index].governance_token_balance as u128) * (stake_percent as u128))/(100_000_000 as u128)) as u64;
-            msg!("Member Deposit After Withdrawal: {}", member_data.funds[matched_index].governance_token_balance);
+        holdings_data.holdings.push(Holding {
+            mint: *mint_info.key,
+            ata: *vault_ata_info.key,
+            token_program: *mint_info.owner,
+            last_balance: balance,
+        });
+
+        let current_holdings_size = holdings_info.data_len();
+        let new_holdings_size = current_holdings_size + 104;
+        let current_holdings_rent = holdings_info.lamports();
+        let new_holdings_rent = rent.minimum_balance(new_holdings_size);
+
+        if new_holdings_rent > current_holdings_rent {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    holdings_info.key,
+                    new_holdings_rent - current_holdings_rent
+                ),
+                &[payer_info.clone(), holdings_info.clone(), system_program_info.clone()]
+            )?;
         }
+
+        holdings_info.realloc(new_holdings_size, false)?;
     }
-    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
-    member_data.serialize(&mut &mut member_account_info.data.borrow_mut()[..])?;
 
-    msg!("Transferred holdings successfully");
+    holdings_data.serialize(&mut &mut holdings_info.data.borrow_mut()[..])?;
 
     Ok(())
 }
 
-// fn process_init_fund_account<'a>(
-//     program_id: &Pubkey,
-//     accounts: &'a [AccountInfo<'a>],
-//     fund_name: String,
-//     privacy: u8,
-//     expected_members: u32,
-//     symbol_str: String,
-// ) -> ProgramResult {
-//     let current_time = Clock::get()?.unix_timestamp;
+/// Refreshes the last known balances of the listed vault token accounts, which have to be
+/// the registry's holdings in order.
+fn sync_holdings(holdings_info: &AccountInfo, vault_atas: &[&AccountInfo]) -> ProgramResult {
+    if holdings_info.data_is_empty() {
+        return Ok(());
+    }
 
-//     let accounts_iter = &mut accounts.iter();
-//     let governance_mint_info = next_account_info(accounts_iter)?; // Governance Mint .........................
-//     let vault_account_info = next_account_info(accounts_iter)?; // Vault PDA Account .........................
-//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...........................
-//     let token_program_2022_info = next_account_info(accounts_iter)?; // Token Program (2022) .................
-//     let fund_account_info = next_account_info(accounts_iter)?; // Fund PDA Account ...........................
-//     let creator_wallet_info = next_account_info(accounts_iter)?; // Creator Wallet Address ...................
-//     let rent_sysvar_info = next_account_info(accounts_iter)?; // Rent Sysvar .................................
-//     let user_account_info = next_account_info(accounts_iter)?; // Global User Account ........................
-//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // first proposal aggregator ...........
-//     let join_proposal_aggregator_info = next_account_info(accounts_iter)?; // join proposal aggregator .......
+    let mut holdings_data = VaultHoldings::try_from_slice(&holdings_info.data.borrow())?;
+    for (holding, vault_ata_info) in holdings_data.holdings.iter_mut().zip(vault_atas.iter()) {
+        holding.last_balance = token_balance(vault_ata_info)?;
+    }
+    holdings_data.serialize(&mut &mut holdings_info.data.borrow_mut()[..])?;
 
-//     // Creator should be signer
-//     if !creator_wallet_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    Ok(())
+}
 
-//     let index: u8 = 0;
+/// Reads the governance mint, the member's governance token account and the Token-2022 program
+/// when the fund was created with a governance mint, none otherwise.
+fn next_governance_accounts<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    fund_data: &LightFundAccount,
+    wallet: &Pubkey,
+) -> Result<Option<(&'b AccountInfo<'a>, &'b AccountInfo<'a>, &'b AccountInfo<'a>)>, ProgramError> {
+    if fund_data.governance_mint == Pubkey::default() {
+        return Ok(None);
+    }
 
-//     // Deriving required PDAs
-//     let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
-//     let (governance_mint, governance_bump) = Pubkey::find_program_address(&[b"governance", fund_pda.as_ref()], program_id);
-//     let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", creator_wallet_info.key.as_ref()], program_id);
-//     let (proposal_pda, proposal_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
-//     let (join_aggregator_pda, join_aggregator_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
+    let governance_mint_info = next_account_info(accounts_iter)?;
+    let governance_token_account_info = next_account_info(accounts_iter)?;
+    let token_program_2022_info = next_account_info(accounts_iter)?;
 
-//     // Check if any of the provided PDA differes from the derived
-//     if *fund_account_info.key != fund_pda ||
-//        *vault_account_info.key != vault_pda ||
-//        *governance_mint_info.key != governance_mint ||
-//        *user_account_info.key != user_pda ||
-//        *proposal_aggregator_info.key != proposal_pda ||
-//        *join_proposal_aggregator_info.key != join_aggregator_pda {
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    if *token_program_2022_info.key != spl_token_2022::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-//     // Check if the an account already exists on that PDA
-//     if fund_account_info.lamports() > 0 {
-//         msg!("[FUND-ERROR] {} {} Fund already exists!!", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    if *governance_mint_info.key != fund_data.governance_mint ||
+       *governance_token_account_info.key != get_associated_token_address_with_program_id(wallet, &fund_data.governance_mint, token_program_2022_info.key) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
 
-//     // Calculate Rent
-//     let rent = &Rent::from_account_info(rent_sysvar_info)?;
-//     let fund_space = 150 as usize;
-//     let vault_space = 40 as usize;
-//     let extensions = vec![ExtensionType::NonTransferable, ExtensionType::MetadataPointer];
-//     let base_mint_space = ExtensionType::try_calculate_account_len::<Mint>(&extensions)?;
-//     let token_name = fund_name.clone();
-//     let token_symbol = String::from(symbol_str.clone());
-//     let token_uri = "".to_string();
-//     let mint_space = base_mint_space;
-//     let proposal_space = 37 as usize;
-//     let join_proposal_space = 37 as usize;
+    Ok(Some((governance_mint_info, governance_token_account_info, token_program_2022_info)))
+}
 
-//     // Creating the Fund Account PDA
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             creator_wallet_info.key,
-//             fund_account_info.key,
-//             rent.minimum_balance(fund_space),
-//             fund_space as u64,
-//             program_id,
-//         ),
-//         &[creator_wallet_info.clone(), fund_account_info.clone(), system_program_info.clone()],
-//         &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
-//     )?;
+/// Accounts used to pay a member's pro-rata share of the vault out to their wallet.
+struct MemberPayout<'a, 'b> {
+    payer: &'b AccountInfo<'a>,
+    recipient: &'b AccountInfo<'a>,
+    fund: &'b AccountInfo<'a>,
+    vault: &'b AccountInfo<'a>,
+    vault_bump: u8,
+    token_program: &'b AccountInfo<'a>,
+    token_program_2022: &'b AccountInfo<'a>,
+    system_program: &'b AccountInfo<'a>,
+    ata_program: &'b AccountInfo<'a>,
+    rent_sysvar: &'b AccountInfo<'a>,
+    mints: Vec<&'b AccountInfo<'a>>,
+    recipient_atas: Vec<&'b AccountInfo<'a>>,
+    vault_atas: Vec<&'b AccountInfo<'a>>,
+    holdings: &'b AccountInfo<'a>,
+    treasury: &'b AccountInfo<'a>,
+    treasury_atas: Vec<&'b AccountInfo<'a>>,
+    protocol_config: ProtocolConfig,
+    stake_program: Option<(&'b AccountInfo<'a>, &'b AccountInfo<'a>)>,
+    stakes: Vec<(u8, &'b AccountInfo<'a>, &'b AccountInfo<'a>)>,
+    stake_nonce: u32, // recipient's stake_payouts, fresh split stake accounts for every payout
+    positions: Vec<&'b AccountInfo<'a>>,
+    single_asset: Option<SingleAssetRoute<'a, 'b>>,
+}
 
-//     // Creating the Vault Account PDA
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             creator_wallet_info.key,
-//             vault_account_info.key,
-//             rent.minimum_balance(vault_space),
-//             vault_space as u64,
-//             program_id,
-//         ),
-//         &[creator_wallet_info.clone(), vault_account_info.clone(), system_program_info.clone()],
-//         &[&[b"vault", fund_pda.as_ref(), &[vault_bump]]],
-//     )?;
+/// Swaps routing every other holding into one mint, for members redeeming in a single asset.
+struct SingleAssetRoute<'a, 'b> {
+    target_index: usize,
+    min_amounts_out: Vec<u64>, // per mint, the minimum out of its swap leg, the target's being the redemption's minimum
+    raydium_clmm_program: &'b AccountInfo<'a>,
+    memo_program: &'b AccountInfo<'a>,
+    // per mint, empty for the target: amm config, pool state, pool input vault, pool output vault, observation state, tick arrays
+    pools: Vec<Vec<&'b AccountInfo<'a>>>,
+}
 
-//     // Creating the Governance Mint account
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             creator_wallet_info.key,
-//             governance_mint_info.key,
-//             rent.minimum_balance(mint_space),
-//             mint_space as u64,
-//             token_program_2022_info.key,
-//         ),
-//         &[creator_wallet_info.clone(), governance_mint_info.clone(), system_program_info.clone(), token_program_2022_info.clone()],
-//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
-//     )?;
+/// Reads the payout accounts following a withdrawal's fixed accounts: mints, recipient token
+/// accounts, vault token accounts, the holdings registry, the Token-2022 program, protocol config
+/// with treasury accounts, then every vault stake account (with the recipient's split account for
+/// `stake_nonce`), every position and, when redeeming in a single asset, the swap route. Mints
+/// must be exactly the registry's, in order.
+#[allow(clippy::too_many_arguments)]
+fn next_member_payout<'a, 'b>(
+    program_id: &Pubkey,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    payer: &'b AccountInfo<'a>,
+    recipient: &'b AccountInfo<'a>,
+    fund: &'b AccountInfo<'a>,
+    vault: &'b AccountInfo<'a>,
+    programs: [&'b AccountInfo<'a>; 4],
+    stake_nonce: u32,
+    num_of_tokens: u8,
+    num_of_stakes: u8,
+    num_of_positions: u8,
+    single_asset: Option<(u8, u8, Vec<u64>)>,
+) -> Result<MemberPayout<'a, 'b>, ProgramError> {
+    let [token_program, system_program, ata_program, rent_sysvar] = programs;
+    if *token_program.key != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-//     invoke_signed(
-//         &spl_token_2022::instruction::initialize_non_transferable_mint(
-//             &spl_token_2022::id(),
-//             governance_mint_info.key,
-//         )?,
-//         &[governance_mint_info.clone(), token_program_2022_info.clone()],
-//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]]
-//     )?;
-//     invoke_signed(
-//         &metadata_pointer::instruction::initialize(
-//             &spl_token_2022::id(),
-//             governance_mint_info.key,
-//             Some(*fund_account_info.key),
-//             Some(*governance_mint_info.key),
-//         )?,
-//         &[governance_mint_info.clone(), rent_sysvar_info.clone(), token_program_2022_info.clone()],
-//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
-//     )?;
-//     invoke_signed(
-//         &spl_token_2022::instruction::initialize_mint2(
-//             &spl_token_2022::id(),
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund.key.as_ref()], program_id);
+    if *vault.key != vault_pda {
+        return Err(FundError::InvaildVaultAccount.into());
+    }
+
+    let vault_data = VaultAccount::try_from_slice(&vault.data.borrow())?;
+
+    let mut mints: Vec<&AccountInfo> = vec![];
+    for _i in 0..num_of_tokens {
+        mints.push(next_account_info(accounts_iter)?);
+    }
+
+    let mut recipient_atas: Vec<&AccountInfo> = vec![];
+    for _i in 0..num_of_tokens {
+        recipient_atas.push(next_account_info(accounts_iter)?);
+    }
+
+    let mut vault_atas: Vec<&AccountInfo> = vec![];
+    for _i in 0..num_of_tokens {
+        vault_atas.push(next_account_info(accounts_iter)?);
+    }
+
+    // All holdings have to be withdrawn together, no picking the assets one likes
+    let holdings = next_account_info(accounts_iter)?;
+    let holdings_data = load_vault_holdings(program_id, holdings, fund.key)?;
+    if num_of_tokens as usize != holdings_data.holdings.len() {
+        msg!("All of the vault's {} token accounts must be provided", holdings_data.holdings.len());
+        return Err(FundError::InvalidMints.into());
+    }
+
+    for (i, holding) in holdings_data.holdings.iter().enumerate() {
+        if *mints[i].key != holding.mint || *vault_atas[i].key != holding.ata {
+            return Err(FundError::InvalidMints.into());
+        }
+    }
+
+    let token_program_2022 = next_account_info(accounts_iter)?;
+    if *token_program_2022.key != spl_token_2022::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let protocol_config_info = next_account_info(accounts_iter)?;
+    let treasury = next_account_info(accounts_iter)?;
+    let protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+    let (treasury_pda, _treasury_bump) = Pubkey::find_program_address(&[b"treasury"], program_id);
+    if *treasury.key != treasury_pda {
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
+
+    let mut treasury_atas: Vec<&AccountInfo> = vec![];
+    for i in 0..num_of_tokens {
+        let treasury_ata_info = next_account_info(accounts_iter)?;
+        if *treasury_ata_info.key != get_associated_token_address_with_program_id(&treasury_pda, mints[i as usize].key, mints[i as usize].owner) {
+            return Err(FundError::InvalidTokenAccount.into());
+        }
+        treasury_atas.push(treasury_ata_info);
+    }
+
+    // Every vault stake account has to be listed along with the recipient's split stake account for it
+    if num_of_stakes as usize != vault_data.stake_accounts.len() {
+        msg!("All of the vault's stake accounts must be provided");
+        return Err(FundError::InvalidStakeAccount.into());
+    }
+
+    let mut stake_program = None;
+    let mut stakes: Vec<(u8, &AccountInfo, &AccountInfo)> = vec![];
+    if num_of_stakes != 0 {
+        let stake_program_info = next_account_info(accounts_iter)?;
+        let clock_sysvar_info = next_account_info(accounts_iter)?;
+        stake_program = Some((stake_program_info, clock_sysvar_info));
+        for i in 0..num_of_stakes {
+            let stake_account_info = next_account_info(accounts_iter)?;
+            let member_stake_info = next_account_info(accounts_iter)?;
+            let (stake_index, _principal) = vault_data.stake_accounts[i as usize];
+            let (stake_pda, _stake_bump) = Pubkey::find_program_address(&[b"vault-stake", fund.key.as_ref(), &[stake_index]], program_id);
+            let (member_stake_pda, _member_stake_bump) = Pubkey::find_program_address(
+                &[b"member-stake", fund.key.as_ref(), recipient.key.as_ref(), &[stake_index], &stake_nonce.to_le_bytes()],
+                program_id
+            );
+            if *stake_account_info.key != stake_pda || *member_stake_info.key != member_stake_pda {
+                return Err(FundError::InvalidStakeAccount.into());
+            }
+            stakes.push((stake_index, stake_account_info, member_stake_info));
+        }
+    }
+
+    // Positions are paid out through their receipt tokens, which are among the held mints
+    if num_of_positions as usize != vault_data.positions.len() {
+        msg!("All of the vault's positions must be provided");
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    let mut positions: Vec<&AccountInfo> = vec![];
+    for i in 0..num_of_positions {
+        let position_account_info = next_account_info(accounts_iter)?;
+        let position_index = vault_data.positions[i as usize];
+        let (position_pda, _position_bump) = Pubkey::find_program_address(&[b"position", fund.key.as_ref(), &[position_index]], program_id);
+        if *position_account_info.key != position_pda {
+            return Err(FundError::InvalidPosition.into());
+        }
+        let position_data = Position::try_from_slice(&position_account_info.data.borrow())?;
+        if !mints.iter().any(|mint_account_info| *mint_account_info.key == position_data.receipt_mint) {
+            msg!("Receipt token of position {} is missing", position_index);
+            return Err(FundError::InvalidPosition.into());
+        }
+        positions.push(position_account_info);
+    }
+
+    let single_asset = match single_asset {
+        Some((target_index, tick_arrays_per_swap, min_amounts_out)) => {
+            if target_index >= num_of_tokens || min_amounts_out.len() != num_of_tokens as usize {
+                return Err(FundError::InvalidMints.into());
+            }
+
+            let raydium_clmm_program = next_account_info(accounts_iter)?;
+            let memo_program = next_account_info(accounts_iter)?;
+
+            let mut pools: Vec<Vec<&AccountInfo>> = vec![];
+            for i in 0..num_of_tokens {
+                let mut pool_accounts: Vec<&AccountInfo> = vec![];
+                if i != target_index {
+                    for _j in 0..(5 + tick_arrays_per_swap) {
+                        pool_accounts.push(next_account_info(accounts_iter)?);
+                    }
+                }
+                pools.push(pool_accounts);
+            }
+
+            Some(SingleAssetRoute {
+                target_index: target_index as usize,
+                min_amounts_out,
+                raydium_clmm_program,
+                memo_program,
+                pools,
+            })
+        }
+        None => None,
+    };
+
+    Ok(MemberPayout {
+        payer,
+        recipient,
+        fund,
+        vault,
+        vault_bump,
+        token_program,
+        token_program_2022,
+        system_program,
+        ata_program,
+        rent_sysvar,
+        mints,
+        recipient_atas,
+        vault_atas,
+        holdings,
+        treasury,
+        treasury_atas,
+        protocol_config,
+        stake_program,
+        stakes,
+        stake_nonce,
+        positions,
+        single_asset,
+    })
+}
+
+/// Sends `amount` of the `i`th listed mint from the vault to the recipient, less the protocol
+/// withdraw fee. wSOL is unwrapped only when the recipient pays for the transaction, as closing
+/// the temporary account needs their signature.
+fn transfer_to_recipient(payout: &MemberPayout, i: usize, amount: u64) -> ProgramResult {
+    let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+    let vault_seeds: &[&[u8]] = &[b"vault", payout.fund.key.as_ref(), &[payout.vault_bump]];
+
+    let mint_account_info = payout.mints[i];
+    let recipient_ata_info = payout.recipient_atas[i];
+    let vault_ata_info = payout.vault_atas[i];
+    let treasury_ata_info = payout.treasury_atas[i];
+    let token_program = if *mint_account_info.owner == spl_token_2022::id() {
+        payout.token_program_2022
+    } else {
+        payout.token_program
+    };
+    let decimals = supported_mint_decimals(mint_account_info)?;
+
+    // Protocol fee stays in the treasury, the member gets the rest
+    if payout.protocol_config.withdraw_fee_bps != 0 && treasury_ata_info.data_is_empty() {
+        invoke(
+            &create_associated_token_account(
+                payout.payer.key,
+                payout.treasury.key,
+                mint_account_info.key,
+                token_program.key
+            ),
+            &[
+                payout.payer.clone(),
+                treasury_ata_info.clone(),
+                payout.treasury.clone(),
+                mint_account_info.clone(),
+                payout.system_program.clone(),
+                token_program.clone(),
+                payout.ata_program.clone(),
+            ]
+        )?;
+    }
+
+    let protocol_fee = collect_protocol_fee(
+        token_program,
+        mint_account_info,
+        vault_ata_info,
+        treasury_ata_info,
+        payout.vault,
+        amount,
+        payout.protocol_config.withdraw_fee_bps,
+        vault_seeds
+    )?;
+    let amount_to_transfer = amount - protocol_fee;
+    msg!("Amount to Transfer: {}", amount_to_transfer);
+
+    if recipient_ata_info.data_is_empty() {
+        invoke(
+            &spl_associated_token_account::instruction::create_associated_token_account(
+                payout.payer.key,
+                payout.recipient.key,
+                mint_account_info.key,
+                token_program.key,
+            ),
+            &[
+                payout.payer.clone(),
+                recipient_ata_info.clone(),
+                payout.recipient.clone(),
+                token_program.clone(),
+                mint_account_info.clone(),
+                payout.system_program.clone(),
+                payout.ata_program.clone(),
+                payout.rent_sysvar.clone(),
+            ]
+        )?;
+    }
+
+    transfer_tokens(token_program, vault_ata_info, mint_account_info, recipient_ata_info, payout.vault, amount_to_transfer, decimals, &[vault_seeds])?;
+
+    if *mint_account_info.key == wsol_mint && payout.payer.key == payout.recipient.key {
+        msg!("Withdrawing SOL");
+        invoke(
+            &spl_token::instruction::close_account(
+                payout.token_program.key,
+                recipient_ata_info.key,
+                payout.recipient.key,
+                payout.recipient.key,
+                &[]
+            )?,
+            &[
+                payout.token_program.clone(),
+                payout.recipient.clone(),
+                recipient_ata_info.clone(),
+                payout.system_program.clone(),
+                payout.ata_program.clone(),
+                payout.rent_sysvar.clone()
+            ]
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Pays `withdraw_percent` (100_000_000 = everything) of every vault holding to the recipient,
+/// the protocol withdraw fee going to the treasury. With a single-asset route the token share
+/// is first swapped into the target mint inside the vault and paid out in that mint alone.
+fn pay_out_member_share(
+    program_id: &Pubkey,
+    payout: &MemberPayout,
+    withdraw_percent: u64,
+) -> ProgramResult {
+    let vault_seeds: &[&[u8]] = &[b"vault", payout.fund.key.as_ref(), &[payout.vault_bump]];
+
+    if let Some(route) = payout.single_asset.as_ref() {
+        let target_vault_ata_info = payout.vault_atas[route.target_index];
+        let target_before = token_balance(target_vault_ata_info)?;
+        let mut amount_out = (((target_before as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+
+        for i in 0..payout.mints.len() {
+            if i == route.target_index {
+                continue;
+            }
+
+            let vault_balance = token_balance(payout.vault_atas[i])?;
+            let share = (((vault_balance as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+            if share == 0 {
+                continue;
+            }
+
+            let pool = &route.pools[i];
+            let mut swap_accounts = vec![
+                payout.vault.clone(),
+                pool[0].clone(),
+                pool[1].clone(),
+                payout.vault_atas[i].clone(),
+                target_vault_ata_info.clone(),
+                pool[2].clone(),
+                pool[3].clone(),
+                pool[4].clone(),
+                payout.token_program.clone(),
+                payout.token_program_2022.clone(),
+                route.memo_program.clone(),
+                payout.mints[i].clone(),
+                payout.mints[route.target_index].clone(),
+            ];
+
+            for tick_array in pool[5..].iter() {
+                swap_accounts.push((*tick_array).clone());
+            }
+
+            // Every leg has its own minimum, on top of the one of the whole redemption below
+            raydium_clmm_swap(route.raydium_clmm_program, &swap_accounts, share, route.min_amounts_out[i], vault_seeds)?;
+        }
+
+        let target_after = token_balance(target_vault_ata_info)?;
+        amount_out += target_after.saturating_sub(target_before);
+        msg!("Single asset amount out: {}", amount_out);
+
+        let min_amount_out = route.min_amounts_out[route.target_index];
+        if amount_out < min_amount_out {
+            msg!("Redemption would return less than {}", min_amount_out);
+            return Err(FundError::InvalidSwapDetails.into());
+        }
+
+        transfer_to_recipient(payout, route.target_index, amount_out)?;
+    } else {
+        for i in 0..payout.mints.len() {
+            let vault_balance = token_balance(payout.vault_atas[i])?;
+            msg!("Vault Balance: {}", vault_balance);
+            let amount_to_transfer_u128: u128 = ((vault_balance as u128) * (withdraw_percent as u128))/100_000_000;
+
+            transfer_to_recipient(payout, i, amount_to_transfer_u128 as u64)?;
+        }
+    }
+
+    sync_holdings(payout.holdings, &payout.vault_atas)?;
+
+    // Staked SOL is paid out in kind: the member's share is split into a stake account they control
+    if let Some((stake_program_info, clock_sysvar_info)) = payout.stake_program {
+        let mut vault_data = VaultAccount::try_from_slice(&payout.vault.data.borrow())?;
+        let rent = Rent::get()?;
+        let stake_space = StakeStateV2::size_of();
+        let stake_rent = rent.minimum_balance(stake_space);
+        let minimum_delegation = get_minimum_delegation()?;
+        let nonce_bytes = payout.stake_nonce.to_le_bytes();
+
+        for (stake_index, stake_account_info, member_stake_info) in payout.stakes.iter() {
+            let stake_balance = stake_account_info.lamports().saturating_sub(stake_rent);
+            let share = (((stake_balance as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+            msg!("Stake Share to Split: {}", share);
+
+            if share == 0 {
+                continue;
+            }
+
+            // Too small to be split into its own stake account, the vault pays it from its idle SOL
+            if share < minimum_delegation {
+                let vault_rent = rent.minimum_balance(payout.vault.data_len());
+                if payout.vault.lamports().saturating_sub(vault_rent) < share {
+                    msg!("Vault can't pay out the stake share of {} in lamports", share);
+                    return Err(FundError::NotEnoughFunds.into());
+                }
+
+                **payout.vault.lamports.borrow_mut() -= share;
+                **payout.recipient.lamports.borrow_mut() += share;
+                continue;
+            }
+
+            let (_member_stake_pda, member_stake_bump) = Pubkey::find_program_address(
+                &[b"member-stake", payout.fund.key.as_ref(), payout.recipient.key.as_ref(), &[*stake_index], &nonce_bytes],
+                program_id
+            );
+            invoke_signed(
+                &system_instruction::create_account(
+                    payout.payer.key,
+                    member_stake_info.key,
+                    stake_rent,
+                    stake_space as u64,
+                    stake_program_info.key
+                ),
+                &[payout.payer.clone(), (*member_stake_info).clone(), payout.system_program.clone()],
+                &[&[b"member-stake", payout.fund.key.as_ref(), payout.recipient.key.as_ref(), &[*stake_index], &nonce_bytes, &[member_stake_bump]]]
+            )?;
+
+            let split_instruction = stake_instruction::split(
+                stake_account_info.key,
+                payout.vault.key,
+                share,
+                member_stake_info.key
+            ).pop().ok_or(FundError::InvalidStakeAccount)?;
+
+            invoke_signed(
+                &split_instruction,
+                &[(*stake_account_info).clone(), (*member_stake_info).clone(), payout.vault.clone(), stake_program_info.clone()],
+                &[vault_seeds]
+            )?;
+
+            for stake_authorize in [StakeAuthorize::Staker, StakeAuthorize::Withdrawer] {
+                invoke_signed(
+                    &stake_instruction::authorize(
+                        member_stake_info.key,
+                        payout.vault.key,
+                        payout.recipient.key,
+                        stake_authorize,
+                        None
+                    ),
+                    &[(*member_stake_info).clone(), clock_sysvar_info.clone(), payout.vault.clone(), stake_program_info.clone()],
+                    &[vault_seeds]
+                )?;
+            }
+
+            // Only principal is tracked, the member takes the same part of it as of the balance
+            if let Some((_, principal)) = vault_data.stake_accounts.iter_mut().find(|(index, _)| index == stake_index) {
+                let principal_out = (((*principal as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+                *principal -= principal_out;
+                vault_data.staked_lamports = vault_data.staked_lamports.saturating_sub(principal_out);
+            }
+        }
+
+        vault_data.serialize(&mut &mut payout.vault.data.borrow_mut()[..])?;
+    }
+
+    // The member's receipt tokens left the vault above, shrink the positions by the same share
+    for position_account_info in payout.positions.iter() {
+        let mut position_data = Position::try_from_slice(&position_account_info.data.borrow())?;
+        let receipt_out = (((position_data.receipt_amount as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+        let principal_out = (((position_data.deposited_amount as u128) * (withdraw_percent as u128))/100_000_000) as u64;
+        position_data.receipt_amount -= receipt_out;
+        position_data.deposited_amount -= principal_out;
+        position_data.serialize(&mut &mut position_account_info.data.borrow_mut()[..])?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_withdraw_or_leave_from_light_fund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    task: u8,
+    stake_percent: u64,
+    num_of_tokens: u8,
+    num_of_stakes: u8,
+    num_of_positions: u8,
+    single_asset: Option<(u8, u8, Vec<u64>)>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let member_wallet_info = next_account_info(accounts_iter)?;
+    let member_account_info = next_account_info(accounts_iter)?;
+    let fund_account_info = next_account_info(accounts_iter)?;
+    let vault_account_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let ata_program_info = next_account_info(accounts_iter)?;
+    let rent_sysvar_info = next_account_info(accounts_iter)?;
+    let membership_info = next_account_info(accounts_iter)?;
+
+    msg!("Stake Percent: {}", stake_percent);
+
+    if stake_percent > 100_000_000 {
+        return Err(FundError::InvalidStakePercent.into());
+    }
+
+    if !member_wallet_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    if task == 1 && stake_percent != 100_000_000 {
+        msg!("Task does not match the data provided.");
+        return Err(FundError::InvalidInstruction.into());
+    }
+
+    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (member_pda, _joiner_bump) = Pubkey::find_program_address(&[b"user", member_wallet_info.key.as_ref()], program_id);
+    let (vault_pda, _vault_bump) = Pubkey::find_program_address(&[b"vault", fund_account_info.key.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *member_account_info.key != member_pda || *vault_account_info.key != vault_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut membership = load_member(program_id, membership_info, fund_account_info.key, member_wallet_info.key)?;
+    let payout = next_member_payout(
+        program_id,
+        accounts_iter,
+        member_wallet_info,
+        member_wallet_info,
+        fund_account_info,
+        vault_account_info,
+        [token_program_info, system_program_info, ata_program_info, rent_sysvar_info],
+        membership.stake_payouts,
+        num_of_tokens,
+        num_of_stakes,
+        num_of_positions,
+        single_asset
+    )?;
+
+    let mut member_data = UserAccount::try_from_slice(&member_account_info.data.borrow())?;
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let governance = next_governance_accounts(accounts_iter, &fund_data, member_wallet_info.key)?;
+
+    if current_time < membership.last_deposit_time + fund_data.lockup_period {
+        msg!("Deposit is locked until {}", membership.last_deposit_time + fund_data.lockup_period);
+        return Err(FundError::DepositLocked.into());
+    }
+
+    // With a notice period the withdrawal has to be requested first, and it's claimed here
+    if fund_data.notice_period != 0 {
+        let request_account_info = next_account_info(accounts_iter)?;
+        let (request_pda, _request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_account_info.key.as_ref(), member_wallet_info.key.as_ref()], program_id);
+        if *request_account_info.key != request_pda || request_account_info.data_is_empty() {
+            return Err(FundError::InvalidWithdrawalRequest.into());
+        }
+
+        let request_data = WithdrawalRequest::try_from_slice(&request_account_info.data.borrow())?;
+        if request_data.task != task || request_data.stake_percent != stake_percent {
+            return Err(FundError::InvalidWithdrawalRequest.into());
+        }
+
+        if current_time < request_data.claimable_at {
+            msg!("Withdrawal can be claimed from {}", request_data.claimable_at);
+            return Err(FundError::WithdrawalNotClaimable.into());
+        }
+
+        let request_lamports = request_account_info.lamports();
+        **request_account_info.lamports.borrow_mut() = 0;
+        **member_wallet_info.lamports.borrow_mut() += request_lamports;
+    }
+
+    let member_deposit = membership.deposit;
+    let total_deposit = fund_data.total_deposit;
+    msg!("Fund's Total Deposit before Withdrawal: {}", total_deposit);
+    msg!("Member Deposit before Withdrawal: {}", member_deposit);
+    if member_deposit > total_deposit {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if total_deposit != 0 {
+        let withdraw_percent_u128: u128 = ((stake_percent as u128) * (member_deposit as u128))/(total_deposit as u128);
+        let withdraw_percent = withdraw_percent_u128 as u64;
+        msg!("Withdraw percent overall: {}", withdraw_percent);
+
+        if withdraw_percent != 0 {
+            pay_out_member_share(program_id, &payout, withdraw_percent)?;
+            membership.stake_payouts += 1;
+        }
+    }
+
+    let shares_out = (((membership.deposit as u128) * (stake_percent as u128))/100_000_000) as u64;
+    fund_data.total_deposit -= shares_out;
+    msg!("Fund's Total Deposit after Withdrawal: {}", fund_data.total_deposit);
+
+    if let Some((governance_mint_info, governance_token_account_info, token_program_2022_info)) = governance {
+        burn_governance_tokens(
+            token_program_2022_info,
+            governance_mint_info,
+            governance_token_account_info,
+            fund_account_info,
+            shares_out,
+            &[b"light-fund", fund_name.as_bytes(), &[fund_bump]]
+        )?;
+    }
+
+    if task == 1 {
+        // Membership goes back to the member, who paid for it when joining
+        close_membership(membership_info, &membership, member_wallet_info, &mut fund_data);
+        member_data.funds_joined -= 1;
+
+        if fund_data.creator_exists && fund_data.creator == *member_wallet_info.key {
+            fund_data.creator_exists = false;
+        }
+    } else {
+        if total_deposit != 0 && stake_percent != 0 {
+            membership.deposit -= shares_out;
+            msg!("Member Deposit After Withdrawal: {}", membership.deposit);
+        }
+        membership.serialize(&mut &mut membership_info.data.borrow_mut()[..])?;
+    }
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    member_data.serialize(&mut &mut member_account_info.data.borrow_mut()[..])?;
+
+    msg!("Transferred holdings successfully");
+
+    Ok(())
+}
+
+// fn process_init_fund_account<'a>(
+//     program_id: &Pubkey,
+//     accounts: &'a [AccountInfo<'a>],
+//     fund_name: String,
+//     privacy: u8,
+//     expected_members: u32,
+//     symbol_str: String,
+// ) -> ProgramResult {
+//     let current_time = Clock::get()?.unix_timestamp;
+
+//     let accounts_iter = &mut accounts.iter();
+//     let governance_mint_info = next_account_info(accounts_iter)?; // Governance Mint .........................
+//     let vault_account_info = next_account_info(accounts_iter)?; // Vault PDA Account .........................
+//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...........................
+//     let token_program_2022_info = next_account_info(accounts_iter)?; // Token Program (2022) .................
+//     let fund_account_info = next_account_info(accounts_iter)?; // Fund PDA Account ...........................
+//     let creator_wallet_info = next_account_info(accounts_iter)?; // Creator Wallet Address ...................
+//     let rent_sysvar_info = next_account_info(accounts_iter)?; // Rent Sysvar .................................
+//     let user_account_info = next_account_info(accounts_iter)?; // Global User Account ........................
+//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // first proposal aggregator ...........
+//     let join_proposal_aggregator_info = next_account_info(accounts_iter)?; // join proposal aggregator .......
+
+//     // Creator should be signer
+//     if !creator_wallet_info.is_signer {
+//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
+//         return Err(FundError::MissingRequiredSignature.into());
+//     }
+
+//     let index: u8 = 0;
+
+//     // Deriving required PDAs
+//     let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+//     let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+//     let (governance_mint, governance_bump) = Pubkey::find_program_address(&[b"governance", fund_pda.as_ref()], program_id);
+//     let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", creator_wallet_info.key.as_ref()], program_id);
+//     let (proposal_pda, proposal_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
+//     let (join_aggregator_pda, join_aggregator_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
+
+//     // Check if any of the provided PDA differes from the derived
+//     if *fund_account_info.key != fund_pda ||
+//        *vault_account_info.key != vault_pda ||
+//        *governance_mint_info.key != governance_mint ||
+//        *user_account_info.key != user_pda ||
+//        *proposal_aggregator_info.key != proposal_pda ||
+//        *join_proposal_aggregator_info.key != join_aggregator_pda {
+//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+
+//     // Check if the an account already exists on that PDA
+//     if fund_account_info.lamports() > 0 {
+//         msg!("[FUND-ERROR] {} {} Fund already exists!!", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+
+//     // Calculate Rent
+//     let rent = &Rent::from_account_info(rent_sysvar_info)?;
+//     let fund_space = 150 as usize;
+//     let vault_space = 40 as usize;
+//     let extensions = vec![ExtensionType::NonTransferable, ExtensionType::MetadataPointer];
+//     let base_mint_space = ExtensionType::try_calculate_account_len::<Mint>(&extensions)?;
+//     let token_name = fund_name.clone();
+//     let token_symbol = String::from(symbol_str.clone());
+//     let token_uri = "".to_string();
+//     let mint_space = base_mint_space;
+//     let proposal_space = 37 as usize;
+//     let join_proposal_space = 37 as usize;
+
+//     // Creating the Fund Account PDA
+//     invoke_signed(
+//         &system_instruction::create_account(
+//             creator_wallet_info.key,
+//             fund_account_info.key,
+//             rent.minimum_balance(fund_space),
+//             fund_space as u64,
+//             program_id,
+//         ),
+//         &[creator_wallet_info.clone(), fund_account_info.clone(), system_program_info.clone()],
+//         &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
+//     )?;
+
+//     // Creating the Vault Account PDA
+//     invoke_signed(
+//         &system_instruction::create_account(
+//             creator_wallet_info.key,
+//             vault_account_info.key,
+//             rent.minimum_balance(vault_space),
+//             vault_space as u64,
+//             program_id,
+//         ),
+//         &[creator_wallet_info.clone(), vault_account_info.clone(), system_program_info.clone()],
+//         &[&[b"vault", fund_pda.as_ref(), &[vault_bump]]],
+//     )?;
+
+//     // Creating the Governance Mint account
+//     invoke_signed(
+//         &system_instruction::create_account(
+//             creator_wallet_info.key,
+//             governance_mint_info.key,
+//             rent.minimum_balance(mint_space),
+//             mint_space as u64,
+//             token_program_2022_info.key,
+//         ),
+//         &[creator_wallet_info.clone(), governance_mint_info.clone(), system_program_info.clone(), token_program_2022_info.clone()],
+//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
+//     )?;
+
+//     invoke_signed(
+//         &spl_token_2022::instruction::initialize_non_transferable_mint(
+//             &spl_token_2022::id(),
+//             governance_mint_info.key,
+//         )?,
+//         &[governance_mint_info.clone(), token_program_2022_info.clone()],
+//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]]
+//     )?;
+//     invoke_signed(
+//         &metadata_pointer::instruction::initialize(
+//             &spl_token_2022::id(),
+//             governance_mint_info.key,
+//             Some(*fund_account_info.key),
+//             Some(*governance_mint_info.key),
+//         )?,
+//         &[governance_mint_info.clone(), rent_sysvar_info.clone(), token_program_2022_info.clone()],
+//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
+//     )?;
+//     invoke_signed(
+//         &spl_token_2022::instruction::initialize_mint2(
+//             &spl_token_2022::id(),
+//             governance_mint_info.key,
+//             fund_account_info.key,
+//             Some(fund_account_info.key),
+//             0,
+//         )?,
+//         &[
+//             governance_mint_info.clone(),
+//             rent_sysvar_info.clone(),
+//             // token_program_2022_info.clone(),
+//             // fund_account_info.clone()
+//         ],
+//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
+//     )?;
+
+
+//     // creating the proposal aggregator account
+//     invoke_signed(
+//         &system_instruction::create_account(
+//             creator_wallet_info.key,
+//             proposal_aggregator_info.key,
+//             rent.minimum_balance(proposal_space),
+//             proposal_space as u64,
+//             program_id
+//         ),
+//         &[creator_wallet_info.clone(), system_program_info.clone(), proposal_aggregator_info.clone()],
+//         &[&[b"proposal-aggregator", &[index], fund_pda.as_ref(), &[proposal_bump]]]
+//     )?;
+
+//     // creating the joining proposal aggregator account
+//     if privacy == 1 {
+//         invoke_signed(
+//             &system_instruction::create_account(
+//                 creator_wallet_info.key,
+//                 join_proposal_aggregator_info.key,
+//                 rent.minimum_balance(join_proposal_space),
+//                 join_proposal_space as u64,
+//                 program_id
+//             ),
+//             &[creator_wallet_info.clone(), system_program_info.clone(), join_proposal_aggregator_info.clone()],
+//             &[&[b"join-proposal-aggregator", &[index], fund_pda.as_ref(), &[join_aggregator_bump]]]
+//         )?;
+//     }
+
+//     let current_governance_lamports = governance_mint_info.lamports();
+//     let new_governance_rent = rent.minimum_balance(322 + fund_name.len() + symbol_str.len());
+//     let transfer_amount = new_governance_rent - current_governance_lamports;
+//     invoke(
+//         &system_instruction::transfer(
+//             creator_wallet_info.key,
+//             governance_mint_info.key,
+//             transfer_amount + 1
+//         ),
+//         &[creator_wallet_info.clone(), governance_mint_info.clone(), system_program_info.clone()]
+//     )?;
+
+//     invoke_signed(
+//         &spl_token_metadata_interface::instruction::initialize(
+//             &spl_token_2022::id(),
 //             governance_mint_info.key,
 //             fund_account_info.key,
-//             Some(fund_account_info.key),
-//             0,
-//         )?,
+//             governance_mint_info.key,
+//             fund_account_info.key,
+//             token_name,
+//             token_symbol,
+//             token_uri
+//         ),
+//         &[
+//             governance_mint_info.clone(),
+//             fund_account_info.clone(),
+//             fund_account_info.clone(),
+//             token_program_2022_info.clone()
+//         ],
+//         &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
+//     )?;
+
+//     // Converting the fund_name to an array of u8 of fixed size 32
+//     let bytes = fund_name.as_bytes();
+//     let mut array = [0u8; 26];
+//     let len = bytes.len().min(26);
+//     array[..len].copy_from_slice(&bytes[..len]);
+
+//     let members: Vec<Pubkey> = vec![*creator_wallet_info.key];
+//     let mut is_refunded = false;
+//     if expected_members == 1 {
+//         is_refunded = true;
+//     }
+
+//     // Deserialization and Serialization of Fund data
+//     let fund_data = FundAccount {
+//         name: array,
+//         is_refunded,
+//         expected_members,
+//         creator_exists: true,
+//         total_deposit: 0 as u64,
+//         governance_mint: *governance_mint_info.key,
+//         vault: *vault_account_info.key,
+//         current_proposal_index: 0 as u8,
+//         created_at: current_time,
+//         is_private: privacy,
+//         members,
+//     };
+//     fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+
+//     // Deserializing the User Global PDA
+//     let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
+
+//     // Calculate current size and new size of User PDA
+//     let current_size = user_account_info.data_len();
+//     let new_size = current_size + 50;
+
+//     // Calculate current min rent-exempt and new rent-exempt
+//     let new_min_balance = rent.minimum_balance(new_size);
+//     let current_balance = user_account_info.lamports();
+
+//     // Deposit lamports if required
+//     if new_min_balance > current_balance {
+//         invoke(
+//             &system_instruction::transfer(
+//                 creator_wallet_info.key,
+//                 user_account_info.key,
+//                 new_min_balance - current_balance,
+//             ),
+//             &[creator_wallet_info.clone(), user_account_info.clone(), system_program_info.clone()],
+//         )?;
+//     }
+
+//     // Reallocation for new bytes
+//     user_account_info.realloc(new_size, false)?;
+//     // Add the new fund details to user funds
+//     user_data.funds.push(UserSpecific {
+//         fund: *fund_account_info.key,
+//         governance_token_balance: 0 as u64,
+//         is_pending: false,
+//         is_eligible: true,
+//         join_time: current_time
+//     });
+//     user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+
+//     // Deserialization and Serialization of Vault Account Data
+//     let vault_data = VaultAccount {
+//         last_deposit_time: 0,
+//     };
+//     vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+
+//     let proposals: Vec<Proposal> = vec![];
+
+//     let proposal_aggregator_data = ProposalAggregatorAccount {
+//         index: 0 as u8,
+//         proposals
+//     };
+
+//     proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+
+//     if privacy == 1 {
+//         let join_proposals: Vec<JoinProposal> = vec![];
+
+//         let join_proposal_aggreagtor_data = JoinProposalAggregator {
+//             fund: *fund_account_info.key,
+//             index: 0 as u8,
+//             join_proposals,
+//         };
+
+//         join_proposal_aggreagtor_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
+//     }
+
+//     msg!("[FUND-ACTIVITY] {} {} {} Fund created by: {}", fund_account_info.key.to_string(), current_time, fund_name, creator_wallet_info.key.to_string());
+
+//     Ok(())
+// }
+
+
+fn process_init_user_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cid: String,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let creator_account_info = next_account_info(accounts_iter)?; // User Wallet
+    let user_account_info = next_account_info(accounts_iter)?; // User PDA Account to be created
+    let system_program_info = next_account_info(accounts_iter)?; // System Program
+
+    // User should be the signer
+    if !creator_account_info.is_signer {
+        msg!("[USER-ERROR] {} Wrong signer!(must be your wallet)", creator_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    // Derive User PDA and check if provided is same as expected
+    let (user_pda, user_bump) = Pubkey::find_program_address(&[b"user", creator_account_info.key.as_ref()], program_id);
+    if *user_account_info.key != user_pda {
+        msg!("[USER-ERROR] {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", creator_account_info.key.to_string());
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    // If user PDA already exists
+    if !user_account_info.data_is_empty() {
+        msg!("[USER-ERROR] {} User already exists", creator_account_info.key.to_string());
+        return Ok(());
+    }
+
+    // Calculate rent-exempt
+    let rent = Rent::get()?;
+    let user_space = 59 + 4 + 4;
+    
+    // Create the User PDA Account which is it's global identity
+    invoke_signed(
+        &system_instruction::create_account(
+            creator_account_info.key,
+            user_account_info.key,
+            rent.minimum_balance(user_space),
+            user_space as u64,
+            program_id
+        ),
+        &[creator_account_info.clone(), user_account_info.clone(), system_program_info.clone()],
+        &[&[b"user", creator_account_info.key.as_ref(), &[user_bump]]]
+    )?;
+
+    let bytes = cid.as_bytes();
+    let mut array = [0u8; 59];
+    let len = bytes.len().min(59);
+    array[..len].copy_from_slice(&bytes[..len]);
+    // Deserialization and Serialization of User Account Data
+    // Initially user is joined in no Funds
+    let user_data = UserAccount {
+        user_cid: array,
+        funds_joined: 0,
+        pending_invites: 0,
+    };
+    user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+
+}
+
+fn process_init_join_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    deadline: i64,
+) -> ProgramResult {
+    let creation_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let joiner_account_info = next_account_info(accounts_iter)?; // joiner wallet ............................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...............................
+    let join_proposal_info = next_account_info(accounts_iter)?; // join proposal ..............................
+    let joiner_membership_info = next_account_info(accounts_iter)?; // joiner's membership PDA ................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...........................
+
+    if !joiner_account_info.is_signer {
+        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (join_proposal_pda, join_proposal_bump) = Pubkey::find_program_address(&[b"join-proposal", fund_pda.as_ref(), joiner_account_info.key.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *join_proposal_info.key != join_proposal_pda {
+        msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if !join_proposal_info.data_is_empty() {
+        msg!("[FUND-ERROR] {} {} You have already applied for this fund! If want to apply again, delete your existing proposal in the 'Pending Funds' section.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::AlreadyAppliedForEntry.into());
+    }
+
+    if deadline <= creation_time {
+        return Err(FundError::DeadlineReached.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if fund_data.privacy != 0 {
+        msg!("[FUND-ERROR] {} {} This fund is not private.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::InvalidFundDetails.into());
+    }
+
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
+
+    if fund_data.member_count >= fund_data.max_members {
+        msg!("[FUND-ERROR] {} {} Fund is already full.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::FundAlreadyFull.into());
+    }
+
+    // Members and invitees can't ask to join
+    if load_membership(program_id, joiner_membership_info, fund_account_info.key, joiner_account_info.key)?.is_some() {
+        msg!("[FUND-ERROR] {} {} Either a member already, or has a pending invitation for this fund!", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::AlreadyMember.into());
+    }
+
+    // Joiner escrows what joining costs, so members' votes can admit them right away
+    let rent = Rent::get()?;
+    let escrow = rent.minimum_balance(MEMBERSHIP_SPACE) + creation_cost_share(&fund_data);
+    let proposal_space = 108;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            joiner_account_info.key,
+            join_proposal_info.key,
+            rent.minimum_balance(proposal_space) + escrow,
+            proposal_space as u64,
+            program_id
+        ),
+        &[joiner_account_info.clone(), join_proposal_info.clone(), system_program_info.clone()],
+        &[&[b"join-proposal", fund_pda.as_ref(), joiner_account_info.key.as_ref(), &[join_proposal_bump]]]
+    )?;
+
+    let join_proposal = JoinProposal {
+        fund: fund_pda,
+        joiner: *joiner_account_info.key,
+        votes_yes: 0,
+        votes_no: 0,
+        creation_time,
+        deadline,
+        escrow,
+        voters: vec![],
+    };
+
+    join_proposal.serialize(&mut &mut join_proposal_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} {} created proposal to join the fund", fund_account_info.key.to_string(), creation_time, fund_name, joiner_account_info.key.to_string());
+
+    Ok(())
+}
+
+fn process_vote_on_join_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vote: u8,
+    fund_name: String,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let voter_account_info = next_account_info(accounts_iter)?; // Voter Wallet ..................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
+    let join_proposal_info = next_account_info(accounts_iter)?; // Join proposal .................................
+    let voter_membership_info = next_account_info(accounts_iter)?; // Voter's membership PDA .....................
+    let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
+
+    if !voter_account_info.is_signer {
+        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), voter_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    if *fund_account_info.key != fund_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut join_proposal = load_join_proposal(program_id, join_proposal_info, fund_account_info.key)?;
+
+    if join_proposal.deadline < current_time {
+        return Err(FundError::VotingCeased.into());
+    }
+
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut voter_member = load_member(program_id, voter_membership_info, fund_account_info.key, voter_account_info.key)?;
+
+    if !has_role(&voter_member, ROLE_VOTER) {
+        msg!("[FUND-ERROR] {} {} You don't have the permission to vote.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
+        return Err(FundError::MissingPermission.into());
+    }
+
+    if join_proposal.voters.iter().any(|voter| voter.0 == *voter_account_info.key) {
+        msg!("[FUND-ERROR] {} {} You have voted for this proposal already.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
+        return Err(FundError::AlreadyVoted.into());
+    }
+
+    let weight = vote_weight(&fund_data, &voter_member);
+    if weight == 0 {
+        return Err(FundError::NoVotingPower.into());
+    }
+    lock_voting_shares(&fund_data, voter_membership_info, &mut voter_member, join_proposal.deadline)?;
+
+    if vote == 0 {
+        join_proposal.votes_no += weight;
+    } else {
+        join_proposal.votes_yes += weight;
+    }
+
+    msg!("[FUND-ACTIVITY] {} {} {} {} voted for addition of {}", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), join_proposal.joiner.to_string());
+
+    // Yes votes already settle it, the joiner is let in with this vote
+    if votes_decisive(join_proposal.votes_yes, voting_rules(&fund_data)) {
+        admit_joiner(program_id, accounts_iter, fund_account_info, join_proposal_info, system_program_info, &mut fund_data, &join_proposal, &fund_name, current_time)?;
+        fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+        return Ok(());
+    }
+
+    join_proposal.voters.push((*voter_account_info.key, vote));
+
+    let current_proposal_size = join_proposal_info.data_len();
+    let new_proposal_size = current_proposal_size + 33;
+    let current_proposal_rent = Rent::get()?.minimum_balance(current_proposal_size);
+    let new_proposal_rent = Rent::get()?.minimum_balance(new_proposal_size);
+
+    invoke(
+        &system_instruction::transfer(
+            voter_account_info.key,
+            join_proposal_info.key,
+            new_proposal_rent - current_proposal_rent
+        ),
+        &[voter_account_info.clone(), join_proposal_info.clone(), system_program_info.clone()]
+    )?;
+
+    join_proposal_info.realloc(new_proposal_size, false)?;
+    join_proposal.serialize(&mut &mut join_proposal_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn process_cancel_join_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let joiner_account_info = next_account_info(accounts_iter)?; // joiner wallet ............................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...............................
+    let join_proposal_info = next_account_info(accounts_iter)?; // join proposal ..............................
+
+    if !joiner_account_info.is_signer {
+        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    if *fund_account_info.key != fund_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let join_proposal = load_join_proposal(program_id, join_proposal_info, fund_account_info.key)?;
+    if join_proposal.joiner != *joiner_account_info.key {
+        msg!("[FUND-ERROR] {} {} Wrong joiner/proposer wallet address.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    // Escrow and rent go back to the joiner, the voters' share included
+    close_join_proposal(join_proposal_info, joiner_account_info);
+
+    msg!("[FUND-ACTIVITY] {} {} {} {} withdrew the request to join", fund_account_info.key.to_string(), current_time, fund_name, joiner_account_info.key.to_string());
+
+    Ok(())
+}
+
+/// Admits the joiner of a join proposal that passed once its voting is over. Anyone can crank it,
+/// the joiner's escrow pays for the membership.
+fn process_add_member(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let cranker_account_info = next_account_info(accounts_iter)?; // any signer ..................................
+    let fund_account_info = next_account_info(accounts_iter)?; // Fund Account ..................................
+    let join_proposal_info = next_account_info(accounts_iter)?; // Join proposal .................................
+    let system_program_info = next_account_info(accounts_iter)?; // System Program ..............................
+
+    if !cranker_account_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    if *fund_account_info.key != fund_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let join_proposal = load_join_proposal(program_id, join_proposal_info, fund_account_info.key)?;
+    if current_time <= join_proposal.deadline {
+        msg!("The join proposal is still under voting. {}, {}", current_time, join_proposal.deadline);
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if !votes_passed(join_proposal.votes_yes, join_proposal.votes_no, voting_rules(&fund_data)) {
+        msg!("[FUND-ERROR] {} {} Not enough votes to join the fund.", fund_account_info.key.to_string(), join_proposal.joiner.to_string());
+        return Err(FundError::NotEnoughVotes.into());
+    }
+
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
+
+    admit_joiner(program_id, accounts_iter, fund_account_info, join_proposal_info, system_program_info, &mut fund_data, &join_proposal, &fund_name, current_time)?;
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Loads a join proposal of the fund after checking its address.
+fn load_join_proposal(program_id: &Pubkey, join_proposal_info: &AccountInfo, fund: &Pubkey) -> Result<JoinProposal, ProgramError> {
+    if join_proposal_info.data_is_empty() {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    let join_proposal = JoinProposal::try_from_slice(&join_proposal_info.data.borrow())?;
+    let (join_proposal_pda, _join_proposal_bump) = Pubkey::find_program_address(&[b"join-proposal", fund.as_ref(), join_proposal.joiner.as_ref()], program_id);
+    if *join_proposal_info.key != join_proposal_pda || join_proposal.fund != *fund {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    Ok(join_proposal)
+}
+
+/// Admits the joiner of an approved join proposal and closes the proposal. The membership rent
+/// and the creation cost share come out of the joiner's escrow, the rest goes back to the joiner.
+/// The caller serializes the fund.
+#[allow(clippy::too_many_arguments)]
+fn admit_joiner<'a, 'b>(
+    program_id: &Pubkey,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    fund_account_info: &AccountInfo<'a>,
+    join_proposal_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    fund_data: &mut LightFundAccount,
+    join_proposal: &JoinProposal,
+    fund_name: &str,
+    current_time: i64,
+) -> ProgramResult {
+    let joiner_wallet_info = next_account_info(accounts_iter)?; // joiner wallet ..............................
+    let joiner_account_info = next_account_info(accounts_iter)?; // joiner's user account ......................
+    let joiner_membership_info = next_account_info(accounts_iter)?; // joiner's membership PDA ..................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ........................
+
+    let (joiner_pda, _joiner_bump) = Pubkey::find_program_address(&[b"user", join_proposal.joiner.as_ref()], program_id);
+    let (membership_pda, membership_bump) = Pubkey::find_program_address(&[b"member", fund_account_info.key.as_ref(), join_proposal.joiner.as_ref()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+
+    if *joiner_wallet_info.key != join_proposal.joiner ||
+       *joiner_account_info.key != joiner_pda ||
+       *joiner_membership_info.key != membership_pda ||
+       *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if !joiner_membership_info.data_is_empty() {
+        return Err(FundError::AlreadyMember.into());
+    }
+
+    let membership_rent = Rent::get()?.minimum_balance(MEMBERSHIP_SPACE);
+    let creation_share = creation_cost_share(fund_data);
+    if membership_rent + creation_share > join_proposal.escrow {
+        return Err(FundError::NotEnoughFunds.into());
+    }
+
+    // Membership PDA is funded from the escrow, then allocated and handed over to the program
+    **join_proposal_info.lamports.borrow_mut() -= membership_rent;
+    **joiner_membership_info.lamports.borrow_mut() += membership_rent;
+    let membership_seeds: &[&[u8]] = &[b"member", fund_account_info.key.as_ref(), join_proposal.joiner.as_ref(), &[membership_bump]];
+    invoke_signed(
+        &system_instruction::allocate(joiner_membership_info.key, MEMBERSHIP_SPACE as u64),
+        &[joiner_membership_info.clone(), system_program_info.clone()],
+        &[membership_seeds]
+    )?;
+    invoke_signed(
+        &system_instruction::assign(joiner_membership_info.key, program_id),
+        &[joiner_membership_info.clone(), system_program_info.clone()],
+        &[membership_seeds]
+    )?;
+
+    // Joiner's share of the fund's creation cost goes to the rent reserve
+    if creation_share != 0 {
+        **join_proposal_info.lamports.borrow_mut() -= creation_share;
+        **rent_reserve_info.lamports.borrow_mut() += creation_share;
+        fund_data.rent_collected += creation_share;
+    }
+
+    let mut membership = pending_membership(fund_account_info.key, &join_proposal.joiner, &Pubkey::default(), 0, current_time);
+    admit_member(fund_data, &mut membership, current_time)?;
+    membership.serialize(&mut &mut joiner_membership_info.data.borrow_mut()[..])?;
+
+    let mut joiner_data = UserAccount::try_from_slice(&joiner_account_info.data.borrow())?;
+    joiner_data.funds_joined += 1;
+    joiner_data.serialize(&mut &mut joiner_account_info.data.borrow_mut()[..])?;
+
+    refund_fund_creator(accounts_iter, fund_account_info, fund_data, rent_reserve_info, fund_name, current_time)?;
+
+    close_join_proposal(join_proposal_info, joiner_wallet_info);
+
+    msg!("[FUND-ACTIVITY] {} {} {} Member joined: {}", fund_account_info.key.to_string(), current_time, fund_name, joiner_wallet_info.key.to_string());
+
+    Ok(())
+}
+
+/// Closes a join proposal, what's left of its lamports going back to the joiner.
+fn close_join_proposal(join_proposal_info: &AccountInfo, joiner_wallet_info: &AccountInfo) {
+    let lamports = join_proposal_info.lamports();
+    **join_proposal_info.lamports.borrow_mut() = 0;
+    **joiner_wallet_info.lamports.borrow_mut() += lamports;
+
+    let mut data = join_proposal_info.data.borrow_mut();
+    for byte in data.iter_mut() {
+        *byte = 0;
+    }
+}
+
+/// Deposits tokens into a fund for its member. With an allowlist proof it is a public fund's entry
+/// instead: the depositor is admitted first, pays for the membership and buys in with this deposit.
+/// The fund's fee account follows the governance accounts. Once the fund prices its assets, and
+/// always for a public entry, shares are bought at the NAV per share: the SOL price update, the
+/// deposited mint's price update and each of the vault's `num_of_tokens` holdings (token account,
+/// mint, price update) follow it, and `mint_amount` only prices a fund's first shares.
+#[allow(clippy::too_many_arguments)]
+fn process_init_deposit_token(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    is_unwrapped_sol: u8,
+    amount: u64,
+    mint_amount: u64,
+    num_of_tokens: u8,
+    fund_name: String,
+    fund_type: u8,
+    allowlist_proof: Option<Vec<[u8; 32]>>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let member_account_info = next_account_info(accounts_iter)?; // Depositor wallet .................................
+    let member_ata_info = next_account_info(accounts_iter)?; // Depositor's ATA for the depositing mint ..............
+    let vault_account_info = next_account_info(accounts_iter)?; // Vault PDA .........................................
+    let vault_ata_info = next_account_info(accounts_iter)?; // Vault PDA's ATA for the depositing mint ...............
+    let mint_account_info = next_account_info(accounts_iter)?; // Mint account of token to be deposited ..............
+    let token_program_info = next_account_info(accounts_iter)?; // Token Progarm .....................................
+    let ata_program_info = next_account_info(accounts_iter)?; // Associated Token Program ............................
+    let fund_account_info = next_account_info(accounts_iter)?; // Fund PDA ...........................................
+    let user_account_info = next_account_info(accounts_iter)?; // user global account ................................
+    let system_program_info = next_account_info(accounts_iter)?; // System program ...................................
+    let rent_sysvar_info = next_account_info(accounts_iter)?; // Rent Sysvar Account .................................
+    let protocol_config_info = next_account_info(accounts_iter)?; // Protocol config PDA ............................
+    let treasury_info = next_account_info(accounts_iter)?; // Protocol treasury PDA ..................................
+    let treasury_ata_info = next_account_info(accounts_iter)?; // Treasury's ATA for the depositing mint ............
+    let holdings_info = next_account_info(accounts_iter)?; // Fund's vault holdings registry .........................
+    let membership_info = next_account_info(accounts_iter)?; // Depositor's membership PDA ...........................
+
+    // Depositor should be signer
+    if !member_account_info.is_signer {
+        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), member_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    // Derive the PDAs and check for equality with provided ones
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_account_info.key.as_ref()], program_id);
+    let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", member_account_info.key.as_ref()], program_id);
+    let mut seed = String::from("light-fund");
+    if fund_type == 1 {
+        seed = String::from("standard-fund");
+    } else if fund_type == 2 {
+        seed = String::from("dao-fund");
+    }
+    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[seed.as_bytes(), fund_name.as_bytes()], program_id);
+    if *vault_account_info.key != vault_pda || *fund_account_info.key != fund_pda || *user_account_info.key != user_pda {
+        msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), member_account_info.key.to_string());
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    // Both token programs are accepted, as long as the mint has no extension the vault can't hold
+    if *token_program_info.key != *mint_account_info.owner {
+        return Err(FundError::InvalidMints.into());
+    }
+    let decimals = supported_mint_decimals(mint_account_info)?;
+
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    check_mint_whitelisted(&fund_data, mint_account_info.key)?;
+
+    if *vault_ata_info.key != get_associated_token_address_with_program_id(&vault_pda, mint_account_info.key, token_program_info.key) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    let protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+    let (treasury_pda, _treasury_bump) = Pubkey::find_program_address(&[b"treasury"], program_id);
+    if *treasury_info.key != treasury_pda || *treasury_ata_info.key != get_associated_token_address_with_program_id(&treasury_pda, mint_account_info.key, token_program_info.key) {
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
+
+    if fund_data.winding_down {
+        msg!("[FUND-ERROR] {} {} Fund is being wound down.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+        return Err(FundError::FundWindingDown.into());
+    }
+
+    // Joiners of a public fund are let in here, the rent reserve taking their creation cost share
+    let (mut membership, joining_rent_reserve_info) = match allowlist_proof {
+        None => {
+            let Some(membership) = load_membership(program_id, membership_info, fund_account_info.key, member_account_info.key)?.filter(|membership| !membership.is_pending) else {
+                msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot deposit in it.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+                return Err(FundError::NotAFundMember.into());
+            };
+            (membership, None)
+        }
+        Some(allowlist_proof) => {
+            let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ......................
+            let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+            if *rent_reserve_info.key != rent_pda {
+                return Err(FundError::InvalidAccountData.into());
+            }
+
+            if fund_type != 0 || fund_data.privacy != 1 {
+                msg!("[FUND-ERROR] {} {} This fund is not public.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+                return Err(FundError::InvalidFundDetails.into());
+            }
+
+            if fund_data.allowlist_root != [0u8; 32] && !verify_allowlist_proof(allowlist_leaf(member_account_info.key), &allowlist_proof, fund_data.allowlist_root) {
+                msg!("[FUND-ERROR] {} {} You are not on this fund's allowlist.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+                return Err(FundError::NotAllowlisted.into());
+            }
+
+            if load_membership(program_id, membership_info, fund_account_info.key, member_account_info.key)?.is_some() {
+                msg!("[FUND-ERROR] {} {} Either a member already, or has a pending invitation for this fund!", fund_account_info.key.to_string(), member_account_info.key.to_string());
+                return Err(FundError::AlreadyMember.into());
+            }
+
+            let mut membership = pending_membership(fund_account_info.key, member_account_info.key, &Pubkey::default(), 0, current_time);
+            admit_member(&mut fund_data, &mut membership, current_time)?;
+            create_membership(program_id, member_account_info, membership_info, system_program_info, &membership)?;
+
+            let creation_share = creation_cost_share(&fund_data);
+            if creation_share != 0 {
+                invoke(
+                    &system_instruction::transfer(
+                        member_account_info.key,
+                        rent_reserve_info.key,
+                        creation_share
+                    ),
+                    &[member_account_info.clone(), rent_reserve_info.clone(), system_program_info.clone()]
+                )?;
+                fund_data.rent_collected += creation_share;
+            }
+
+            let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
+            user_data.funds_joined += 1;
+            user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+
+            (membership, Some(rent_reserve_info))
+        }
+    };
+
+
+    // If depositor's governance token account doesn't exist, create one
+    let governance = next_governance_accounts(accounts_iter, &fund_data, member_account_info.key)?;
+    if let Some((governance_mint_info, governance_token_account_info, token_program_2022_info)) = governance {
+        if governance_token_account_info.data_is_empty() {
+            invoke(
+                &create_associated_token_account(
+                    member_account_info.key,
+                    member_account_info.key,
+                    governance_mint_info.key,
+                    token_program_2022_info.key
+                ),
+                &[
+                    member_account_info.clone(),
+                    governance_token_account_info.clone(),
+                    member_account_info.clone(),
+                    governance_mint_info.clone(),
+                    system_program_info.clone(),
+                    token_program_2022_info.clone(),
+                    ata_program_info.clone(),
+                ]
+            )?;
+        }
+    }
+
+    // Shares are bought at the NAV per share, the vault valued before the deposit lands. Funds
+    // without price feeds can't value their assets, members' deposits there are priced by them.
+    let fee_account_info = next_account_info(accounts_iter)?; // fund's fee account, lists the price feeds .......
+    let (fee_pda, _fee_bump) = Pubkey::find_program_address(&[b"fees", fund_pda.as_ref()], program_id);
+    if *fee_account_info.key != fee_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let entry_pricing = if fee_account_info.data_is_empty() {
+        if joining_rent_reserve_info.is_some() {
+            msg!("[FUND-ERROR] {} {} Public funds need price feeds to price their shares.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+            return Err(FundError::InvalidFeeConfig.into());
+        }
+        None
+    } else {
+        let sol_price_update_info = next_account_info(accounts_iter)?; // SOL price update, values staked SOL ........
+        let deposit_price_update_info = next_account_info(accounts_iter)?; // deposited mint's price update ..........
+
+        let fee_data = FeeAccount::try_from_slice(&fee_account_info.data.borrow())?;
+        let staked_lamports = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?.staked_lamports;
+        let (nav, _holdings) = vault_nav(program_id, accounts_iter, &fee_data, holdings_info, &fund_pda, staked_lamports, sol_price_update_info, num_of_tokens, current_time)?;
+
+        let (price, expo) = read_oracle_price(deposit_price_update_info, &price_feed_of(&fee_data, mint_account_info.key)?, current_time)?;
+        Some((nav, normalize_price(price, expo)?))
+    };
+
+    let rent = &Rent::from_account_info(rent_sysvar_info)?;
+    let rent_req = rent.minimum_balance(TokenAccount::LEN);
+
+    // If vault's token account account for the depositing mint doesn't exist, create it
+    if vault_ata_info.data_is_empty() {
+
+        invoke_signed(
+            &create_associated_token_account(
+                member_account_info.key,
+                vault_account_info.key,
+                mint_account_info.key,
+                token_program_info.key
+            ),
+            &[
+                member_account_info.clone(),
+                vault_ata_info.clone(),
+                vault_account_info.clone(),
+                mint_account_info.clone(),
+                system_program_info.clone(),
+                token_program_info.clone(),
+                ata_program_info.clone(),
+                rent_sysvar_info.clone(),
+            ],
+            &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
+        )?;
+    }
+
+    // let mint: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+    let vault_balance_before = token_balance(vault_ata_info)?;
+
+    if is_unwrapped_sol == 1 {
+        invoke(
+            &system_instruction::create_account(
+                member_account_info.key,
+                member_ata_info.key,
+                rent_req+amount,
+                TokenAccount::LEN as u64,
+                token_program_info.key,
+            ),
+            &[
+                member_account_info.clone(),
+                member_ata_info.clone(),
+                token_program_info.clone(),
+                system_program_info.clone(),
+                rent_sysvar_info.clone(),
+            ]
+        )?;
+
+        invoke(
+            &spl_token::instruction::initialize_account(
+                token_program_info.key,
+                member_ata_info.key,
+                mint_account_info.key,
+                member_account_info.key,
+            )?,
+            &[
+                token_program_info.clone(),
+                member_ata_info.clone(),
+                mint_account_info.clone(),
+                member_account_info.clone(),
+                rent_sysvar_info.clone(),
+            ]
+        )?;
+
+        transfer_tokens(token_program_info, member_ata_info, mint_account_info, vault_ata_info, member_account_info, amount, decimals, &[])?;
+
+        invoke(
+            &spl_token::instruction::close_account(
+                token_program_info.key,
+                member_ata_info.key,
+                member_account_info.key,
+                member_account_info.key,
+                &[]
+            )?,
+            &[
+                token_program_info.clone(),
+                member_account_info.clone(),
+                member_ata_info.clone()
+               ]
+        )?;
+    } else {
+        // Now transfer the required number of tokens from depositor's token account to vault's token account
+        transfer_tokens(token_program_info, member_ata_info, mint_account_info, vault_ata_info, member_account_info, amount, decimals, &[])?;
+    }
+
+    // Transfer-fee mints deliver less than was sent, only what arrived counts
+    let received = token_balance(vault_ata_info)? - vault_balance_before;
+    msg!("Received in vault: {}", received);
+
+
+    // Protocol fee is taken from the deposit, the member is credited for the rest
+    if protocol_config.deposit_fee_bps != 0 && treasury_ata_info.data_is_empty() {
+        invoke(
+            &create_associated_token_account(
+                member_account_info.key,
+                treasury_info.key,
+                mint_account_info.key,
+                token_program_info.key
+            ),
+            &[
+                member_account_info.clone(),
+                treasury_ata_info.clone(),
+                treasury_info.clone(),
+                mint_account_info.clone(),
+                system_program_info.clone(),
+                token_program_info.clone(),
+                ata_program_info.clone(),
+            ]
+        )?;
+    }
+
+    let protocol_fee = collect_protocol_fee(
+        token_program_info,
+        mint_account_info,
+        vault_ata_info,
+        treasury_ata_info,
+        vault_account_info,
+        received,
+        protocol_config.deposit_fee_bps,
+        &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]
+    )?;
+    let deposited = received - protocol_fee;
+    let priced_shares = match entry_pricing {
+        Some((nav, price)) => shares_for_value(token_value(deposited, decimals, price)?, nav, fund_data.total_deposit as u128)?,
+        None => None,
+    };
+    let mint_amount = match priced_shares {
+        Some(shares) => shares,
+        None => ((mint_amount as u128) * (deposited as u128)).checked_div(amount as u128).unwrap_or(0) as u64,
+    };
+
+    // A joiner's first deposit has to reach the fund's minimum, the entry fee stays in the vault
+    // unminted and so goes to the existing members
+    let mint_amount = if joining_rent_reserve_info.is_some() {
+        if mint_amount < fund_data.min_first_deposit {
+            msg!("[FUND-ERROR] {} {} First deposit is below the fund's minimum of {}.", fund_account_info.key.to_string(), member_account_info.key.to_string(), fund_data.min_first_deposit);
+            return Err(FundError::DepositBelowMinimum.into());
+        }
+        mint_amount - ((mint_amount as u128) * (fund_data.entry_fee_bps as u128) / 10_000) as u64
+    } else {
+        mint_amount
+    };
+
+    // Tokens would sit in the vault with nothing to show for them
+    if mint_amount == 0 {
+        msg!("[FUND-ERROR] {} {} Deposit is too small to buy a share.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+        return Err(FundError::DepositTooSmall.into());
+    }
+
+    // Governance tokens follow the internal ledger one to one
+    if let Some((governance_mint_info, governance_token_account_info, token_program_2022_info)) = governance {
+        mint_governance_tokens(
+            token_program_2022_info,
+            governance_mint_info,
+            governance_token_account_info,
+            fund_account_info,
+            mint_amount,
+            &[seed.as_bytes(), fund_name.as_bytes(), &[fund_bump]]
+        )?;
+    }
+
+    // In vault account, set the last deposit time
+    let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+    vault_data.last_deposit_time = current_time;
+    vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+    record_holding(program_id, holdings_info, fund_account_info, member_account_info, system_program_info, mint_account_info, vault_ata_info)?;
+
+    if let Some(rent_reserve_info) = joining_rent_reserve_info {
+        refund_fund_creator(accounts_iter, fund_account_info, &mut fund_data, rent_reserve_info, &fund_name, current_time)?;
+        msg!("[FUND-ACTIVITY] {} {} {} Member joined: {}", fund_account_info.key.to_string(), current_time, fund_name, member_account_info.key.to_string());
+    }
+
+    // In fund account increase the deposited amount (unit lamports)
+    fund_data.total_deposit += mint_amount;
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+
+    membership.deposit += mint_amount;
+    membership.last_deposit_time = current_time;
+    membership.serialize(&mut &mut membership_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Token deposit: {} of {} by {}", fund_account_info.key.to_string(), current_time, fund_name, amount, mint_account_info.key.to_string(), member_account_info.key.to_string());
+
+    Ok(())
+}
+
+
+fn process_init_investment_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    cid: String,
+    deadline: i64,
+    merkel_bytes: MerkleRoot,
+    actions: u8,
+) -> ProgramResult {
+    let creation_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let proposer_account_info = next_account_info(accounts_iter)?;
+    let fund_account_info = next_account_info(accounts_iter)?;
+    let proposal_aggregator_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let new_proposal_aggregator_info = next_account_info(accounts_iter)?;
+    let proposer_membership_info = next_account_info(accounts_iter)?;
+    let voters_info = next_account_info(accounts_iter)?; // new proposal's voter bitmap .......................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve .......................
+
+    msg!("{} {} {}", cid, deadline, fund_name);
+
+    if !proposer_account_info.is_signer {
+        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    // One bit of swaps_status per action
+    if actions == 0 || actions > 16 {
+        msg!("[FUND-ERROR] {} {} A proposal holds between 1 and 16 actions.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::InvalidInstruction.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    msg!("Fund PDA derived");
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+
+    let Some(mut proposer_info) = load_membership(program_id, proposer_membership_info, &fund_pda, proposer_account_info.key)?.filter(|membership| !membership.is_pending) else {
+        msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot create a proposal.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::NotAFundMember.into());
+    };
+
+    if *fund_account_info.key != fund_pda {
+        msg!("[FUND-ERROR] {} {} Wrong fund account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let current_index = fund_data.current_proposal_index;
+
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(
+        &[
+            b"proposal-aggregator",
+            &[current_index],
+            fund_pda.as_ref()
+        ],
+        program_id
+    );
+
+    msg!("Current Derived");
+
+    let (new_proposal_aggregator_pda, new_proposal_aggregator_bump) = Pubkey::find_program_address(
+        &[
+            b"proposal-aggregator",
+            &[current_index + 1],
+            fund_pda.as_ref()
+        ],
+        program_id
+    );
+
+    msg!("New Derived");
+
+    if *proposal_aggregator_info.key != proposal_aggregator_pda || *new_proposal_aggregator_info.key != new_proposal_aggregator_pda {
+        msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    if *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if !has_role(&proposer_info, ROLE_PROPOSER) {
+        msg!("[FUND-ERROR] {} {} You don't have the permission to create proposals.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::MissingPermission.into());
+    }
+
+    if fund_data.winding_down {
+        msg!("[FUND-ERROR] {} {} Fund is being wound down.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+        return Err(FundError::FundWindingDown.into());
+    }
+
+    let bytes = cid.as_bytes();
+    let mut array = [0u8; 59];
+    let len = bytes.len().min(59);
+    array[..len].copy_from_slice(&bytes[..len]);
+
+    let rent = Rent::get()?;
+    let current_slots = proposal_slots(&proposal_aggregator_info.data.borrow());
+    let free_slot = (0..current_slots as u16).find(|vec_index| load_proposal(&proposal_aggregator_info.data.borrow(), *vec_index).is_err());
+
+    // Proposal takes the first freed slot, then the next one, once every slot is taken it opens
+    // the next aggregator
+    let (aggregator_info, aggregator_index, vec_index) = if let Some(vec_index) = free_slot {
+        // Slot's rent is still in the aggregator, the rent reserve fronted it to the last proposer
+        let slot_rent = rent.minimum_balance(PROPOSAL_SPACE) - rent.minimum_balance(0);
+        invoke(
+            &system_instruction::transfer(proposer_account_info.key, rent_reserve_info.key, slot_rent),
+            &[proposer_account_info.clone(), rent_reserve_info.clone(), system_program_info.clone()]
+        )?;
+
+        (proposal_aggregator_info, current_index, vec_index)
+    } else if current_slots >= PROPOSALS_PER_AGGREGATOR {
+        msg!("New is creating");
+        let new_aggregator_size = AGGREGATOR_HEADER_SPACE + PROPOSAL_SPACE;
+        invoke_signed(
+            &system_instruction::create_account(
+                proposer_account_info.key,
+                new_proposal_aggregator_info.key,
+                rent.minimum_balance(new_aggregator_size),
+                new_aggregator_size as u64,
+                program_id
+            ),
+            &[
+                new_proposal_aggregator_info.clone(),
+                proposer_account_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                b"proposal-aggregator",
+                &[current_index + 1],
+                fund_account_info.key.as_ref(),
+                &[new_proposal_aggregator_bump]
+            ]]
+        )?;
+
+        let new_proposal_data = ProposalAggregatorAccount {
+            index: current_index + 1,
+            _padding: [0u8; 7],
+            opener: *proposer_account_info.key,
+        };
+        new_proposal_aggregator_info.data.borrow_mut()[..AGGREGATOR_HEADER_SPACE].copy_from_slice(bytemuck::bytes_of(&new_proposal_data));
+
+        fund_data.current_proposal_index += 1;
+        fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+
+        (new_proposal_aggregator_info, current_index + 1, 0)
+    } else {
+        msg!("Current is enough");
+        let new_aggregator_size = AGGREGATOR_HEADER_SPACE + (current_slots + 1) * PROPOSAL_SPACE;
+        let current_rent_exempt = proposal_aggregator_info.lamports();
+        let new_rent_exempt = rent.minimum_balance(new_aggregator_size);
+
+        if new_rent_exempt > current_rent_exempt {
+            invoke(
+                &system_instruction::transfer(
+                    proposer_account_info.key,
+                    proposal_aggregator_info.key,
+                    new_rent_exempt-current_rent_exempt
+                ),
+                &[proposal_aggregator_info.clone(), proposer_account_info.clone(), system_program_info.clone()]
+            )?;
+        }
+
+        proposal_aggregator_info.realloc(new_aggregator_size, true)?;
+
+        (proposal_aggregator_info, current_index, current_slots as u16)
+    };
+    msg!("Vec Index: {}", vec_index);
+
+    // Voter bitmap covers every current member, the proposer's yes vote included
+    let (voters_pda, voters_bump) = Pubkey::find_program_address(&[b"proposal-voters", fund_pda.as_ref(), &[aggregator_index], &vec_index.to_le_bytes()], program_id);
+    if *voters_info.key != voters_pda {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    let voters_space = voters_bitmap_space(fund_data.next_member_index);
+    invoke_signed(
+        &system_instruction::create_account(
+            proposer_account_info.key,
+            voters_info.key,
+            rent.minimum_balance(voters_space),
+            voters_space as u64,
+            program_id
+        ),
+        &[proposer_account_info.clone(), voters_info.clone(), system_program_info.clone()],
+        &[&[b"proposal-voters", fund_pda.as_ref(), &[aggregator_index], &vec_index.to_le_bytes(), &[voters_bump]]]
+    )?;
+    mark_voted(&mut voters_info.data.borrow_mut(), proposer_info.member_index)?;
+    lock_voting_shares(&fund_data, proposer_membership_info, &mut proposer_info, deadline / 1000)?;
+
+    let mut aggregator_data = aggregator_info.data.borrow_mut();
+    let proposal = proposal_slot_mut(&mut aggregator_data, vec_index)?;
+    *proposal = Proposal {
+        votes_yes: vote_weight(&fund_data, &proposer_info),
+        votes_no: 0,
+        creation_time,
+        deadline,
+        voters_rent: rent.minimum_balance(voters_space),
+        proposer: *proposer_account_info.key,
+        merkel_root: merkel_bytes.0,
+        cid: array,
+        state: ProposalState::Draft as u8,
+        vec_index,
+        swaps_status: 0,
+        actions,
+        _padding: [0u8; 7],
+    };
+    transition_proposal(proposal, ProposalState::Voting, voting_rules(&fund_data), creation_time)?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, aggregator_index, vec_index, proposer_account_info.key.to_string());
+
+    Ok(())
+}
+
+
+fn process_vote_on_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vote: u8,
+    proposal_index: u8,
+    vec_index: u16,
+    fund_name: String,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let voter_account_info = next_account_info(accounts_iter)?; // Voter's Wallet ................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal account ........................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
+    let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
+    let _system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
+    let voter_membership_info = next_account_info(accounts_iter)?; // Voter's membership PDA .....................
+    let voters_info = next_account_info(accounts_iter)?; // Proposal's voter bitmap ...............................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ........................
+
+    msg!("Vec Index: {}", vec_index);
+
+    // Voter needs to be signer
+    if !voter_account_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    // Pdas derivation
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+
+    let (voters_pda, _voters_bump) = Pubkey::find_program_address(&[b"proposal-voters", fund_pda.as_ref(), &[proposal_index], &vec_index.to_le_bytes()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+
+    // Pdas verification
+    if *fund_account_info.key != fund_pda ||
+       *proposal_aggregator_info.key != proposal_aggregator_pda ||
+       *voters_info.key != voters_pda ||
+       *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut voter_info = load_member(program_id, voter_membership_info, fund_account_info.key, voter_account_info.key)?;
+
+    if !has_role(&voter_info, ROLE_VOTER) {
+        return Err(FundError::MissingPermission.into());
+    }
+
+    let voter_vec_index = voter_info.member_index;
+
+    let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+    let proposal = load_proposal_mut(&mut aggregator_data, vec_index)?;
+
+    if proposal.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    if proposal_state(proposal)? != ProposalState::Voting || proposal.deadline / 1000 < current_time {
+        return Err(FundError::VotingCeased.into());
+    }
+
+    // Bitmap only grows for members admitted after the proposal was created, the rent reserve
+    // pays for it and gets it back when the proposal is closed
+    let voters_space = voters_bitmap_space(voter_vec_index);
+    if voters_info.data_len() < voters_space {
+        let rent = Rent::get()?;
+        let new_voters_rent = rent.minimum_balance(voters_space);
+        let current_voters_rent = voters_info.lamports();
+        if new_voters_rent > current_voters_rent {
+            **rent_reserve_info.lamports.borrow_mut() -= new_voters_rent - current_voters_rent;
+            **voters_info.lamports.borrow_mut() += new_voters_rent - current_voters_rent;
+        }
+
+        voters_info.realloc(voters_space, true)?;
+    }
+
+    if !mark_voted(&mut voters_info.data.borrow_mut(), voter_vec_index)? {
+        return Err(FundError::AlreadyVoted.into());
+    }
+
+    lock_voting_shares(&fund_data, voter_membership_info, &mut voter_info, proposal.deadline / 1000)?;
+    if vote == 0 {
+        proposal.votes_no += vote_weight(&fund_data, &voter_info);
+    } else {
+        proposal.votes_yes += vote_weight(&fund_data, &voter_info);
+    }
+
+    msg!("[FUND-ACTIVITY] {} {} {} Vote: {} on proposal ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), proposal_index, vec_index);
+
+    Ok(())
+}
+
+
+// fn process_init_investment_proposal(
+//     program_id: &Pubkey,
+//     accounts: &[AccountInfo],
+//     amounts: Vec<u64>,
+//     slippages: Vec<u16>,
+//     deadline: i64,
+//     fund_name: String,
+// ) -> ProgramResult {
+//     let creation_time = Clock::get()?.unix_timestamp;
+
+//     let accounts_iter = &mut accounts.iter();
+//     let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
+//     let fund_account_info = next_account_info(accounts_iter)?; // Fund Account ...................................
+//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal Account ........................
+//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
+//     let new_proposal_aggregator_info = next_account_info(accounts_iter)?; // new proposal aggregator account .....
+//     let vote_account_info = next_account_info(accounts_iter)?; // vote account 1 .................................
+//     let new_vote_account_info = next_account_info(accounts_iter)?; // vote account 2 .............................
+//     let proposer_token_account_info = next_account_info(accounts_iter)?;// 
+//     let governance_mint_info = next_account_info(accounts_iter)?;
+//     let token_program_2022_info = next_account_info(accounts_iter)?;
+
+//     // Proposer needs to be signer
+//     if !proposer_account_info.is_signer {
+//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::MissingRequiredSignature.into());
+//     }
+
+//     // Derive PDAs and check for equality with the provided ones
+//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+//     let mut fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+
+//     let token_account = get_associated_token_address_with_program_id(
+//         proposer_account_info.key,
+//         governance_mint_info.key,
+//         token_program_2022_info.key
+//     );
+
+//     if token_account != *proposer_token_account_info.key {
+//         msg!("[FUND-ERROR] {} {} Wrong governance token account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::InvalidTokenAccount.into());
+//     }
+
+//     if proposer_token_account_info.data_is_empty() {
+//         msg!("[FUND-ERROR] {} {} Governance token account does not exists.", fund_account_info.key.to_string(), proposal_aggregator_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+
+//     let token_account_data = proposer_token_account_info.try_borrow_data()?;
+//     let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
+//     let base_token_account = token_account.base;
+//     let balance = base_token_account.amount;
+
+//     if balance == 0 {
+//         msg!("[FUND-ERROR] {} {} You have no voting power(contribution) in this fund.", fund_account_info.key.to_string(), proposal_aggregator_info.key.to_string());
+//         return Err(FundError::NoVotingPower.into());
+//     }
+
+//     let is_proposer_member = fund_data
+//         .members
+//         .iter()
+//         .any(|member| *member == *proposer_account_info.key);
+
+//     if !is_proposer_member {
+//         msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot create a proposal.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::NotAFundMember.into());
+//     }
+
+//     let current_index = fund_data.current_proposal_index;
+
+//     if *fund_account_info.key != fund_pda {
+//         msg!("[FUND-ERROR] {} {} Wrong fund account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+
+//     let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(
 //         &[
-//             governance_mint_info.clone(),
-//             rent_sysvar_info.clone(),
-//             // token_program_2022_info.clone(),
-//             // fund_account_info.clone()
+//             b"proposal-aggregator",
+//             &[current_index],
+//             fund_pda.as_ref()
 //         ],
-//         &[&[b"governance", fund_pda.as_ref(), &[governance_bump]]],
-//     )?;
+//         program_id
+//     );
+
+//     let (new_proposal_aggregator_pda, new_proposal_aggregator_bump) = Pubkey::find_program_address(
+//         &[
+//             b"proposal-aggregator",
+//             &[current_index + 1],
+//             fund_pda.as_ref()
+//         ],
+//         program_id
+//     );
+
+//     if *proposal_aggregator_info.key != proposal_aggregator_pda || *new_proposal_aggregator_info.key != new_proposal_aggregator_pda {
+//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::InvalidProposalAccount.into());
+//     }
+
+//     // Extract From Assets Mint
+//     let from_assets_info : Vec<&AccountInfo> = accounts_iter
+//         .take(amounts.len())
+//         .collect();
+//     if from_assets_info.len() != amounts.len() {
+//         msg!("[FUND-ERROR] {} {} Wrong information of number of assets to trade of.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+//     let from_assets_mints: Vec<Pubkey> = from_assets_info.iter().map(|m| *m.key).collect();
+
+//     // Extract To Assets Mint
+//     let to_assets_info: Vec<&AccountInfo> = accounts_iter
+//         .take(amounts.len())
+//         .collect();
+//     if to_assets_info.len() != amounts.len() {
+//         msg!("[FUND-ERROR] {} {} Wrong information of number of assets to trade with.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+//     let to_assets_mints: Vec<Pubkey> = to_assets_info.iter().map(|m| *m.key).collect();
+
+//     // Rent Calculation
+//     let rent = Rent::get()?;
+//     let extra_proposal_space = (83 + to_assets_info.len()*74) as usize;
+//     let current_proposal_space = proposal_aggregator_info.data_len();
+
+//     let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
+
+//     // check if new proposal aggregator is required
+//     if (current_proposal_space + extra_proposal_space) > 10240 as usize {
+//         // Create Proposal Account
+//         invoke_signed(
+//             &system_instruction::create_account(
+//                 proposer_account_info.key,
+//                 new_proposal_aggregator_info.key,
+//                 rent.minimum_balance(37 + extra_proposal_space),
+//                 (37 + extra_proposal_space) as u64,
+//                 program_id
+//             ),
+//             &[
+//                 new_proposal_aggregator_info.clone(),
+//                 proposer_account_info.clone(),
+//                 system_program_info.clone(),
+//             ],
+//             &[&[
+//                 b"proposal-aggregator",
+//                 &[current_index + 1],
+//                 fund_account_info.key.as_ref(),
+//                 &[new_proposal_aggregator_bump]
+//             ]]
+//         )?;
+
+//         let proposals_vec: Vec<Proposal> = vec![ Proposal {
+//             proposer: *proposer_account_info.key,
+//             from_assets: from_assets_mints,
+//             to_assets: to_assets_mints,
+//             amounts,
+//             slippages,
+//             votes_yes: balance,
+//             votes_no: 0 as u64,
+//             creation_time,
+//             deadline,
+//             executed: false,
+//             vec_index: 0 as u16
+//         }];
+
+//         let new_proposal_data = ProposalAggregatorAccount {
+//             fund: *fund_account_info.key,
+//             index: current_index + 1,
+//             proposals: proposals_vec
+//         };
+
+//         new_proposal_data.serialize(&mut &mut new_proposal_aggregator_info.data.borrow_mut()[..])?;
+
+//         fund_data.current_proposal_index += 1;
+//         fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+        
+//         let new_vec_index = 0 as u16;
+//         let (new_vote_pda, new_vote_bump) = Pubkey::find_program_address(&[b"vote", &[current_index + 1], &new_vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
+//         if *new_vote_account_info.key != new_vote_pda {
+//             msg!("[FUND-ERROR] {} {} Invalid new vote account data.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+//             return Err(FundError::InvalidVoteAccount.into());
+//         }
+//         if !new_vote_account_info.data_is_empty() {
+//             msg!("Wrong Vote Account");
+//             return Err(FundError::InvalidVoteAccount.into());
+//         }
+
+//         let vote_space = 40 as usize;
+//         let vote_rent = rent.minimum_balance(vote_space);
+
+//         invoke_signed(
+//             &system_instruction::create_account(
+//                 proposer_account_info.key,
+//                 new_vote_account_info.key,
+//                 vote_rent,
+//                 vote_space as u64,
+//                 program_id
+//             ),
+//             &[proposer_account_info.clone(), new_vote_account_info.clone(), system_program_info.clone()],
+//             &[&[b"vote", &[current_index + 1], &new_vec_index.to_le_bytes(), fund_account_info.key.as_ref(), &[new_vote_bump]]]
+//         )?;
+
+//         let new_voters_vec: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1)];
+//         let new_vote_data = VoteAccount {
+//             proposal_index: current_index + 1,
+//             vec_index: new_vec_index,
+//             voters: new_voters_vec
+//         };
+
+//         new_vote_data.serialize(&mut &mut new_vote_account_info.data.borrow_mut()[..])?;
+
+//         msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, (current_index + 1), new_vec_index, proposer_account_info.key.to_string());
+//     } else {
+//         let new_aggregator_size = extra_proposal_space + current_proposal_space as usize;
+//         let current_rent_exempt = proposal_aggregator_info.lamports();
+//         let new_rent_exempt = rent.minimum_balance(new_aggregator_size);
+
+//         if new_rent_exempt > current_rent_exempt {
+//             invoke(
+//                 &system_instruction::transfer(
+//                     proposer_account_info.key,
+//                     proposal_aggregator_info.key,
+//                     new_rent_exempt-current_rent_exempt
+//                 ),
+//                 &[proposal_aggregator_info.clone(), proposer_account_info.clone(), system_program_info.clone()]
+//             )?;
+//         }
+
+//         let num_of_proposals = proposal_aggregator_data.proposals.len();
+//         let mut vec_index = 0 as u16;
+//         if num_of_proposals != 0 {
+//             vec_index = proposal_aggregator_data.proposals[num_of_proposals - 1].vec_index + 1;
+//         }
+//         msg!("Vec Index: {}", vec_index);
+
+//         proposal_aggregator_info.realloc(new_aggregator_size, false)?;
+        
+//         proposal_aggregator_data.proposals.push( Proposal {
+//             proposer: *proposer_account_info.key,
+//             from_assets: from_assets_mints,
+//             to_assets: to_assets_mints,
+//             amounts,
+//             slippages,
+//             votes_yes: balance,
+//             votes_no: 0 as u64,
+//             creation_time,
+//             deadline,
+//             executed: false,
+//             vec_index
+//         });
+
+//         proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+
+//         let (vote_pda, vote_bump) = Pubkey::find_program_address(&[b"vote", &[current_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
+//         if *vote_account_info.key != vote_pda {
+//             return Err(FundError::InvalidVoteAccount.into());
+//         }
+//         if !vote_account_info.data_is_empty() {
+//             msg!("Wrong Vote Account");
+//             return Err(FundError::InvalidVoteAccount.into());
+//         }
+
+//         let vote_space = 40 as usize;
+//         let vote_rent = rent.minimum_balance(vote_space);
+        
+//         invoke_signed(
+//             &system_instruction::create_account(
+//                 proposer_account_info.key,
+//                 vote_account_info.key,
+//                 vote_rent,
+//                 vote_space as u64,
+//                 program_id
+//             ),
+//             &[vote_account_info.clone(), proposer_account_info.clone(), system_program_info.clone()],
+//             &[&[b"vote", &[current_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref(), &[vote_bump]]]
+//         )?;
+
+//         let voters: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1)];
+
+//         let vote_data = VoteAccount {
+//             proposal_index: current_index,
+//             vec_index,
+//             voters,
+//         };
+
+//         vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+
+//         msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, current_index, vec_index, proposer_account_info.key.to_string());
+//     }
+
+//     Ok(())
+// }
+
+
+// fn process_vote_on_proposal(
+//     program_id: &Pubkey,
+//     accounts: &[AccountInfo],
+//     vote: u8,
+//     proposal_index: u8,
+//     vec_index: u16,
+//     fund_name: String,
+// ) -> ProgramResult {
+//     let current_time = Clock::get()?.unix_timestamp;
+
+//     let accounts_iter = &mut accounts.iter();
+//     let voter_account_info = next_account_info(accounts_iter)?; // Voter's Wallet ................................
+//     let vote_account_info = next_account_info(accounts_iter)?; // Voter's vote account to be created .............
+//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal account ........................
+//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
+//     let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
+//     let governance_token_mint_info = next_account_info(accounts_iter)?; // Governance Mint account ...............
+//     let voter_token_account_info = next_account_info(accounts_iter)?; // Voter's governance token account ........
+//     let token_program_2022_info = next_account_info(accounts_iter)?; // Token program extensions .................
+//     let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
+
+//     msg!("Vec Index: {}", vec_index);
+
+//     // Voter needs to be signer
+//     if !voter_account_info.is_signer {
+//         return Err(FundError::MissingRequiredSignature.into());
+//     }
+
+//     // Pdas derivation
+//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+//     let (vote_pda, _vote_bump) = Pubkey::find_program_address(&[b"vote", &[proposal_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
+//     let token_account = spl_associated_token_account::get_associated_token_address_with_program_id(
+//         voter_account_info.key,
+//         governance_token_mint_info.key,
+//         token_program_2022_info.key
+//     );
+
+//     let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+
+//     // Pdas verification
+//     if *fund_account_info.key != fund_pda ||
+//        *vote_account_info.key != vote_pda ||
+//        token_account != *voter_token_account_info.key ||
+//        *proposal_aggregator_info.key != proposal_aggregator_pda {
+//         return Err(FundError::InvalidAccountData.into());
+//     }
+
+//     let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+
+//     let is_voter_member = fund_data
+//         .members
+//         .iter()
+//         .any(|member| *member == *voter_account_info.key);
+
+//     if !is_voter_member {
+//         return Err(FundError::NotAFundMember.into());
+//     }
+
+//     if fund_data.governance_mint != *governance_token_mint_info.key {
+//         return Err(FundError::InvalidGovernanceMint.into());
+//     }
+
+//     let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
+//     let (matched_index, proposal) = proposal_aggregator_data
+//         .proposals
+//         .iter()
+//         .enumerate()
+//         .find(|(_, proposal)| proposal.vec_index == vec_index)
+//         .ok_or(FundError::InvalidProposalAccount)?;
+
+//     if proposal.proposer != *proposer_account_info.key {
+//         return Err(FundError::InvalidProposerInfo.into());
+//     }
+
+//     if proposal.deadline < current_time {
+//         return Err(FundError::VotingCeased.into());
+//     }
+
+//     let mut vote_data = VoteAccount::try_from_slice(&vote_account_info.data.borrow())?;
+
+//     let voter_exists = vote_data
+//         .voters
+//         .iter()
+//         .any(|(key, _)| *key == *voter_account_info.key);
+
+//     if voter_exists {
+//         return Err(FundError::AlreadyVoted.into());
+//     }
+
+//     if voter_token_account_info.data_is_empty() {
+//         msg!("No Voting Power");
+//         return Err(FundError::NoVotingPower.into());
+//     }
+
+//     if vote_account_info.data_is_empty() {
+//         msg!("Vote account should be already created");
+//         return Err(FundError::InvalidVoteAccount.into());
+//     } else {
+//         let rent = Rent::get()?;
+//         let extra_vote_space = 33 as usize;
+//         let current_vote_space = vote_account_info.data_len();
+//         let new_vote_space = current_vote_space + extra_vote_space;
+//         let new_rent = rent.minimum_balance(new_vote_space);
+//         let current_rent = vote_account_info.lamports();
+
+//         if new_rent > current_rent {
+//             invoke(
+//                 &system_instruction::transfer(
+//                     voter_account_info.key,
+//                     vote_account_info.key,
+//                     new_rent - current_rent
+//                 ),
+//                 &[system_program_info.clone(), voter_account_info.clone(), vote_account_info.clone()]
+//             )?;
+//         }
+
+//         vote_account_info.realloc(new_vote_space, false)?;
+        
+//         vote_data.voters.push((*voter_account_info.key, vote));
+//         vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+
+//         let token_account_data = voter_token_account_info.try_borrow_data()?;
+//         let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
+//         let base_token_account = token_account.base;
+//         let balance = base_token_account.amount;
+
+//         if balance == 0 {
+//             msg!("No Voting Power");
+//             return Err(FundError::NoVotingPower.into());
+//         }
+
+//         if vote != 0 {
+//             proposal_aggregator_data.proposals[matched_index as usize].votes_yes += balance;
+//         } else {
+//             proposal_aggregator_data.proposals[matched_index as usize].votes_no += balance;
+//         }
+
+//         proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+//     }
+
+//     msg!("[FUND-ACTIVITY] {} {} {} Vote: {} on proposal ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), proposal_index, vec_index);
+
+//     Ok(())
+// }
+
+
+/// Withdraws a proposal before its deadline, releasing its slot and voter bitmap.
+fn process_cancel_investment_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let proposer_account_info = next_account_info(accounts_iter)?; // Proposer wallet ..........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator ...................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account .................................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ......................
+    let voters_info = next_account_info(accounts_iter)?; // proposal's voter bitmap ............................
+
+    if !proposer_account_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    let (voters_pda, _voters_bump) = Pubkey::find_program_address(&[b"proposal-voters", fund_pda.as_ref(), &[proposal_index], &vec_index.to_le_bytes()], program_id);
+
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda || *rent_reserve_info.key != rent_pda || *voters_info.key != voters_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if proposal_index > fund_data.current_proposal_index {
+        msg!("Yaha se aaya hai");
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    msg!("Vec Index obtained = {}", vec_index);
+
+    let mut proposal = *load_proposal(&proposal_aggregator_info.data.borrow(), vec_index)?;
+
+    if proposal.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    // Only while voting is still open
+    transition_proposal(&mut proposal, ProposalState::Cancelled, voting_rules(&fund_data), current_time)?;
+
+    release_proposal(proposal_aggregator_info, voters_info, proposer_account_info, rent_reserve_info, vec_index)?;
+
+    msg!("[FUND-ACTIVITY] {} {} Investment Proposal ({}, {}) deleted by proposer ({})", fund_account_info.key.to_string(), current_time, proposal_index, vec_index, proposer_account_info.key.to_string());
+
+    Ok(())
+}
+
+
+/// Moves a proposal along the transitions that only depend on votes and time, whoever sends it:
+/// voting ends in Succeeded or Defeated, and an unexecuted proposal Expires or Fails.
+fn process_finalize_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can finalize a proposal ....................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account .................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator ...................
+
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+
+    let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+    let proposal = load_proposal_mut(&mut aggregator_data, vec_index)?;
+    let previous_state = proposal_state(proposal)?;
+    let state = finalize_proposal(proposal, voting_rules(&fund_data), current_time)?;
+
+    if state == previous_state {
+        msg!("Nothing to finalize, proposal is {:?}", state);
+        return Err(FundError::InvalidProposalState.into());
+    }
+
+    msg!("[FUND-ACTIVITY] {} {} {} Proposal ({}, {}) finalized: {:?}", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index, state);
+
+    Ok(())
+}
+
+/// Closes a finalized proposal, whoever sends it: Defeated, Executed, Failed, Expired or
+/// Cancelled, vote and time driven transitions applied first. A compact record of it is logged as an event
+/// before its slot and voter bitmap are released.
+fn process_close_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can close a finalized proposal .............
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account .................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator ...................
+    let voters_info = next_account_info(accounts_iter)?; // proposal's voter bitmap ............................
+    let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet, gets their rent back ....
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ......................
+
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (voters_pda, _voters_bump) = Pubkey::find_program_address(&[b"proposal-voters", fund_pda.as_ref(), &[proposal_index], &vec_index.to_le_bytes()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda || *voters_info.key != voters_pda || *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut proposal = *load_proposal(&proposal_aggregator_info.data.borrow(), vec_index)?;
+
+    if proposal.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    if !proposal_closable(finalize_proposal(&mut proposal, voting_rules(&fund_data), current_time)?) {
+        msg!("Proposal can still be voted on or executed");
+        return Err(FundError::ProposalNotFinalized.into());
+    }
+
+    let archive = ProposalArchive {
+        fund: *fund_account_info.key,
+        proposal_index,
+        vec_index,
+        proposer: proposal.proposer,
+        cid: proposal.cid,
+        merkel_root: proposal.merkel_root,
+        votes_yes: proposal.votes_yes,
+        votes_no: proposal.votes_no,
+        creation_time: proposal.creation_time,
+        deadline: proposal.deadline,
+        state: proposal.state,
+        swaps_status: proposal.swaps_status,
+        closed_at: current_time,
+    };
+    sol_log_data(&[b"proposal-archive", &archive.try_to_vec()?]);
+
+    release_proposal(proposal_aggregator_info, voters_info, proposer_account_info, rent_reserve_info, vec_index)?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Proposal closed: ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index);
+
+    Ok(())
+}
+
+/// Frees a proposal's slot and closes its voter bitmap. The proposer gets back the slot's rent
+/// right away, fronted by the rent reserve until a new proposal takes the slot or the freed slots
+/// are the last ones and the aggregator shrinks, and the bitmap's initial rent, any growth going
+/// back to the rent reserve.
+fn release_proposal<'a>(
+    proposal_aggregator_info: &AccountInfo<'a>,
+    voters_info: &AccountInfo<'a>,
+    proposer_account_info: &AccountInfo<'a>,
+    rent_reserve_info: &AccountInfo<'a>,
+    vec_index: u16,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+
+    let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+    let voters_rent = load_proposal(&aggregator_data, vec_index)?.voters_rent;
+    *proposal_slot_mut(&mut aggregator_data, vec_index)? = Proposal::zeroed();
+
+    let mut remaining_slots = proposal_slots(&aggregator_data);
+    while remaining_slots > 0 && proposal_slot_mut(&mut aggregator_data, (remaining_slots - 1) as u16)?.proposer == Pubkey::default() {
+        remaining_slots -= 1;
+    }
+    drop(aggregator_data);
+
+    let slot_rent = rent.minimum_balance(PROPOSAL_SPACE) - rent.minimum_balance(0);
+    **rent_reserve_info.lamports.borrow_mut() -= slot_rent;
+    **proposer_account_info.lamports.borrow_mut() += slot_rent;
+
+    let new_aggregator_size = AGGREGATOR_HEADER_SPACE + remaining_slots * PROPOSAL_SPACE;
+    if new_aggregator_size < proposal_aggregator_info.data_len() {
+        proposal_aggregator_info.realloc(new_aggregator_size, false)?;
+
+        let freed_rent = proposal_aggregator_info.lamports().saturating_sub(rent.minimum_balance(new_aggregator_size));
+        **proposal_aggregator_info.lamports.borrow_mut() -= freed_rent;
+        **rent_reserve_info.lamports.borrow_mut() += freed_rent;
+    }
+
+    let voters_lamports = voters_info.lamports();
+    let proposer_share = voters_rent.min(voters_lamports);
+    **voters_info.lamports.borrow_mut() = 0;
+    **proposer_account_info.lamports.borrow_mut() += proposer_share;
+    **rent_reserve_info.lamports.borrow_mut() += voters_lamports - proposer_share;
+    for byte in voters_info.data.borrow_mut().iter_mut() {
+        *byte = 0;
+    }
+
+    Ok(())
+}
+
+fn process_init_rent_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let rent_account_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let god_father_info = next_account_info(accounts_iter)?;
+
+    if !god_father_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (rent_pda, rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+
+    if *rent_account_info.key != rent_pda || !rent_account_info.data_is_empty() || rent_account_info.lamports() != 0 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let rent = Rent::get()?;
+    let data_len = 0;
+    let rent_exemption_amount = rent.minimum_balance(data_len);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            god_father_info.key,
+            rent_account_info.key,
+            rent_exemption_amount,
+            data_len as u64,
+            program_id
+        ),
+        &[god_father_info.clone(), rent_account_info.clone(), system_program_info.clone()],
+        &[&[b"rent", &[rent_bump]]]
+    )?;
+
+    Ok(())
+}
+
+fn process_set_executing(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    proposal_index: u8,
+    vec_index: u16,
+    fund_name: String,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let peerfunds_wallet_info = next_account_info(accounts_iter)?;
+    let fund_account_info = next_account_info(accounts_iter)?;
+    let proposal_aggregator_info = next_account_info(accounts_iter)?;
+
+    if !peerfunds_wallet_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (aggregator_pda, _aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    msg!("Fund and Aggregator are correct");
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    check_executor(&fund_data, peerfunds_wallet_info.key)?;
+
+    let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+    let proposal = load_proposal_mut(&mut aggregator_data, vec_index)?;
+
+    transition_proposal(proposal, ProposalState::Queued, voting_rules(&fund_data), current_time)?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_execute_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    swap_index: u8,
+    no_of_swaps: u8,
+    merkel_proof: Vec<[u8; 32]>,
+    amount: u64,
+    slippage: u16,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let account_iter = &mut accounts.iter();
+    let payer_info = next_account_info(account_iter)?; // payer ...............................................
+    let fund_account_info = next_account_info(account_iter)?; // fund account .................................
+    let vault_account_info = next_account_info(account_iter)?; // fund's vault account ........................
+    let proposal_aggregator_info = next_account_info(account_iter)?; // proposal account ......................
+    let token_program_2022_info = next_account_info(account_iter)?; // token program 2022 .....................
+    let token_program_std_info = next_account_info(account_iter)?; // token program 2020 ......................
+    let raydium_clmm_program = next_account_info(account_iter)?; // raydium clmm program ......................
+    let amm_config = next_account_info(account_iter)?; // Amm config account ..................................
+    let pool_state = next_account_info(account_iter)?; // Pool state account ..................................
+    let input_token_account = next_account_info(account_iter)?; // Fund's vault input token account .. Should exist
+    let output_token_account = next_account_info(account_iter)?; // fund's output token account .. might need to be created
+    let input_vault_ata = next_account_info(account_iter)?; // Raydium pool's input vault ata .................
+    let output_vault_ata = next_account_info(account_iter)?; // Raysium pool's output vault ata ...............
+    let observation_state = next_account_info(account_iter)?; // Observation state account ....................
+    let input_token_mint = next_account_info(account_iter)?; // Input token mint account ......................
+    let output_token_mint = next_account_info(account_iter)?; // Output token mint account ....................
+    let memo_program = next_account_info(account_iter)?; // memo program ......................................
+    let protocol_config_info = next_account_info(account_iter)?; // protocol config ...........................
+    let treasury_ata_info = next_account_info(account_iter)?; // treasury's input token account ..............
+    let system_program_info = next_account_info(account_iter)?; // system program .............................
+    let holdings_info = next_account_info(account_iter)?; // fund's vault holdings registry ...................
+
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (proposal_pda, _proposal_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+    if *proposal_aggregator_info.key != proposal_pda {
+        msg!("Wrong proposal aggregator account");
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    // check fund account
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    msg!("Fund Name: {}", fund_name);
+    msg!("{} {}", fund_pda, fund_account_info.key);
+    if *fund_account_info.key != fund_pda {
+        msg!("Wrong Fund details");
+        return Err(FundError::InvalidFundDetails.into());
+    }
+
+    let proposal = *load_proposal(&proposal_aggregator_info.data.borrow(), vec_index)?;
+
+
+    // let is_executed = proposal.executed;
+
+    // // if proposal is executed/not initiated then return
+    // if is_executed != 1 {
+    //     msg!("Proposal execution isn't initiated yet or it is already executed");
+    //     return Err(FundError::InvalidAccountData.into());
+    // }
+
+    // --- Merkle Root Verification Start ---
+
+    // let mut swap_hasher = Sha256::new();
+
+    // swap_hasher.update(input_token_mint.key.as_ref());
+    // swap_hasher.update(output_token_mint.key.as_ref());
+    // swap_hasher.update(&amount.to_le_bytes());
+    // swap_hasher.update(&slippage.to_le_bytes());
+
+    // let mut calculated_merkel_root: [u8; 32] = swap_hasher.finalize().into();
+
+    let leaf = swap_leaf(input_token_mint.key, output_token_mint.key, amount, slippage);
+
+    // let mut merkle_hasher = Sha256::new();
+
+    // for node in merkel_proof.iter() {
+    //     if index % 2 == 0 {
+    //         // Current hash is left child
+    //         merkle_hasher.update(&calculated_merkel_root);
+    //         merkle_hasher.update(&node[..]);
+    //     } else {
+    //         // Current hash is right child
+    //         merkle_hasher.update(&node[..]);
+    //         merkle_hasher.update(&calculated_merkel_root);
+    //     }
+    //     calculated_merkel_root = merkle_hasher.finalize().into();
+    //     merkle_hasher = Sha256::new();
+    //     index /= 2;
+    // }
+
+    if no_of_swaps != proposal.actions {
+        return Err(FundError::InvalidSwapDetails.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    check_executor(&fund_data, payer_info.key)?;
+    check_mint_whitelisted(&fund_data, output_token_mint.key)?;
+
+    // voting must be over, with quorum and majority reached, and each swap runs only once,
+    // the last one completing the execution
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, swap_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    // verify vault account
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    if *vault_account_info.key != vault_pda {
+        msg!("Wrong vault account");
+        return Err(FundError::InvaildVaultAccount.into());
+    }
+
+    // verify the vault's token accounts
+    let input_vault_token_account = get_associated_token_address_with_program_id(
+        &vault_pda,
+        input_token_mint.key,
+        input_token_mint.owner
+    );
+    if *input_token_account.key != input_vault_token_account || input_token_account.data_is_empty() {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    let output_vault_token_account = get_associated_token_address_with_program_id(
+        &vault_pda,
+        output_token_mint.key,
+        output_token_mint.owner
+    );
+    if *output_token_account.key != output_vault_token_account {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    // the vault can't take in tokens it would be unable to pay out
+    supported_mint_decimals(output_token_mint)?;
+
+    // protocol fee on swap volume, taken from the input side
+    let protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+    let (treasury_pda, _treasury_bump) = Pubkey::find_program_address(&[b"treasury"], program_id);
+    if *treasury_ata_info.key != get_associated_token_address_with_program_id(&treasury_pda, input_token_mint.key, input_token_mint.owner) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    let input_token_program = if *input_token_mint.owner == spl_token_2022::id() {
+        token_program_2022_info
+    } else {
+        token_program_std_info
+    };
+
+    let protocol_fee = collect_protocol_fee(
+        input_token_program,
+        input_token_mint,
+        input_token_account,
+        treasury_ata_info,
+        vault_account_info,
+        amount,
+        protocol_config.swap_fee_bps,
+        &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]
+    )?;
+
+    let mut swap_accounts = vec![
+        vault_account_info.clone(),
+        amm_config.clone(),
+        pool_state.clone(),
+        input_token_account.clone(),
+        output_token_account.clone(),
+        input_vault_ata.clone(),
+        output_vault_ata.clone(),
+        observation_state.clone(),
+        token_program_std_info.clone(),
+        token_program_2022_info.clone(),
+        memo_program.clone(),
+        input_token_mint.clone(),
+        output_token_mint.clone(),
+    ];
+
+    for acc in account_iter {
+        swap_accounts.push(acc.clone());
+    }
+
+    raydium_clmm_swap(raydium_clmm_program, &swap_accounts, amount - protocol_fee, slippage_min_amount_out(amount - protocol_fee, slippage)?, &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]])?;
+    record_holding(program_id, holdings_info, fund_account_info, payer_info, system_program_info, input_token_mint, input_token_account)?;
+    record_holding(program_id, holdings_info, fund_account_info, payer_info, system_program_info, output_token_mint, output_token_account)?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Proposal executed: ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_create_conditional_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    order_index: u8,
+    comparison: u8,
+    threshold_price: u64,
+    expiry: i64,
+    feed_id: [u8; 32],
+    swap_leaf: [u8; 32],
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let creator_wallet_info = next_account_info(accounts_iter)?; // member creating the order ....................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let order_account_info = next_account_info(accounts_iter)?; // conditional order account to be created .......
+    let trigger_mint_info = next_account_info(accounts_iter)?; // mint whose price triggers the order ............
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let creator_membership_info = next_account_info(accounts_iter)?; // order creator's membership PDA ...........
+
+    if !creator_wallet_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    if comparison > 1 || threshold_price == 0 || expiry <= current_time {
+        msg!("[FUND-ERROR] {} {} Invalid order trigger details.", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
+        return Err(FundError::InvalidOrderDetails.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (order_pda, order_bump) = Pubkey::find_program_address(
+        &[
+            b"conditional-order",
+            fund_pda.as_ref(),
+            &[proposal_index],
+            &vec_index.to_le_bytes(),
+            &[order_index]
+        ],
+        program_id
+    );
+
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda || *order_account_info.key != order_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if !order_account_info.data_is_empty() {
+        msg!("[FUND-ERROR] {} {} Conditional order already exists.", fund_account_info.key.to_string(), creator_wallet_info.key.to_string());
+        return Err(FundError::InvalidOrderDetails.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let creator_member = load_member(program_id, creator_membership_info, fund_account_info.key, creator_wallet_info.key)?;
+
+    if !has_role(&creator_member, ROLE_PROPOSER) {
+        return Err(FundError::MissingPermission.into());
+    }
+
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
+
+    let leaf = conditional_order_leaf(trigger_mint_info.key, &feed_id, comparison, threshold_price, expiry, &swap_leaf);
+    let proposal = *load_proposal(&proposal_aggregator_info.data.borrow(), vec_index)?;
+    if !verify_merkle_proof(leaf, &merkel_proof, order_index, proposal.merkel_root) {
+        msg!("The order is not part of the approved proposal.");
+        return Err(FundError::InvalidOrderDetails.into());
+    }
+
+    // Placing the order is the action, it can't be placed again once triggered or expired
+    record_executed_action(
+        load_proposal_mut(&mut proposal_aggregator_info.data.borrow_mut(), vec_index)?,
+        order_index,
+        voting_rules(&fund_data),
+        current_time
+    )?;
+
+    let rent = Rent::get()?;
+    let order_space = 189;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            creator_wallet_info.key,
+            order_account_info.key,
+            rent.minimum_balance(order_space),
+            order_space as u64,
+            program_id
+        ),
+        &[creator_wallet_info.clone(), order_account_info.clone(), system_program_info.clone()],
+        &[&[
+            b"conditional-order",
+            fund_pda.as_ref(),
+            &[proposal_index],
+            &vec_index.to_le_bytes(),
+            &[order_index],
+            &[order_bump]
+        ]]
+    )?;
+
+    let order_data = ConditionalOrder {
+        fund: *fund_account_info.key,
+        creator: *creator_wallet_info.key,
+        proposal_index,
+        vec_index,
+        order_index,
+        trigger_mint: *trigger_mint_info.key,
+        feed_id,
+        comparison,
+        threshold_price,
+        expiry,
+        swap_leaf,
+        created_at: current_time,
+    };
+
+    order_data.serialize(&mut &mut order_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Conditional order ({}, {}, {}) created by {}", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index, order_index, creator_wallet_info.key.to_string());
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_trigger_conditional_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    order_index: u8,
+    amount: u64,
+    slippage: u16,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let account_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(account_iter)?; // anyone can trigger ...................................
+    let creator_wallet_info = next_account_info(account_iter)?; // order creator, receives the rent back .........
+    let fund_account_info = next_account_info(account_iter)?; // fund account ....................................
+    let vault_account_info = next_account_info(account_iter)?; // fund's vault account ...........................
+    let order_account_info = next_account_info(account_iter)?; // conditional order account ......................
+    let price_update_info = next_account_info(account_iter)?; // oracle price update for the trigger mint .......
+    let raydium_clmm_program = next_account_info(account_iter)?; // raydium clmm program .........................
+    let amm_config = next_account_info(account_iter)?; // Amm config account .....................................
+    let pool_state = next_account_info(account_iter)?; // Pool state account .....................................
+    let input_token_account = next_account_info(account_iter)?; // Fund's vault input token account ..............
+    let output_token_account = next_account_info(account_iter)?; // fund's vault output token account ............
+    let input_vault_ata = next_account_info(account_iter)?; // Raydium pool's input vault ata ....................
+    let output_vault_ata = next_account_info(account_iter)?; // Raydium pool's output vault ata ..................
+    let observation_state = next_account_info(account_iter)?; // Observation state account .......................
+    let token_program_std_info = next_account_info(account_iter)?; // token program ..............................
+    let token_program_2022_info = next_account_info(account_iter)?; // token program 2022 ........................
+    let memo_program = next_account_info(account_iter)?; // memo program .........................................
+    let input_token_mint = next_account_info(account_iter)?; // Input token mint account .........................
+    let output_token_mint = next_account_info(account_iter)?; // Output token mint account .......................
+    let protocol_config_info = next_account_info(account_iter)?; // protocol config ..............................
+    let treasury_ata_info = next_account_info(account_iter)?; // treasury's input token account .................
+    let system_program_info = next_account_info(account_iter)?; // system program ................................
+    let holdings_info = next_account_info(account_iter)?; // fund's vault holdings registry ......................
+
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (order_pda, _order_bump) = Pubkey::find_program_address(
+        &[
+            b"conditional-order",
+            fund_pda.as_ref(),
+            &[proposal_index],
+            &vec_index.to_le_bytes(),
+            &[order_index]
+        ],
+        program_id
+    );
+
+    if *fund_account_info.key != fund_pda || *vault_account_info.key != vault_pda || *order_account_info.key != order_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if order_account_info.data_is_empty() {
+        msg!("Conditional order doesn't exist or is already settled");
+        return Err(FundError::InvalidOrderDetails.into());
+    }
+
+    let order_data = ConditionalOrder::try_from_slice(&order_account_info.data.borrow())?;
+    if order_data.creator != *creator_wallet_info.key {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    // Expired orders are closed without trading
+    if order_data.expiry >= current_time {
+        let (price, expo) = read_oracle_price(price_update_info, &order_data.feed_id, current_time)?;
+        let price = normalize_price(price, expo)?;
+        msg!("Oracle price: {}, threshold: {}", price, order_data.threshold_price);
+
+        let triggered = if order_data.comparison == 0 {
+            price <= order_data.threshold_price
+        } else {
+            price >= order_data.threshold_price
+        };
+
+        if !triggered {
+            return Err(FundError::OrderNotTriggered.into());
+        }
+
+        if swap_leaf(input_token_mint.key, output_token_mint.key, amount, slippage) != order_data.swap_leaf {
+            msg!("Swap details don't match the approved order.");
+            return Err(FundError::InvalidSwapDetails.into());
+        }
+
+        let input_vault_token_account = get_associated_token_address_with_program_id(&vault_pda, input_token_mint.key, input_token_mint.owner);
+        if *input_token_account.key != input_vault_token_account || input_token_account.data_is_empty() {
+            return Err(FundError::InvalidTokenAccount.into());
+        }
+
+        let output_vault_token_account = get_associated_token_address_with_program_id(&vault_pda, output_token_mint.key, output_token_mint.owner);
+        if *output_token_account.key != output_vault_token_account {
+            return Err(FundError::InvalidTokenAccount.into());
+        }
+
+        supported_mint_decimals(output_token_mint)?;
+        check_mint_whitelisted(&LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?, output_token_mint.key)?;
+
+        let protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+        let (treasury_pda, _treasury_bump) = Pubkey::find_program_address(&[b"treasury"], program_id);
+        if *treasury_ata_info.key != get_associated_token_address_with_program_id(&treasury_pda, input_token_mint.key, input_token_mint.owner) {
+            return Err(FundError::InvalidTokenAccount.into());
+        }
+
+        let input_token_program = if *input_token_mint.owner == spl_token_2022::id() {
+            token_program_2022_info
+        } else {
+            token_program_std_info
+        };
+
+        let protocol_fee = collect_protocol_fee(
+            input_token_program,
+            input_token_mint,
+            input_token_account,
+            treasury_ata_info,
+            vault_account_info,
+            amount,
+            protocol_config.swap_fee_bps,
+            &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]
+        )?;
+
+        let mut swap_accounts = vec![
+            vault_account_info.clone(),
+            amm_config.clone(),
+            pool_state.clone(),
+            input_token_account.clone(),
+            output_token_account.clone(),
+            input_vault_ata.clone(),
+            output_vault_ata.clone(),
+            observation_state.clone(),
+            token_program_std_info.clone(),
+            token_program_2022_info.clone(),
+            memo_program.clone(),
+            input_token_mint.clone(),
+            output_token_mint.clone(),
+        ];
+
+        for acc in account_iter {
+            swap_accounts.push(acc.clone());
+        }
+
+        raydium_clmm_swap(raydium_clmm_program, &swap_accounts, amount - protocol_fee, slippage_min_amount_out(amount - protocol_fee, slippage)?, &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]])?;
+        record_holding(program_id, holdings_info, fund_account_info, cranker_info, system_program_info, input_token_mint, input_token_account)?;
+        record_holding(program_id, holdings_info, fund_account_info, cranker_info, system_program_info, output_token_mint, output_token_account)?;
+
+        msg!("[FUND-ACTIVITY] {} {} {} Conditional order ({}, {}, {}) triggered at price {}", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index, order_index, price);
+    } else {
+        msg!("[FUND-ACTIVITY] {} {} {} Conditional order ({}, {}, {}) expired", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index, order_index);
+    }
+
+    // Close the order account, rent goes back to its creator
+    let lamports = order_account_info.lamports();
+    **order_account_info.lamports.borrow_mut() = 0;
+    **creator_wallet_info.lamports.borrow_mut() += lamports;
+
+    let mut data = order_account_info.data.borrow_mut();
+    for byte in data.iter_mut() {
+        *byte = 0;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_delegate_vault_stake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    stake_index: u8,
+    amount: u64,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer, gets temporary rent back ......................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let vault_wsol_ata_info = next_account_info(accounts_iter)?; // vault's wSOL token account ...................
+    let unwrap_account_info = next_account_info(accounts_iter)?; // temporary wSOL account used to unwrap ........
+    let wsol_mint_info = next_account_info(accounts_iter)?; // wSOL mint ..........................................
+    let stake_account_info = next_account_info(accounts_iter)?; // vault stake account to be created .............
+    let vote_account_info = next_account_info(accounts_iter)?; // validator vote account .........................
+    let token_program_info = next_account_info(accounts_iter)?; // token program ..................................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ................................
+    let stake_program_info = next_account_info(accounts_iter)?; // stake program ..................................
+    let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
+    let stake_history_info = next_account_info(accounts_iter)?; // stake history sysvar ...........................
+    let stake_config_info = next_account_info(accounts_iter)?; // stake config account ...........................
+    let rent_sysvar_info = next_account_info(accounts_iter)?; // rent sysvar .....................................
+
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (unwrap_pda, unwrap_bump) = Pubkey::find_program_address(&[b"unwrap", fund_pda.as_ref()], program_id);
+    let (stake_pda, stake_bump) = Pubkey::find_program_address(&[b"vault-stake", fund_pda.as_ref(), &[stake_index]], program_id);
+
+    if *fund_account_info.key != fund_pda ||
+       *vault_account_info.key != vault_pda ||
+       *proposal_aggregator_info.key != proposal_aggregator_pda ||
+       *unwrap_account_info.key != unwrap_pda ||
+       *wsol_mint_info.key != wsol_mint {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if *stake_account_info.key != stake_pda || !stake_account_info.data_is_empty() {
+        return Err(FundError::InvalidStakeAccount.into());
+    }
+
+    if *vault_wsol_ata_info.key != spl_associated_token_account::get_associated_token_address(&vault_pda, &wsol_mint) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = stake_leaf(0, stake_index, vote_account_info.key, amount);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    let rent = Rent::get()?;
+    let vault_seeds: &[&[u8]] = &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]];
+
+    // Unwrap: move the wSOL into a temporary account and close it into the vault PDA
+    let unwrap_rent = rent.minimum_balance(TokenAccount::LEN);
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            unwrap_account_info.key,
+            unwrap_rent,
+            TokenAccount::LEN as u64,
+            token_program_info.key
+        ),
+        &[payer_info.clone(), unwrap_account_info.clone(), system_program_info.clone()],
+        &[&[b"unwrap", fund_account_info.key.as_ref(), &[unwrap_bump]]]
+    )?;
+
+    invoke(
+        &spl_token::instruction::initialize_account3(
+            token_program_info.key,
+            unwrap_account_info.key,
+            wsol_mint_info.key,
+            vault_account_info.key,
+        )?,
+        &[unwrap_account_info.clone(), wsol_mint_info.clone(), token_program_info.clone()]
+    )?;
 
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program_info.key,
+            vault_wsol_ata_info.key,
+            unwrap_account_info.key,
+            vault_account_info.key,
+            &[],
+            amount
+        )?,
+        &[vault_wsol_ata_info.clone(), unwrap_account_info.clone(), vault_account_info.clone(), token_program_info.clone()],
+        &[vault_seeds]
+    )?;
 
-//     // creating the proposal aggregator account
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             creator_wallet_info.key,
-//             proposal_aggregator_info.key,
-//             rent.minimum_balance(proposal_space),
-//             proposal_space as u64,
-//             program_id
-//         ),
-//         &[creator_wallet_info.clone(), system_program_info.clone(), proposal_aggregator_info.clone()],
-//         &[&[b"proposal-aggregator", &[index], fund_pda.as_ref(), &[proposal_bump]]]
-//     )?;
+    invoke_signed(
+        &spl_token::instruction::close_account(
+            token_program_info.key,
+            unwrap_account_info.key,
+            vault_account_info.key,
+            vault_account_info.key,
+            &[]
+        )?,
+        &[unwrap_account_info.clone(), vault_account_info.clone(), token_program_info.clone()],
+        &[vault_seeds]
+    )?;
 
-//     // creating the joining proposal aggregator account
-//     if privacy == 1 {
-//         invoke_signed(
-//             &system_instruction::create_account(
-//                 creator_wallet_info.key,
-//                 join_proposal_aggregator_info.key,
-//                 rent.minimum_balance(join_proposal_space),
-//                 join_proposal_space as u64,
-//                 program_id
-//             ),
-//             &[creator_wallet_info.clone(), system_program_info.clone(), join_proposal_aggregator_info.clone()],
-//             &[&[b"join-proposal-aggregator", &[index], fund_pda.as_ref(), &[join_aggregator_bump]]]
-//         )?;
-//     }
+    // Create the stake account, the payer is paid back from the unwrapped SOL
+    let stake_space = StakeStateV2::size_of();
+    let stake_rent = rent.minimum_balance(stake_space);
+    if amount <= stake_rent {
+        return Err(FundError::NotEnoughFunds.into());
+    }
 
-//     let current_governance_lamports = governance_mint_info.lamports();
-//     let new_governance_rent = rent.minimum_balance(322 + fund_name.len() + symbol_str.len());
-//     let transfer_amount = new_governance_rent - current_governance_lamports;
-//     invoke(
-//         &system_instruction::transfer(
-//             creator_wallet_info.key,
-//             governance_mint_info.key,
-//             transfer_amount + 1
-//         ),
-//         &[creator_wallet_info.clone(), governance_mint_info.clone(), system_program_info.clone()]
-//     )?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            stake_account_info.key,
+            stake_rent,
+            stake_space as u64,
+            stake_program_info.key
+        ),
+        &[payer_info.clone(), stake_account_info.clone(), system_program_info.clone()],
+        &[&[b"vault-stake", fund_account_info.key.as_ref(), &[stake_index], &[stake_bump]]]
+    )?;
 
-//     invoke_signed(
-//         &spl_token_metadata_interface::instruction::initialize(
-//             &spl_token_2022::id(),
-//             governance_mint_info.key,
-//             fund_account_info.key,
-//             governance_mint_info.key,
-//             fund_account_info.key,
-//             token_name,
-//             token_symbol,
-//             token_uri
-//         ),
-//         &[
-//             governance_mint_info.clone(),
-//             fund_account_info.clone(),
-//             fund_account_info.clone(),
-//             token_program_2022_info.clone()
-//         ],
-//         &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
-//     )?;
+    **vault_account_info.lamports.borrow_mut() -= unwrap_rent + amount;
+    **payer_info.lamports.borrow_mut() += unwrap_rent + stake_rent;
+    **stake_account_info.lamports.borrow_mut() += amount - stake_rent;
 
-//     // Converting the fund_name to an array of u8 of fixed size 32
-//     let bytes = fund_name.as_bytes();
-//     let mut array = [0u8; 26];
-//     let len = bytes.len().min(26);
-//     array[..len].copy_from_slice(&bytes[..len]);
+    invoke(
+        &stake_instruction::initialize(
+            stake_account_info.key,
+            &Authorized { staker: *vault_account_info.key, withdrawer: *vault_account_info.key },
+            &Lockup::default()
+        ),
+        &[stake_account_info.clone(), rent_sysvar_info.clone(), stake_program_info.clone()]
+    )?;
+
+    invoke_signed(
+        &stake_instruction::delegate_stake(
+            stake_account_info.key,
+            vault_account_info.key,
+            vote_account_info.key
+        ),
+        &[
+            stake_account_info.clone(),
+            vote_account_info.clone(),
+            clock_sysvar_info.clone(),
+            stake_history_info.clone(),
+            stake_config_info.clone(),
+            vault_account_info.clone(),
+            stake_program_info.clone(),
+        ],
+        &[vault_seeds]
+    )?;
+
+    // Keep track of the stake account in the vault
+    let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+    vault_data.staked_lamports += amount;
+    vault_data.stake_accounts.push((stake_index, amount));
+
+    let current_vault_size = vault_account_info.data_len();
+    let new_vault_size = current_vault_size + 9;
+    let current_vault_rent = vault_account_info.lamports();
+    let new_vault_rent = rent.minimum_balance(new_vault_size);
+
+    if new_vault_rent > current_vault_rent {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                vault_account_info.key,
+                new_vault_rent - current_vault_rent
+            ),
+            &[payer_info.clone(), vault_account_info.clone(), system_program_info.clone()]
+        )?;
+    }
+
+    vault_account_info.realloc(new_vault_size, false)?;
+    vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Staked {} lamports with {} (stake account {})", fund_account_info.key.to_string(), current_time, fund_name, amount, vote_account_info.key.to_string(), stake_index);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_deactivate_vault_stake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    stake_index: u8,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer ...............................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let stake_account_info = next_account_info(accounts_iter)?; // vault stake account ............................
+    let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
+    let stake_program_info = next_account_info(accounts_iter)?; // stake program ..................................
+
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (stake_pda, _stake_bump) = Pubkey::find_program_address(&[b"vault-stake", fund_pda.as_ref(), &[stake_index]], program_id);
+
+    if *fund_account_info.key != fund_pda || *vault_account_info.key != vault_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if *stake_account_info.key != stake_pda || stake_account_info.data_is_empty() {
+        return Err(FundError::InvalidStakeAccount.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = stake_leaf(1, stake_index, &Pubkey::default(), 0);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    invoke_signed(
+        &stake_instruction::deactivate_stake(stake_account_info.key, vault_account_info.key),
+        &[stake_account_info.clone(), clock_sysvar_info.clone(), vault_account_info.clone(), stake_program_info.clone()],
+        &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
+    )?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Deactivated stake account {}", fund_account_info.key.to_string(), current_time, fund_name, stake_index);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_withdraw_vault_stake(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    stake_index: u8,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer ...............................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let stake_account_info = next_account_info(accounts_iter)?; // vault stake account ............................
+    let vault_wsol_ata_info = next_account_info(accounts_iter)?; // vault's wSOL token account ...................
+    let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
+    let stake_history_info = next_account_info(accounts_iter)?; // stake history sysvar ...........................
+    let stake_program_info = next_account_info(accounts_iter)?; // stake program ..................................
+    let token_program_info = next_account_info(accounts_iter)?; // token program ..................................
+
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (stake_pda, _stake_bump) = Pubkey::find_program_address(&[b"vault-stake", fund_pda.as_ref(), &[stake_index]], program_id);
+
+    if *fund_account_info.key != fund_pda || *vault_account_info.key != vault_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if *stake_account_info.key != stake_pda || stake_account_info.data_is_empty() {
+        return Err(FundError::InvalidStakeAccount.into());
+    }
+
+    if *vault_wsol_ata_info.key != spl_associated_token_account::get_associated_token_address(&vault_pda, &wsol_mint) || vault_wsol_ata_info.data_is_empty() {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = stake_leaf(2, stake_index, &Pubkey::default(), 0);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    // Whole balance (principal, rewards and rent) comes back to the vault and the stake account is closed
+    let stake_lamports = stake_account_info.lamports();
+
+    invoke_signed(
+        &stake_instruction::withdraw(
+            stake_account_info.key,
+            vault_account_info.key,
+            vault_account_info.key,
+            stake_lamports,
+            None
+        ),
+        &[
+            stake_account_info.clone(),
+            vault_account_info.clone(),
+            clock_sysvar_info.clone(),
+            stake_history_info.clone(),
+            stake_program_info.clone(),
+        ],
+        &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
+    )?;
+
+    // Wrap it back into the vault's wSOL account
+    **vault_account_info.lamports.borrow_mut() -= stake_lamports;
+    **vault_wsol_ata_info.lamports.borrow_mut() += stake_lamports;
+
+    invoke(
+        &spl_token::instruction::sync_native(token_program_info.key, vault_wsol_ata_info.key)?,
+        &[vault_wsol_ata_info.clone(), token_program_info.clone()]
+    )?;
+
+    // Rewards and rent came back as tokens, only the principal left the staked total
+    let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+    let principal = vault_data.stake_accounts.iter().find(|(index, _)| *index == stake_index).map(|(_, principal)| *principal).ok_or(FundError::InvalidStakeAccount)?;
+    vault_data.staked_lamports = vault_data.staked_lamports.saturating_sub(principal);
+    vault_data.stake_accounts.retain(|(index, _)| *index != stake_index);
+
+    let rent = Rent::get()?;
+    let current_vault_size = vault_account_info.data_len();
+    let new_vault_size = current_vault_size - 9;
+    let current_vault_rent = rent.minimum_balance(current_vault_size);
+    let new_vault_rent = rent.minimum_balance(new_vault_size);
+
+    vault_account_info.realloc(new_vault_size, false)?;
+
+    if current_vault_rent > new_vault_rent {
+        **vault_account_info.lamports.borrow_mut() -= current_vault_rent - new_vault_rent;
+        **payer_info.lamports.borrow_mut() += current_vault_rent - new_vault_rent;
+    }
+
+    vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} Withdrew {} lamports from stake account {}", fund_account_info.key.to_string(), current_time, fund_name, stake_lamports, stake_index);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_deposit_to_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    position_index: u8,
+    protocol: u8,
+    amount: u64,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer, pays for the position account ...............
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let position_account_info = next_account_info(accounts_iter)?; // position account ...........................
+    let deposited_mint_info = next_account_info(accounts_iter)?; // mint deposited into the venue .................
+    let receipt_mint_info = next_account_info(accounts_iter)?; // receipt mint (pool mint / collateral mint) ......
+    let vault_deposit_ata_info = next_account_info(accounts_iter)?; // vault's token account of deposited mint ...
+    let vault_receipt_ata_info = next_account_info(accounts_iter)?; // vault's token account of receipt mint .....
+    let protocol_program_info = next_account_info(accounts_iter)?; // stake-pool / lending program ...............
+    let token_program_info = next_account_info(accounts_iter)?; // token program of the deposited mint ............
+    let receipt_token_program_info = next_account_info(accounts_iter)?; // token program of the receipt mint ....
+    let system_program_info = next_account_info(accounts_iter)?; // system program ................................
+    let ata_program_info = next_account_info(accounts_iter)?; // associated token program .......................
+    let venue_info = next_account_info(accounts_iter)?; // stake pool / lending reserve ..........................
+    let holdings_info = next_account_info(accounts_iter)?; // fund's vault holdings registry ......................
+
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    if protocol > 1 {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (position_pda, position_bump) = Pubkey::find_program_address(&[b"position", fund_pda.as_ref(), &[position_index]], program_id);
+
+    if *fund_account_info.key != fund_pda ||
+       *vault_account_info.key != vault_pda ||
+       *proposal_aggregator_info.key != proposal_aggregator_pda ||
+       *position_account_info.key != position_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if *token_program_info.key != *deposited_mint_info.owner || *receipt_token_program_info.key != *receipt_mint_info.owner {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if *vault_deposit_ata_info.key != get_associated_token_address_with_program_id(&vault_pda, deposited_mint_info.key, token_program_info.key) ||
+       *vault_receipt_ata_info.key != get_associated_token_address_with_program_id(&vault_pda, receipt_mint_info.key, receipt_token_program_info.key) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
+
+    if protocol == 0 && *deposited_mint_info.key != wsol_mint {
+        msg!("Stake pools only take SOL");
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    let position_exists = !position_account_info.data_is_empty();
+    if position_exists {
+        let position_data = Position::try_from_slice(&position_account_info.data.borrow())?;
+        if position_data.protocol != protocol ||
+           position_data.protocol_program != *protocol_program_info.key ||
+           position_data.venue != *venue_info.key ||
+           position_data.deposited_mint != *deposited_mint_info.key ||
+           position_data.receipt_mint != *receipt_mint_info.key {
+            return Err(FundError::InvalidPosition.into());
+        }
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = position_leaf(0, protocol, protocol_program_info.key, venue_info.key, amount);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    if vault_receipt_ata_info.data_is_empty() {
+        invoke(
+            &create_associated_token_account(
+                payer_info.key,
+                vault_account_info.key,
+                receipt_mint_info.key,
+                receipt_token_program_info.key
+            ),
+            &[
+                payer_info.clone(),
+                vault_receipt_ata_info.clone(),
+                vault_account_info.clone(),
+                receipt_mint_info.clone(),
+                system_program_info.clone(),
+                receipt_token_program_info.clone(),
+                ata_program_info.clone(),
+            ]
+        )?;
+    }
+
+    let rent = Rent::get()?;
+    let vault_seeds: &[&[u8]] = &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]];
+    let receipt_before = token_balance(vault_receipt_ata_info)?;
+
+    if protocol == 0 {
+        let withdraw_authority_info = next_account_info(accounts_iter)?; // stake pool withdraw authority .........
+        let reserve_stake_info = next_account_info(accounts_iter)?; // stake pool reserve stake ......................
+        let manager_fee_info = next_account_info(accounts_iter)?; // stake pool manager fee account ...................
+        let referrer_fee_info = next_account_info(accounts_iter)?; // referrer pool token account ....................
+        let unwrap_account_info = next_account_info(accounts_iter)?; // temporary wSOL account used to unwrap ........
+        let transit_account_info = next_account_info(accounts_iter)?; // system owned PDA handing SOL to the pool ....
+
+        let (unwrap_pda, unwrap_bump) = Pubkey::find_program_address(&[b"unwrap", fund_pda.as_ref()], program_id);
+        let (transit_pda, transit_bump) = Pubkey::find_program_address(&[b"position-sol", fund_pda.as_ref()], program_id);
+        if *unwrap_account_info.key != unwrap_pda || *transit_account_info.key != transit_pda {
+            return Err(FundError::InvalidAccountData.into());
+        }
+
+        check_stake_pool_accounts(
+            protocol_program_info,
+            venue_info,
+            withdraw_authority_info.key,
+            reserve_stake_info.key,
+            manager_fee_info.key,
+            receipt_mint_info.key,
+            receipt_token_program_info.key
+        )?;
+        let transit_seeds: &[&[u8]] = &[b"position-sol", fund_account_info.key.as_ref(), &[transit_bump]];
+
+        // Unwrap: move the wSOL into a temporary account and close it into the transit PDA
+        let unwrap_rent = rent.minimum_balance(TokenAccount::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                unwrap_account_info.key,
+                unwrap_rent,
+                TokenAccount::LEN as u64,
+                token_program_info.key
+            ),
+            &[payer_info.clone(), unwrap_account_info.clone(), system_program_info.clone()],
+            &[&[b"unwrap", fund_account_info.key.as_ref(), &[unwrap_bump]]]
+        )?;
 
-//     let members: Vec<Pubkey> = vec![*creator_wallet_info.key];
-//     let mut is_refunded = false;
-//     if expected_members == 1 {
-//         is_refunded = true;
-//     }
+        invoke(
+            &spl_token::instruction::initialize_account3(
+                token_program_info.key,
+                unwrap_account_info.key,
+                deposited_mint_info.key,
+                vault_account_info.key,
+            )?,
+            &[unwrap_account_info.clone(), deposited_mint_info.clone(), token_program_info.clone()]
+        )?;
 
-//     // Deserialization and Serialization of Fund data
-//     let fund_data = FundAccount {
-//         name: array,
-//         is_refunded,
-//         expected_members,
-//         creator_exists: true,
-//         total_deposit: 0 as u64,
-//         governance_mint: *governance_mint_info.key,
-//         vault: *vault_account_info.key,
-//         current_proposal_index: 0 as u8,
-//         created_at: current_time,
-//         is_private: privacy,
-//         members,
-//     };
-//     fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program_info.key,
+                vault_deposit_ata_info.key,
+                unwrap_account_info.key,
+                vault_account_info.key,
+                &[],
+                amount
+            )?,
+            &[vault_deposit_ata_info.clone(), unwrap_account_info.clone(), vault_account_info.clone(), token_program_info.clone()],
+            &[vault_seeds]
+        )?;
 
-//     // Deserializing the User Global PDA
-//     let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
+        invoke_signed(
+            &spl_token::instruction::close_account(
+                token_program_info.key,
+                unwrap_account_info.key,
+                transit_account_info.key,
+                vault_account_info.key,
+                &[]
+            )?,
+            &[unwrap_account_info.clone(), transit_account_info.clone(), vault_account_info.clone(), token_program_info.clone()],
+            &[vault_seeds]
+        )?;
 
-//     // Calculate current size and new size of User PDA
-//     let current_size = user_account_info.data_len();
-//     let new_size = current_size + 50;
+        stake_pool_deposit_sol(
+            protocol_program_info,
+            &[
+                venue_info.clone(),
+                withdraw_authority_info.clone(),
+                reserve_stake_info.clone(),
+                transit_account_info.clone(),
+                vault_receipt_ata_info.clone(),
+                manager_fee_info.clone(),
+                referrer_fee_info.clone(),
+                receipt_mint_info.clone(),
+                system_program_info.clone(),
+                receipt_token_program_info.clone(),
+            ],
+            amount,
+            transit_seeds
+        )?;
 
-//     // Calculate current min rent-exempt and new rent-exempt
-//     let new_min_balance = rent.minimum_balance(new_size);
-//     let current_balance = user_account_info.lamports();
+        // Whatever is left in transit is the temporary account's rent, it goes back to the payer
+        invoke_signed(
+            &system_instruction::transfer(
+                transit_account_info.key,
+                payer_info.key,
+                transit_account_info.lamports()
+            ),
+            &[transit_account_info.clone(), payer_info.clone(), system_program_info.clone()],
+            &[transit_seeds]
+        )?;
+    } else {
+        let liquidity_supply_info = next_account_info(accounts_iter)?; // reserve liquidity supply ...................
+        let lending_market_info = next_account_info(accounts_iter)?; // lending market ................................
+        let market_authority_info = next_account_info(accounts_iter)?; // lending market authority ...................
+        let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
+
+        // Token-lending moves the liquidity and mints the collateral with one token program
+        if receipt_token_program_info.key != token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
 
-//     // Deposit lamports if required
-//     if new_min_balance > current_balance {
-//         invoke(
-//             &system_instruction::transfer(
-//                 creator_wallet_info.key,
-//                 user_account_info.key,
-//                 new_min_balance - current_balance,
-//             ),
-//             &[creator_wallet_info.clone(), user_account_info.clone(), system_program_info.clone()],
-//         )?;
-//     }
+        check_lending_reserve_accounts(
+            protocol_program_info,
+            venue_info,
+            lending_market_info,
+            deposited_mint_info.key,
+            liquidity_supply_info.key,
+            receipt_mint_info.key
+        )?;
 
-//     // Reallocation for new bytes
-//     user_account_info.realloc(new_size, false)?;
-//     // Add the new fund details to user funds
-//     user_data.funds.push(UserSpecific {
-//         fund: *fund_account_info.key,
-//         governance_token_balance: 0 as u64,
-//         is_pending: false,
-//         is_eligible: true,
-//         join_time: current_time
-//     });
-//     user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+        lending_deposit_liquidity(
+            protocol_program_info,
+            &[
+                vault_deposit_ata_info.clone(),
+                vault_receipt_ata_info.clone(),
+                venue_info.clone(),
+                liquidity_supply_info.clone(),
+                receipt_mint_info.clone(),
+                lending_market_info.clone(),
+                market_authority_info.clone(),
+                vault_account_info.clone(),
+                clock_sysvar_info.clone(),
+                token_program_info.clone(),
+            ],
+            amount,
+            vault_seeds
+        )?;
+    }
 
-//     // Deserialization and Serialization of Vault Account Data
-//     let vault_data = VaultAccount {
-//         last_deposit_time: 0,
-//     };
-//     vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+    let receipt_after = token_balance(vault_receipt_ata_info)?;
+    let receipt_received = receipt_after.saturating_sub(receipt_before);
+    if receipt_received == 0 {
+        msg!("No receipt tokens were received");
+        return Err(FundError::InvalidPosition.into());
+    }
 
-//     let proposals: Vec<Proposal> = vec![];
+    if position_exists {
+        let mut position_data = Position::try_from_slice(&position_account_info.data.borrow())?;
+        position_data.deposited_amount = position_data.deposited_amount.checked_add(amount).ok_or(FundError::MathOverflow)?;
+        position_data.receipt_amount = position_data.receipt_amount.checked_add(receipt_received).ok_or(FundError::MathOverflow)?;
+        position_data.serialize(&mut &mut position_account_info.data.borrow_mut()[..])?;
+    } else {
+        let position_space = 218;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                position_account_info.key,
+                rent.minimum_balance(position_space),
+                position_space as u64,
+                program_id
+            ),
+            &[payer_info.clone(), position_account_info.clone(), system_program_info.clone()],
+            &[&[b"position", fund_account_info.key.as_ref(), &[position_index], &[position_bump]]]
+        )?;
 
-//     let proposal_aggregator_data = ProposalAggregatorAccount {
-//         index: 0 as u8,
-//         proposals
-//     };
+        let position_data = Position {
+            fund: fund_pda,
+            opened_by: *payer_info.key,
+            position_index,
+            protocol,
+            protocol_program: *protocol_program_info.key,
+            venue: *venue_info.key,
+            deposited_mint: *deposited_mint_info.key,
+            receipt_mint: *receipt_mint_info.key,
+            deposited_amount: amount,
+            receipt_amount: receipt_received,
+            opened_at: current_time,
+        };
+        position_data.serialize(&mut &mut position_account_info.data.borrow_mut()[..])?;
 
-//     proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+        // Keep track of the position in the vault
+        let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+        vault_data.positions.push(position_index);
 
-//     if privacy == 1 {
-//         let join_proposals: Vec<JoinProposal> = vec![];
+        let current_vault_size = vault_account_info.data_len();
+        let new_vault_size = current_vault_size + 1;
+        let current_vault_rent = vault_account_info.lamports();
+        let new_vault_rent = rent.minimum_balance(new_vault_size);
 
-//         let join_proposal_aggreagtor_data = JoinProposalAggregator {
-//             fund: *fund_account_info.key,
-//             index: 0 as u8,
-//             join_proposals,
-//         };
+        if new_vault_rent > current_vault_rent {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    vault_account_info.key,
+                    new_vault_rent - current_vault_rent
+                ),
+                &[payer_info.clone(), vault_account_info.clone(), system_program_info.clone()]
+            )?;
+        }
 
-//         join_proposal_aggreagtor_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
-//     }
+        vault_account_info.realloc(new_vault_size, false)?;
+        vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+    }
 
-//     msg!("[FUND-ACTIVITY] {} {} {} Fund created by: {}", fund_account_info.key.to_string(), current_time, fund_name, creator_wallet_info.key.to_string());
+    record_holding(program_id, holdings_info, fund_account_info, payer_info, system_program_info, deposited_mint_info, vault_deposit_ata_info)?;
+    record_holding(program_id, holdings_info, fund_account_info, payer_info, system_program_info, receipt_mint_info, vault_receipt_ata_info)?;
 
-//     Ok(())
-// }
+    msg!("[FUND-ACTIVITY] {} {} {} Deposited {} of {} into position {} for {} receipt tokens", fund_account_info.key.to_string(), current_time, fund_name, amount, deposited_mint_info.key.to_string(), position_index, receipt_received);
 
+    Ok(())
+}
 
-fn process_init_user_account(
+#[allow(clippy::too_many_arguments)]
+fn process_withdraw_from_position(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    cid: String,
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    position_index: u8,
+    amount: u64,
+    merkel_proof: Vec<[u8; 32]>,
 ) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
     let accounts_iter = &mut accounts.iter();
-    let creator_account_info = next_account_info(accounts_iter)?; // User Wallet
-    let user_account_info = next_account_info(accounts_iter)?; // User PDA Account to be created
-    let system_program_info = next_account_info(accounts_iter)?; // System Program
+    let payer_info = next_account_info(accounts_iter)?; // payer ...............................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let position_account_info = next_account_info(accounts_iter)?; // position account ...........................
+    let opener_info = next_account_info(accounts_iter)?; // wallet that paid for the position ....................
+    let receipt_mint_info = next_account_info(accounts_iter)?; // receipt mint (pool mint / collateral mint) ......
+    let vault_deposit_ata_info = next_account_info(accounts_iter)?; // vault's token account of deposited mint ...
+    let vault_receipt_ata_info = next_account_info(accounts_iter)?; // vault's token account of receipt mint .....
+    let protocol_program_info = next_account_info(accounts_iter)?; // stake-pool / lending program ...............
+    let token_program_info = next_account_info(accounts_iter)?; // token program of the deposited mint ............
+    let receipt_token_program_info = next_account_info(accounts_iter)?; // token program of the receipt mint ....
+    let venue_info = next_account_info(accounts_iter)?; // stake pool / lending reserve ..........................
 
-    // User should be the signer
-    if !creator_account_info.is_signer {
-        msg!("[USER-ERROR] {} Wrong signer!(must be your wallet)", creator_account_info.key.to_string());
+    if !payer_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    // Derive User PDA and check if provided is same as expected
-    let (user_pda, user_bump) = Pubkey::find_program_address(&[b"user", creator_account_info.key.as_ref()], program_id);
-    if *user_account_info.key != user_pda {
-        msg!("[USER-ERROR] {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", creator_account_info.key.to_string());
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (position_pda, _position_bump) = Pubkey::find_program_address(&[b"position", fund_pda.as_ref(), &[position_index]], program_id);
+
+    if *fund_account_info.key != fund_pda ||
+       *vault_account_info.key != vault_pda ||
+       *proposal_aggregator_info.key != proposal_aggregator_pda ||
+       *position_account_info.key != position_pda {
         return Err(FundError::InvalidAccountData.into());
     }
 
-    // If user PDA already exists
-    if !user_account_info.data_is_empty() {
-        msg!("[USER-ERROR] {} User already exists", creator_account_info.key.to_string());
-        return Ok(());
+    if position_account_info.data_is_empty() {
+        return Err(FundError::InvalidPosition.into());
     }
 
-    // Calculate rent-exempt
-    let rent = Rent::get()?;
-    let user_space = 59 + 4 as usize;
-    
-    // Create the User PDA Account which is it's global identity
-    invoke_signed(
-        &system_instruction::create_account(
-            creator_account_info.key,
-            user_account_info.key,
-            rent.minimum_balance(user_space),
-            user_space as u64,
-            program_id
-        ),
-        &[creator_account_info.clone(), user_account_info.clone(), system_program_info.clone()],
-        &[&[b"user", creator_account_info.key.as_ref(), &[user_bump]]]
-    )?;
-
-    // Initially user is joined in no Funds
-    let funds:Vec<UserSpecific> = vec![];
-
-    let bytes = cid.as_bytes();
-    let mut array = [0u8; 59];
-    let len = bytes.len().min(59);
-    array[..len].copy_from_slice(&bytes[..len]);
-    // Deserialization and Serialization of User Account Data
-    let user_data = UserAccount {
-        user_cid: array,
-        funds,
-    };
-    user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
-
-    Ok(())
-
-}
-
-// fn process_init_join_proposal(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-//     fund_name: String,
-// ) -> ProgramResult {
-//     let creation_time = Clock::get()?.unix_timestamp;
+    let mut position_data = Position::try_from_slice(&position_account_info.data.borrow())?;
+    if position_data.protocol_program != *protocol_program_info.key ||
+       position_data.venue != *venue_info.key ||
+       position_data.receipt_mint != *receipt_mint_info.key ||
+       position_data.opened_by != *opener_info.key {
+        return Err(FundError::InvalidPosition.into());
+    }
 
-//     let accounts_iter = &mut accounts.iter();
-//     let joiner_account_info = next_account_info(accounts_iter)?; // joiner wallet ............................
-//     let join_proposal_aggregator_info = next_account_info(accounts_iter)?; // join proposal aggregator .......
-//     let fund_account_info = next_account_info(accounts_iter)?; // fund account ...............................
-//     let vote_account_info = next_account_info(accounts_iter)?; // join votes aggregator ......................
-//     let system_program_info = next_account_info(accounts_iter)?; // system program ...........................
-//     let user_account_info = next_account_info(accounts_iter)?; // user's global account ......................
+    if *receipt_token_program_info.key != *receipt_mint_info.owner ||
+       *vault_deposit_ata_info.owner != *token_program_info.key ||
+       *vault_receipt_ata_info.owner != *receipt_token_program_info.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-//     if !joiner_account_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
-//     let mut proposal_index = 0;
-//     let index = 0 as u8;
+    if *vault_deposit_ata_info.key != get_associated_token_address_with_program_id(&vault_pda, &position_data.deposited_mint, token_program_info.key) ||
+       *vault_receipt_ata_info.key != get_associated_token_address_with_program_id(&vault_pda, &position_data.receipt_mint, receipt_token_program_info.key) ||
+       vault_deposit_ata_info.data_is_empty() {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
 
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let (join_proposal_pda, _join_proposal_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
-//     let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", joiner_account_info.key.as_ref()], program_id);
-//     let (join_vote_pda, join_vote_bump) = Pubkey::find_program_address(&[b"join-vote", &[proposal_index], fund_pda.as_ref()], program_id);
+    if amount == 0 || amount > position_data.receipt_amount {
+        return Err(FundError::InvalidPosition.into());
+    }
 
-//     if *fund_account_info.key != fund_pda || *join_proposal_aggregator_info.key != join_proposal_pda || *user_account_info.key != user_pda || *vote_account_info.key != join_vote_pda{
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = position_leaf(1, position_data.protocol, protocol_program_info.key, venue_info.key, amount);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    let vault_seeds: &[&[u8]] = &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]];
+    let received;
+
+    if position_data.protocol == 0 {
+        let withdraw_authority_info = next_account_info(accounts_iter)?; // stake pool withdraw authority .........
+        let reserve_stake_info = next_account_info(accounts_iter)?; // stake pool reserve stake ......................
+        let manager_fee_info = next_account_info(accounts_iter)?; // stake pool manager fee account ...................
+        let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
+        let stake_history_info = next_account_info(accounts_iter)?; // stake history sysvar ...........................
+        let stake_program_info = next_account_info(accounts_iter)?; // stake program ..................................
+
+        check_stake_pool_accounts(
+            protocol_program_info,
+            venue_info,
+            withdraw_authority_info.key,
+            reserve_stake_info.key,
+            manager_fee_info.key,
+            receipt_mint_info.key,
+            receipt_token_program_info.key
+        )?;
 
-//     if !vote_account_info.data_is_empty() {
-//         return Err(FundError::InvalidVoteAccount.into());
-//     }
+        let lamports_before = vault_account_info.lamports();
 
-//     let mut join_proposal_data = JoinProposalAggregator::try_from_slice(&join_proposal_aggregator_info.data.borrow())?;
+        stake_pool_withdraw_sol(
+            protocol_program_info,
+            &[
+                venue_info.clone(),
+                withdraw_authority_info.clone(),
+                vault_account_info.clone(),
+                vault_receipt_ata_info.clone(),
+                reserve_stake_info.clone(),
+                vault_account_info.clone(),
+                manager_fee_info.clone(),
+                receipt_mint_info.clone(),
+                clock_sysvar_info.clone(),
+                stake_history_info.clone(),
+                stake_program_info.clone(),
+                receipt_token_program_info.clone(),
+            ],
+            amount,
+            vault_seeds
+        )?;
 
-//     // Deserialize User Data and check if User is already a member of provided Fund
-//     let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
-//     if user_data.funds.iter().any(|entry| entry.fund == *fund_account_info.key) {
-//         msg!("[FUND-ERROR] {} {} Either a member already, or has a pending join proposal for this fund!", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::AlreadyMember.into());
-//     }
+        // Wrap the SOL back into the vault's wSOL account
+        received = vault_account_info.lamports().saturating_sub(lamports_before);
+        **vault_account_info.lamports.borrow_mut() -= received;
+        **vault_deposit_ata_info.lamports.borrow_mut() += received;
 
-//     // check if already applied for entry in fund
-//     let joiner_in_queue = join_proposal_data
-//         .join_proposals
-//         .iter()
-//         .any(|proposal| proposal.joiner == *joiner_account_info.key);
+        invoke(
+            &spl_token::instruction::sync_native(token_program_info.key, vault_deposit_ata_info.key)?,
+            &[vault_deposit_ata_info.clone(), token_program_info.clone()]
+        )?;
+    } else {
+        let liquidity_supply_info = next_account_info(accounts_iter)?; // reserve liquidity supply ...................
+        let lending_market_info = next_account_info(accounts_iter)?; // lending market ................................
+        let market_authority_info = next_account_info(accounts_iter)?; // lending market authority ...................
+        let clock_sysvar_info = next_account_info(accounts_iter)?; // clock sysvar ...................................
 
-//     if joiner_in_queue {
-//         msg!("[FUND-ERROR] {} {} You have already applied for this fund! If want to apply again, delete your existing proposal in the 'Pending Funds' section.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::AlreadyAppliedForEntry.into());
-//     }
+        if receipt_token_program_info.key != token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
 
-//     let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-//     if fund_data.members.len() >= fund_data.expected_members as usize {
-//         msg!("[FUND-ERROR] {} {} Fund already exists!!", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::FundAlreadyFull.into());
-//     }
+        check_lending_reserve_accounts(
+            protocol_program_info,
+            venue_info,
+            lending_market_info,
+            &position_data.deposited_mint,
+            liquidity_supply_info.key,
+            receipt_mint_info.key
+        )?;
 
-//     let rent = Rent::get()?;
-//     let extra_space = 57 as usize;
-//     let current_space = join_proposal_aggregator_info.data_len();
-//     let current_rent = join_proposal_aggregator_info.lamports();
-//     let new_space = current_space + extra_space;
-//     let new_rent = rent.minimum_balance(new_space);
+        let deposit_before = token_balance(vault_deposit_ata_info)?;
 
-//     if new_rent > current_rent {
-//         invoke(
-//             &system_instruction::transfer(
-//                 joiner_account_info.key,
-//                 join_proposal_aggregator_info.key,
-//                 new_rent - current_rent,
-//             ),
-//             &[joiner_account_info.clone(), join_proposal_aggregator_info.clone(), system_program_info.clone()]
-//         )?;
-//     }
+        lending_redeem_collateral(
+            protocol_program_info,
+            &[
+                vault_receipt_ata_info.clone(),
+                vault_deposit_ata_info.clone(),
+                venue_info.clone(),
+                receipt_mint_info.clone(),
+                liquidity_supply_info.clone(),
+                lending_market_info.clone(),
+                market_authority_info.clone(),
+                vault_account_info.clone(),
+                clock_sysvar_info.clone(),
+                token_program_info.clone(),
+            ],
+            amount,
+            vault_seeds
+        )?;
 
-//     join_proposal_aggregator_info.realloc(new_space, false)?;
-//     let num_of_join_proposals = join_proposal_data.join_proposals.len();
-//     if num_of_join_proposals != 0 {
-//         proposal_index = join_proposal_data.join_proposals[join_proposal_data.join_proposals.len() - 1].proposal_index + 1;
-//     }
-//     join_proposal_data.join_proposals.push(JoinProposal {
-//         joiner: *joiner_account_info.key,
-//         votes_yes: 0 as u64,
-//         votes_no: 0 as u64,
-//         creation_time,
-//         proposal_index,
-//     });
+        received = token_balance(vault_deposit_ata_info)?.saturating_sub(deposit_before);
+    }
 
-//     join_proposal_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
+    // Principal leaves the position in the same proportion as the receipt tokens
+    let principal_out = (((position_data.deposited_amount as u128) * (amount as u128))/(position_data.receipt_amount as u128)) as u64;
+    position_data.deposited_amount -= principal_out;
+    position_data.receipt_amount -= amount;
 
-//     let vote_space = 5 as usize;
-    
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             joiner_account_info.key,
-//             vote_account_info.key,
-//             rent.minimum_balance(vote_space),
-//             vote_space as u64,
-//             program_id
-//         ),
-//         &[joiner_account_info.clone(), vote_account_info.clone(), system_program_info.clone()],
-//         &[&[b"join-vote", &[proposal_index], fund_pda.as_ref(), &[join_vote_bump]]]
-//     )?;
+    if position_data.receipt_amount != 0 {
+        position_data.serialize(&mut &mut position_account_info.data.borrow_mut()[..])?;
+    } else {
+        // Position is fully unwound, close it and drop it from the vault
+        let position_lamports = position_account_info.lamports();
+        **position_account_info.lamports.borrow_mut() = 0;
+        **opener_info.lamports.borrow_mut() += position_lamports;
+        position_account_info.data.borrow_mut().fill(0);
 
-//     let voters: Vec<(Pubkey, u8)> = vec![];
+        let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+        vault_data.positions.retain(|index| *index != position_index);
 
-//     let join_vote_data = JoinVoteAccount {
-//         proposal_index,
-//         voters,
-//     };
+        let rent = Rent::get()?;
+        let current_vault_size = vault_account_info.data_len();
+        let new_vault_size = current_vault_size - 1;
+        let current_vault_rent = rent.minimum_balance(current_vault_size);
+        let new_vault_rent = rent.minimum_balance(new_vault_size);
 
-//     join_vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+        vault_account_info.realloc(new_vault_size, false)?;
 
-//     let current_user_size = user_account_info.data_len();
-//     let new_user_size = current_user_size + 50;
-//     let current_user_rent = user_account_info.lamports();
-//     let new_user_rent = rent.minimum_balance(new_user_size);
+        if current_vault_rent > new_vault_rent {
+            **vault_account_info.lamports.borrow_mut() -= current_vault_rent - new_vault_rent;
+            **payer_info.lamports.borrow_mut() += current_vault_rent - new_vault_rent;
+        }
 
-//     if new_user_rent > current_user_rent {
-//         invoke(
-//             &system_instruction::transfer(
-//                 joiner_account_info.key,
-//                 user_account_info.key,
-//                 new_user_rent - current_user_rent
-//             ),
-//             &[joiner_account_info.clone(), user_account_info.clone(), system_program_info.clone()]
-//         )?;
-//     }
+        vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+    }
 
-//     user_account_info.realloc(new_user_size, false)?;
+    msg!("[FUND-ACTIVITY] {} {} {} Redeemed {} receipt tokens from position {} for {}", fund_account_info.key.to_string(), current_time, fund_name, amount, position_index, received);
 
-//     user_data.funds.push(UserSpecific {
-//         fund: *fund_account_info.key,
-//         governance_token_balance: 0 as u64,
-//         is_pending: true,
-//         is_eligible: false,
-//         join_time: creation_time
-//     });
+    Ok(())
+}
 
-//     user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+#[allow(clippy::too_many_arguments)]
+fn process_set_fee_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    recipient: [u8; 32],
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    payout_mode: u8,
+    price_feeds: Vec<PriceFeed>,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-//     msg!("[FUND-ACTIVITY] {} {} {} {} created proposal to join the fund", fund_account_info.key.to_string(), creation_time, fund_name, joiner_account_info.key.to_string());
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer, pays for the fee account ....................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let fee_account_info = next_account_info(accounts_iter)?; // fee account ....................................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ................................
 
-//     Ok(())
-// }
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-// fn process_vote_on_join_proposal(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-//     vote: u8,
-//     fund_name: String,
-//     proposal_index: u8
-// ) -> ProgramResult {
-//     let current_time = Clock::get()?.unix_timestamp;
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
 
-//     let accounts_iter = &mut accounts.iter();
-//     let voter_account_info = next_account_info(accounts_iter)?; // Voter Wallet ..................................
-//     let vote_account_info = next_account_info(accounts_iter)?; // Join Votes aggregator ..........................
-//     let join_proposal_aggregator_info = next_account_info(accounts_iter)?; // Join proposal aggregator ...........
-//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
-//     let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
-//     let governance_token_mint_info = next_account_info(accounts_iter)?; // Governance Mint account ...............
-//     let voter_token_account_info = next_account_info(accounts_iter)?; // Voter's governance token account ........
-//     let token_program_2022_info = next_account_info(accounts_iter)?; // token extension program ..................
-//     let joiner_account_info = next_account_info(accounts_iter)?; // joiner wallet ................................
-//     let joiner_pda_info = next_account_info(accounts_iter)?; // joiner global account ............................
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if !voter_account_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    let recipient = Pubkey::new_from_array(recipient);
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = fee_config_leaf(&recipient, management_fee_bps, performance_fee_bps, payout_mode, &price_feeds);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
 
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let index = 0 as u8;
-//     let (join_proposal_pda, _join_proposal_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
-//     let (join_vote_pda, _join_vote_bump) = Pubkey::find_program_address(&[b"join-vote", &[proposal_index], fund_pda.as_ref()], program_id);
-//     let (governance_mint, _governance_bump) = Pubkey::find_program_address(&[b"governance", fund_pda.as_ref()], program_id);
-//     let (joiner_pda, _joiner_bump) = Pubkey::find_program_address(&[b"user", joiner_account_info.key.as_ref()], program_id);
+    write_fee_config(program_id, payer_info, fund_account_info, fee_account_info, system_program_info, recipient, management_fee_bps, performance_fee_bps, payout_mode, price_feeds, current_time)?;
 
-//     if *fund_account_info.key != fund_pda ||
-//        *join_proposal_aggregator_info.key != join_proposal_pda ||
-//        *vote_account_info.key != join_vote_pda ||
-//        *governance_token_mint_info.key != governance_mint ||
-//        *joiner_pda_info.key != joiner_pda {
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    msg!("[FUND-ACTIVITY] {} {} {} Fees set: management {} bps, performance {} bps to {}", fund_account_info.key.to_string(), current_time, fund_name, management_fee_bps, performance_fee_bps, recipient.to_string());
 
-//     let token_account = spl_associated_token_account::get_associated_token_address_with_program_id(
-//         voter_account_info.key,
-//         governance_token_mint_info.key,
-//         token_program_2022_info.key
-//     );
+    Ok(())
+}
 
-//     if token_account != *voter_token_account_info.key {
-//         msg!("[FUND-ERROR] {} {} Your token account doesn's match the derived one.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::InvalidTokenAccount.into());
-//     }
+/// Creates the fund's fee account or updates its settings, resizing it for the price feeds with
+/// the payer covering or getting back the rent difference.
+#[allow(clippy::too_many_arguments)]
+fn write_fee_config<'a>(
+    program_id: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    fund_account_info: &AccountInfo<'a>,
+    fee_account_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    recipient: Pubkey,
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    payout_mode: u8,
+    price_feeds: Vec<PriceFeed>,
+    current_time: i64,
+) -> ProgramResult {
+    if management_fee_bps > MAX_MANAGEMENT_FEE_BPS || performance_fee_bps > MAX_PERFORMANCE_FEE_BPS || payout_mode > 1 {
+        msg!("Fee configuration out of bounds");
+        return Err(FundError::InvalidFeeConfig.into());
+    }
 
-//     if voter_token_account_info.data_is_empty() {
-//         msg!("[FUND-ERROR] {} {} Your token account does not exist.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return  Err(FundError::NoVotingPower.into());
-//     }
+    let (fee_pda, fee_bump) = Pubkey::find_program_address(&[b"fees", fund_account_info.key.as_ref()], program_id);
+    if *fee_account_info.key != fee_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if vote_account_info.data_is_empty() {
-//         msg!("[FUND-ERROR] {} {} The vote account for this proposal doesn't exist. Maybe the proposal is cancelled.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::InvalidVoteAccount.into());
-//     }
+    let rent = Rent::get()?;
+    let new_fee_space = 129 + 64 * price_feeds.len();
 
-//     let mut join_proposal_data = JoinProposalAggregator::try_from_slice(&join_proposal_aggregator_info.data.borrow())?;
-//     let (matched_index, proposal) = join_proposal_data
-//         .join_proposals
-//         .iter()
-//         .enumerate()
-//         .find(|(_, p)| p.proposal_index == proposal_index)
-//         .ok_or(FundError::InvalidAccountData)?;
+    let fee_data = if fee_account_info.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                fee_account_info.key,
+                rent.minimum_balance(new_fee_space),
+                new_fee_space as u64,
+                program_id
+            ),
+            &[payer_info.clone(), fee_account_info.clone(), system_program_info.clone()],
+            &[&[b"fees", fund_account_info.key.as_ref(), &[fee_bump]]]
+        )?;
 
-//     if proposal.joiner != *joiner_account_info.key {
-//         msg!("[FUND-ERROR] {} {} The proposal's joiner wallet differs from what is provided.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+        FeeAccount {
+            fund: *fund_account_info.key,
+            recipient,
+            management_fee_bps,
+            performance_fee_bps,
+            payout_mode,
+            high_water_mark: 0,
+            last_nav: 0,
+            last_crystallized: current_time,
+            management_fees_accrued: 0,
+            performance_fees_accrued: 0,
+            fees_paid: 0,
+            fee_shares_minted: 0,
+            price_feeds,
+        }
+    } else {
+        // Accrual history and the high-water mark are kept, only the settings change
+        let mut fee_data = FeeAccount::try_from_slice(&fee_account_info.data.borrow())?;
+        fee_data.recipient = recipient;
+        fee_data.management_fee_bps = management_fee_bps;
+        fee_data.performance_fee_bps = performance_fee_bps;
+        fee_data.payout_mode = payout_mode;
+        fee_data.price_feeds = price_feeds;
+
+        let current_fee_rent = fee_account_info.lamports();
+        let new_fee_rent = rent.minimum_balance(new_fee_space);
+        if new_fee_rent > current_fee_rent {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    fee_account_info.key,
+                    new_fee_rent - current_fee_rent
+                ),
+                &[payer_info.clone(), fee_account_info.clone(), system_program_info.clone()]
+            )?;
+        }
 
-//     let mut joiner_data = UserAccount::try_from_slice(&joiner_pda_info.data.borrow())?;
-//     let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-//     let is_voter_member = fund_data
-//         .members
-//         .iter()
-//         .any(|member| *member == *voter_account_info.key);
+        fee_account_info.realloc(new_fee_space, false)?;
 
-//     if !is_voter_member {
-//         msg!("[FUND-ERROR] {} {} You are not a member of the fund and so cannot vote.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::NotAFundMember.into());
-//     }
+        if current_fee_rent > new_fee_rent {
+            **fee_account_info.lamports.borrow_mut() -= current_fee_rent - new_fee_rent;
+            **payer_info.lamports.borrow_mut() += current_fee_rent - new_fee_rent;
+        }
 
-//     let mut vote_data = JoinVoteAccount::try_from_slice(&mut &mut vote_account_info.data.borrow())?;
+        fee_data
+    };
 
-//     // check if already voted
-//     let voter_exists = vote_data
-//         .voters
-//         .iter()
-//         .any(|(key, _)| *key == *voter_account_info.key);
+    fee_data.serialize(&mut &mut fee_account_info.data.borrow_mut()[..])?;
 
-//     if voter_exists {
-//         msg!("[FUND-ERROR] {} {} You have voted for this proposal already.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::AlreadyVoted.into());
-//     }
+    Ok(())
+}
 
-//     let rent = Rent::get()?;
-//     let current_space = vote_account_info.data_len();
-//     let new_space = current_space + 33 as usize;
-//     let current_rent = vote_account_info.lamports();
-//     let new_rent = rent.minimum_balance(new_space);
+/// Oracle feed the fee account lists for a mint.
+fn price_feed_of(fee_data: &FeeAccount, mint: &Pubkey) -> Result<[u8; 32], ProgramError> {
+    fee_data
+        .price_feeds
+        .iter()
+        .find(|price_feed| price_feed.mint == *mint)
+        .map(|price_feed| price_feed.feed_id)
+        .ok_or(FundError::MissingPriceFeed.into())
+}
 
-//     // transfer reallocation lamports
-//     if new_rent > current_rent {
-//         invoke(
-//             &system_instruction::transfer(
-//                 voter_account_info.key,
-//                 vote_account_info.key,
-//                 new_rent - current_rent
-//             ),
-//             &[voter_account_info.clone(), vote_account_info.clone(), system_program_info.clone()]
-//         )?;
-//     }
+/// NAV of a fund: every vault holding in the registry plus natively staked SOL, valued at oracle
+/// prices. Each holding comes as its vault token account, mint and price update, in registry
+/// order, and is handed back with its balance, decimals and price.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn vault_nav<'a, 'b>(
+    program_id: &Pubkey,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    fee_data: &FeeAccount,
+    holdings_info: &AccountInfo<'a>,
+    fund: &Pubkey,
+    staked_lamports: u64,
+    sol_price_update_info: &AccountInfo<'a>,
+    num_of_tokens: u8,
+    current_time: i64,
+) -> Result<(u128, Vec<(&'b AccountInfo<'a>, &'b AccountInfo<'a>, u64, u8, u64)>), ProgramError> {
+    let holdings_data = load_vault_holdings(program_id, holdings_info, fund)?;
+    if num_of_tokens as usize != holdings_data.holdings.len() {
+        msg!("All of the vault's {} holdings must be provided", holdings_data.holdings.len());
+        return Err(FundError::InvalidMints.into());
+    }
 
-//     vote_account_info.realloc(new_space, false)?;
-//     vote_data.voters.push((*voter_account_info.key, vote));
-//     vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+    let mut nav: u128 = 0;
+    let mut holdings: Vec<(&AccountInfo, &AccountInfo, u64, u8, u64)> = vec![];
+    for holding in holdings_data.holdings.iter() {
+        let vault_ata_info = next_account_info(accounts_iter)?;
+        let mint_account_info = next_account_info(accounts_iter)?;
+        let price_update_info = next_account_info(accounts_iter)?;
 
-    
-//     if *voter_token_account_info.owner != *token_program_2022_info.key {
-//         msg!("[FUND-ERROR] {} {} The owner of your token account is not Token Program 2022.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+        if *vault_ata_info.key != holding.ata || *mint_account_info.key != holding.mint {
+            return Err(FundError::InvalidMints.into());
+        }
 
-//     let token_account_data = voter_token_account_info.try_borrow_data()?;
-//     let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
-//     let base_account = token_account.base;
-//     let balance = base_account.amount;
-//     if vote == 0 {
-//         join_proposal_data.join_proposals[matched_index as usize].votes_no += balance;
-//     } else {
-//         join_proposal_data.join_proposals[matched_index as usize].votes_yes += balance;
-//     }
+        let balance = token_balance(vault_ata_info)?;
+        let decimals = supported_mint_decimals(mint_account_info)?;
+        let (price, expo) = read_oracle_price(price_update_info, &price_feed_of(fee_data, mint_account_info.key)?, current_time)?;
+        let price = normalize_price(price, expo)?;
 
-//     join_proposal_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
-
-//     if 2 * join_proposal_data.join_proposals[matched_index as usize].votes_yes >= fund_data.total_deposit {
-//         if let Some(user_specific) = joiner_data
-//             .funds
-//             .iter_mut()
-//             .find(|entry| entry.fund == *fund_account_info.key) {
-//                 user_specific.is_pending = true;
-//                 // user_specific.is_eligible = true;
-//             } else {
-//                 msg!("[FUND-ERROR] {} {} User is not a member in this fund.", fund_account_info.key.to_string(), voter_account_info.key.to_string());
-//                 return Err(FundError::InvalidAccountData.into());
-//             }
-//     }
+        nav = nav.checked_add(token_value(balance, decimals, price)?).ok_or(FundError::MathOverflow)?;
+        holdings.push((vault_ata_info, mint_account_info, balance, decimals, price));
+    }
 
-//     joiner_data.serialize(&mut &mut joiner_pda_info.data.borrow_mut()[..])?;
+    if staked_lamports != 0 {
+        let wsol_mint = pubkey!("So11111111111111111111111111111111111111112");
+        let (price, expo) = read_oracle_price(sol_price_update_info, &price_feed_of(fee_data, &wsol_mint)?, current_time)?;
+        nav = nav.checked_add(token_value(staked_lamports, 9, normalize_price(price, expo)?)?).ok_or(FundError::MathOverflow)?;
+    }
 
-//     msg!("[FUND-ACTIVITY] {} {} {} {} voted for addition of {}", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), joiner_account_info.key.to_string());
+    Ok((nav, holdings))
+}
 
-//     Ok(())
-// }
+fn process_crystallize_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    num_of_tokens: u8,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can crystallize ............................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // fund's vault account ...........................
+    let fee_account_info = next_account_info(accounts_iter)?; // fee account ....................................
+    let recipient_membership_info = next_account_info(accounts_iter)?; // fee recipient's membership PDA ..........
+    let sol_price_update_info = next_account_info(accounts_iter)?; // SOL price update, values staked SOL ..........
+    let token_program_info = next_account_info(accounts_iter)?; // token program ..................................
+    let holdings_info = next_account_info(accounts_iter)?; // fund's vault holdings registry ......................
+    let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 ........................
 
-// fn process_cancel_join_proposal(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-//     fund_name: String,
-//     proposal_index: u8,
-// ) -> ProgramResult {
-//     let accounts_iter = &mut accounts.iter();
-//     let joiner_account_info = next_account_info(accounts_iter)?; //  joiner wallet ...........................
-//     let joiner_pda_info = next_account_info(accounts_iter)?; // joiner global pda ............................
-//     let fund_account_info = next_account_info(accounts_iter)?; // fund account ...............................
-//     let join_proposal_aggregator_info = next_account_info(accounts_iter)?; // join proposal aggregator .......
-//     let vote_account_info = next_account_info(accounts_iter)?; // vote account ...............................
-//     let rent_reserve_info = next_account_info(accounts_iter)?; // rent reserve ...............................
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     if !joiner_account_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (fee_pda, _fee_bump) = Pubkey::find_program_address(&[b"fees", fund_pda.as_ref()], program_id);
 
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let (joiner_pda, _joiner_bump) = Pubkey::find_program_address(&[b"user", joiner_account_info.key.as_ref()], program_id);
-//     let index = 0 as u8;
-//     let (join_aggregator_pda, _join_aggregator_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_account_info.key.as_ref()], program_id);
-//     let (vote_pda, _vote_bump) = Pubkey::find_program_address(&[b"join-vote", &[proposal_index], fund_account_info.key.as_ref()], program_id);
-//     let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    if *fund_account_info.key != fund_pda || *vault_account_info.key != vault_pda || *fee_account_info.key != fee_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if *fund_account_info.key != fund_pda ||
-//        *joiner_pda_info.key != joiner_pda ||
-//        *join_proposal_aggregator_info.key != join_aggregator_pda ||
-//        *vote_account_info.key != vote_pda ||
-//        *rent_reserve_info.key != rent_pda {
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//        }
+    if fee_account_info.data_is_empty() {
+        return Err(FundError::InvalidFeeConfig.into());
+    }
 
-//     let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-//     if fund_data.is_private == 0 {
-//         msg!("[FUND-ERROR] {} {} This fund is not private.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::InvalidFundDetails.into());
-//     }
+    let mut fee_data = FeeAccount::try_from_slice(&fee_account_info.data.borrow())?;
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
 
-//     let is_joiner_member = fund_data
-//        .members
-//        .iter()
-//        .any(|member| *member == *joiner_account_info.key);
+    let (nav, holdings) = vault_nav(program_id, accounts_iter, &fee_data, holdings_info, &fund_pda, vault_data.staked_lamports, sol_price_update_info, num_of_tokens, current_time)?;
 
-//     if is_joiner_member {
-//         msg!("[FUND-ERROR] {} {} You are already a member of this fund.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::AlreadyMember.into());
-//     }
+    let total_shares = fund_data.total_deposit as u128;
+    let elapsed = current_time.saturating_sub(fee_data.last_crystallized).max(0) as u128;
+    msg!("NAV: {}, Shares: {}, Elapsed: {}", nav, total_shares, elapsed);
 
-//     let mut joiner_data = UserAccount::try_from_slice(&joiner_pda_info.data.borrow())?;
-//     let user_specific = joiner_data
-//         .funds
-//         .iter()
-//         .find(|user_specific| user_specific.fund == *fund_account_info.key)
-//         .ok_or(FundError::InvalidAccountData)?;
+    let (management_fee, performance_fee) = crystallization_fees(
+        nav,
+        total_shares,
+        elapsed,
+        fee_data.management_fee_bps,
+        fee_data.performance_fee_bps,
+        fee_data.high_water_mark
+    );
+    let fee_value = management_fee + performance_fee;
+    msg!("Management Fee: {}, Performance Fee: {}", management_fee, performance_fee);
+
+    let mut fees_paid: u128 = 0;
+    if fee_value != 0 {
+        if fee_data.payout_mode == 0 {
+            // New shares dilute everyone else by exactly the fee value
+            let fee_shares = fee_shares(fee_value, total_shares, nav)?;
+
+            let mut recipient_membership = load_member(program_id, recipient_membership_info, fund_account_info.key, &fee_data.recipient)?;
+            recipient_membership.deposit += fee_shares;
+            recipient_membership.serialize(&mut &mut recipient_membership_info.data.borrow_mut()[..])?;
+
+            if let Some((governance_mint_info, governance_token_account_info, governance_program_info)) = next_governance_accounts(accounts_iter, &fund_data, &fee_data.recipient)? {
+                if governance_token_account_info.data_is_empty() {
+                    msg!("Recipient's governance token account doesn't exist");
+                    return Err(FundError::InvalidTokenAccount.into());
+                }
 
-//     if !user_specific.is_pending {
-//         msg!("[FUND-ERROR] {} {} You are already a member of this fund.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::AlreadyMember.into());
-//     }
+                mint_governance_tokens(
+                    governance_program_info,
+                    governance_mint_info,
+                    governance_token_account_info,
+                    fund_account_info,
+                    fee_shares,
+                    &[b"light-fund", fund_name.as_bytes(), &[fund_bump]]
+                )?;
+            }
 
-//     let mut join_proposal_data = JoinProposalAggregator::try_from_slice(&join_proposal_aggregator_info.data.borrow())?;
-//     let (_matched_index, proposal) = join_proposal_data
-//         .join_proposals
-//         .iter()
-//         .enumerate()
-//         .find(|(_, proposal)| proposal.proposal_index == proposal_index)
-//         .ok_or(FundError::InvalidAccountData)?;
+            fund_data.total_deposit += fee_shares;
+            fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
-//     if proposal.joiner != *joiner_account_info.key {
-//         msg!("[FUND-ERROR] {} {} Wrong joiner/proposer wallet address.", fund_account_info.key.to_string(), joiner_account_info.key.to_string());
-//         return Err(FundError::InvalidProposerInfo.into());
-//     }
+            fee_data.fee_shares_minted += fee_shares;
+            fees_paid = fee_value;
+        } else {
+            // Same share of every listed holding goes to the recipient's token accounts. Stake
+            // can't be paid out this way, so the share is taken of the tokens' value alone
+            let mut token_nav: u128 = 0;
+            for (_, _, balance, decimals, price) in holdings.iter() {
+                token_nav = token_nav.checked_add(token_value(*balance, *decimals, *price)?).ok_or(FundError::MathOverflow)?;
+            }
+            if fee_value > token_nav {
+                msg!("Fee of {} is more than the vault's {} in tokens", fee_value, token_nav);
+                return Err(FundError::NotEnoughFunds.into());
+            }
+            let fee_percent = fee_value * 100_000_000 / token_nav;
+            for (vault_ata_info, mint_account_info, balance, decimals, price) in holdings.iter() {
+                let recipient_ata_info = next_account_info(accounts_iter)?;
+                if *recipient_ata_info.key != get_associated_token_address_with_program_id(&fee_data.recipient, mint_account_info.key, mint_account_info.owner) {
+                    return Err(FundError::InvalidTokenAccount.into());
+                }
 
-//     join_proposal_data.join_proposals.retain(|proposal| proposal.proposal_index != proposal_index);
+                let amount_to_transfer = ((*balance as u128) * fee_percent / 100_000_000) as u64;
+                if amount_to_transfer == 0 {
+                    continue;
+                }
 
-//     let rent = Rent::get()?;
-//     let current_aggregator_size = join_proposal_aggregator_info.data_len();
-//     let new_aggregator_size = current_aggregator_size - 57;
-//     let current_aggregator_rent = join_proposal_aggregator_info.lamports();
-//     let new_aggregator_rent = rent.minimum_balance(new_aggregator_size);
+                let holding_token_program = if *mint_account_info.owner == spl_token_2022::id() {
+                    token_program_2022_info
+                } else {
+                    token_program_info
+                };
+
+                transfer_tokens(
+                    holding_token_program,
+                    vault_ata_info,
+                    mint_account_info,
+                    recipient_ata_info,
+                    vault_account_info,
+                    amount_to_transfer,
+                    *decimals,
+                    &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
+                )?;
+
+                fees_paid += token_value(amount_to_transfer, *decimals, *price)?;
+            }
+        }
+    }
 
-//     join_proposal_aggregator_info.realloc(new_aggregator_size, false)?;
+    let vault_atas: Vec<&AccountInfo> = holdings.iter().map(|holding| holding.0).collect();
+    sync_holdings(holdings_info, &vault_atas)?;
 
-//     if new_aggregator_rent < current_aggregator_rent {
-//         **join_proposal_aggregator_info.lamports.borrow_mut() -= current_aggregator_rent - new_aggregator_rent;
-//         **joiner_account_info.lamports.borrow_mut() += current_aggregator_rent - new_aggregator_rent;
-//     }
+    let nav_after = nav.saturating_sub(fees_paid);
+    let shares_after = fund_data.total_deposit as u128;
+    if let Some(nav_per_share_after) = (nav_after * SHARE_PRICE_SCALE).checked_div(shares_after) {
+        if nav_per_share_after > fee_data.high_water_mark as u128 {
+            fee_data.high_water_mark = u64::try_from(nav_per_share_after).map_err(|_| FundError::MathOverflow)?;
+        }
+    }
 
-//     join_proposal_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
+    // Only what was actually paid is recorded, split between the two fees as they were owed
+    let management_paid = (fees_paid * management_fee).checked_div(fee_value).unwrap_or(0);
+    let performance_paid = fees_paid - management_paid;
+    fee_data.management_fees_accrued += u64::try_from(management_paid).map_err(|_| FundError::MathOverflow)?;
+    fee_data.performance_fees_accrued += u64::try_from(performance_paid).map_err(|_| FundError::MathOverflow)?;
+    fee_data.fees_paid += u64::try_from(fees_paid).map_err(|_| FundError::MathOverflow)?;
+    fee_data.last_nav = u64::try_from(nav_after).map_err(|_| FundError::MathOverflow)?;
+    fee_data.last_crystallized = current_time;
+    fee_data.serialize(&mut &mut fee_account_info.data.borrow_mut()[..])?;
 
-//     // Delete Vote account
-//     let lamports = vote_account_info.lamports();
-//     **rent_reserve_info.lamports.borrow_mut() += lamports;
-//     **vote_account_info.lamports.borrow_mut() -= lamports;
+    msg!("[FUND-ACTIVITY] {} {} {} Fees crystallized: {} paid to {}", fund_account_info.key.to_string(), current_time, fund_name, fees_paid, fee_data.recipient.to_string());
 
-//     let mut data = vote_account_info.data.borrow_mut();
-//     for byte in data.iter_mut() {
-//         *byte = 0;
-//     }
+    Ok(())
+}
+
+fn process_init_protocol_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    multisig: [u8; 32],
+    deposit_fee_bps: u16,
+    withdraw_fee_bps: u16,
+    swap_fee_bps: u16,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let admin_info = next_account_info(accounts_iter)?; // protocol admin ........................................
+    let protocol_config_info = next_account_info(accounts_iter)?; // protocol config PDA .........................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ................................
 
-//     // remove fund data from joiner pda
-//     joiner_data.funds.retain(|user_specific| user_specific.fund != *fund_account_info.key);
-//     let current_joiner_size = joiner_pda_info.data_len();
-//     let new_joiner_size = current_joiner_size - 50;
-//     let current_joiner_rent = joiner_pda_info.lamports();
-//     let new_joiner_rent = rent.minimum_balance(new_joiner_size);
+    if !admin_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     joiner_pda_info.realloc(new_joiner_size, false)?;
+    // Only the peerfunds operator can set the protocol up, whoever comes first doesn't become admin
+    if *admin_info.key != PEERFUNDS_OPERATOR {
+        return Err(FundError::InvalidSigner.into());
+    }
 
-//     if new_joiner_rent < current_joiner_rent {
-//         **joiner_pda_info.lamports.borrow_mut() -= current_joiner_rent - new_joiner_rent;
-//         **joiner_account_info.lamports.borrow_mut() += current_joiner_rent - new_joiner_rent;
-//     }
+    let (protocol_config_pda, protocol_config_bump) = Pubkey::find_program_address(&[b"protocol-config"], program_id);
+    if *protocol_config_info.key != protocol_config_pda || !protocol_config_info.data_is_empty() {
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
 
-//     joiner_data.serialize(&mut &mut joiner_pda_info.data.borrow_mut()[..])?;
+    if deposit_fee_bps > MAX_PROTOCOL_DEPOSIT_FEE_BPS || withdraw_fee_bps > MAX_PROTOCOL_WITHDRAW_FEE_BPS || swap_fee_bps > MAX_PROTOCOL_SWAP_FEE_BPS {
+        msg!("Protocol fee above its cap");
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
 
-//     Ok(())
-// }
+    let rent = Rent::get()?;
+    let protocol_config_space = 70;
 
+    invoke_signed(
+        &system_instruction::create_account(
+            admin_info.key,
+            protocol_config_info.key,
+            rent.minimum_balance(protocol_config_space),
+            protocol_config_space as u64,
+            program_id
+        ),
+        &[admin_info.clone(), protocol_config_info.clone(), system_program_info.clone()],
+        &[&[b"protocol-config", &[protocol_config_bump]]]
+    )?;
 
-// fn process_add_member<'a>(
-//     program_id: &Pubkey,
-//     accounts: &'a [AccountInfo<'a>],
-//     fund_name: String,
-//     proposal_index: u8,
-// ) -> ProgramResult {
-//     let current_time = Clock::get()?.unix_timestamp;
+    let protocol_config = ProtocolConfig {
+        admin: *admin_info.key,
+        multisig: Pubkey::new_from_array(multisig),
+        deposit_fee_bps,
+        withdraw_fee_bps,
+        swap_fee_bps,
+    };
 
-//     let accounts_iter = &mut accounts.iter();
-//     let fund_account_info = next_account_info(accounts_iter)?; // Fund Account ..................................
-//     let member_account_info = next_account_info(accounts_iter)?; // User to be added ............................
-//     let system_program_info = next_account_info(accounts_iter)?; // System Program ..............................
-//     let user_account_info = next_account_info(accounts_iter)?; // User Global identity account ..................
-//     let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve .......................
-
-//     // User should be signer
-//     if !member_account_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    protocol_config.serialize(&mut &mut protocol_config_info.data.borrow_mut()[..])?;
 
-//     // Derive PDAs and check if it is same as provided in accounts
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", member_account_info.key.as_ref()], program_id);
-//     let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
-//     if *fund_account_info.key != fund_pda || *user_account_info.key != user_pda || *rent_reserve_info.key != rent_pda {
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    msg!("Protocol config initialized, admin: {}", admin_info.key.to_string());
 
-//     // Check if Fund exist or not!
-//     if fund_account_info.data_is_empty() {
-//         msg!("[FUND-ERROR] {} {} This fund does not exists..", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    Ok(())
+}
 
-//     // Deserialize User Data and check if User is already a member of provided Fund
-//     let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
-//     if user_data.funds.iter().any(|entry| entry.fund == *fund_account_info.key && !entry.is_pending) {
-//         msg!("[FUND-ERROR] {} {} User is already a member.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::AlreadyMember.into());
-//     }
+fn process_update_protocol_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_admin: [u8; 32],
+    multisig: [u8; 32],
+    deposit_fee_bps: u16,
+    withdraw_fee_bps: u16,
+    swap_fee_bps: u16,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let admin_info = next_account_info(accounts_iter)?; // protocol admin ........................................
+    let protocol_config_info = next_account_info(accounts_iter)?; // protocol config PDA .........................
 
-//     // Deserialize the fund data
-//     let mut fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-//     let total_voting_power = fund_data.total_deposit;
+    if !admin_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     if fund_data.expected_members <= fund_data.members.len() as u32 {
-//         msg!("[FUND-ERROR] {} {} Fund already has more member than expected members.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::FundAlreadyFull.into());
-//     }
+    let mut protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+    if protocol_config.admin != *admin_info.key {
+        return Err(FundError::InvalidSigner.into());
+    }
 
-//     let rent = Rent::get()?;
+    if deposit_fee_bps > MAX_PROTOCOL_DEPOSIT_FEE_BPS || withdraw_fee_bps > MAX_PROTOCOL_WITHDRAW_FEE_BPS || swap_fee_bps > MAX_PROTOCOL_SWAP_FEE_BPS {
+        msg!("Protocol fee above its cap");
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
 
-//     if fund_data.is_private == 0 {
-//         // Calculate if more lamports are needed for reallocation of User Global Account
-//         let user_current_size = user_account_info.data_len();
-//         let user_new_size = user_current_size + 50;
-//         let user_new_min_balance = rent.minimum_balance(user_new_size);
-//         let user_current_balance = user_account_info.lamports();
-//         if user_new_min_balance > user_current_balance {
-//             invoke(
-//                 &system_instruction::transfer(
-//                     member_account_info.key,
-//                     user_account_info.key,
-//                     user_new_min_balance - user_current_balance,
-//                 ),
-//                 &[member_account_info.clone(), user_account_info.clone(), system_program_info.clone()],
-//             )?;
-//         }
+    protocol_config.admin = Pubkey::new_from_array(new_admin);
+    protocol_config.multisig = Pubkey::new_from_array(multisig);
+    protocol_config.deposit_fee_bps = deposit_fee_bps;
+    protocol_config.withdraw_fee_bps = withdraw_fee_bps;
+    protocol_config.swap_fee_bps = swap_fee_bps;
 
-//         // Reallocate new bytes ofr storage of new Fund details
-//         user_account_info.realloc(user_new_size, false)?;
-//         user_data.funds.push(UserSpecific {
-//             fund: *fund_account_info.key,
-//             governance_token_balance: 0 as u64,
-//             is_pending: false,
-//             is_eligible: true,
-//             join_time: current_time
-//         });
-//         user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
-//     }
+    protocol_config.serialize(&mut &mut protocol_config_info.data.borrow_mut()[..])?;
+
+    msg!("Protocol fees updated: deposit {} bps, withdraw {} bps, swap {} bps", deposit_fee_bps, withdraw_fee_bps, swap_fee_bps);
+
+    Ok(())
+}
 
+fn process_withdraw_protocol_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let admin_info = next_account_info(accounts_iter)?; // protocol admin ........................................
+    let protocol_config_info = next_account_info(accounts_iter)?; // protocol config PDA .........................
+    let treasury_info = next_account_info(accounts_iter)?; // protocol treasury PDA ...............................
+    let treasury_ata_info = next_account_info(accounts_iter)?; // treasury's token account .......................
+    let multisig_ata_info = next_account_info(accounts_iter)?; // multisig's token account ........................
+    let mint_account_info = next_account_info(accounts_iter)?; // mint of the collected token .....................
+    let token_program_info = next_account_info(accounts_iter)?; // token program ..................................
+
+    if !admin_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     // Check if user already exists
-//     if fund_data.members.contains(member_account_info.key) {
-//         msg!("[FUND-ERROR] {} {} User is already in the fund.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    let protocol_config = load_protocol_config(protocol_config_info, program_id)?;
+    if protocol_config.admin != *admin_info.key {
+        return Err(FundError::InvalidSigner.into());
+    }
 
-//     // Calculate if more lamports are needed for reallocation of Fund Account
-//     let fund_current_size = fund_account_info.data_len();
-//     let fund_new_size = fund_current_size + 32;
-//     let fund_new_min_balance = rent.minimum_balance(fund_new_size);
-//     let fund_current_balance = fund_account_info.lamports();
+    let (treasury_pda, treasury_bump) = Pubkey::find_program_address(&[b"treasury"], program_id);
+    if *treasury_info.key != treasury_pda {
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
 
-//     if fund_new_min_balance > fund_current_balance {
-//         invoke(
-//             &system_instruction::transfer(
-//                 member_account_info.key,
-//                 fund_account_info.key,
-//                 fund_new_min_balance - fund_current_balance
-//             ),
-//             &[member_account_info.clone(), fund_account_info.clone(), system_program_info.clone()]
-//         )?;
-//     }
+    if *token_program_info.key != *mint_account_info.owner {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-//     // Reallocate new bytes for storage of new member pubkey
-//     fund_account_info.realloc(fund_new_size, false)?;
-//     fund_data.members.push(*member_account_info.key);
+    // Collected fees only ever leave towards the multisig
+    if *treasury_ata_info.key != get_associated_token_address_with_program_id(&treasury_pda, mint_account_info.key, token_program_info.key) ||
+       *multisig_ata_info.key != get_associated_token_address_with_program_id(&protocol_config.multisig, mint_account_info.key, token_program_info.key) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
 
-//     // Refund logic
-//     let mut refund_per_member: u64 = (rent.minimum_balance(327 + fund_name.len()) + 5760000 as u64) / (fund_data.expected_members as u64);
-//     if fund_data.is_refunded {
-//         refund_per_member = 1_500_000;
-//     }
+    let decimals = supported_mint_decimals(mint_account_info)?;
+    transfer_tokens(
+        token_program_info,
+        treasury_ata_info,
+        mint_account_info,
+        multisig_ata_info,
+        treasury_info,
+        amount,
+        decimals,
+        &[&[b"treasury", &[treasury_bump]]]
+    )?;
 
-//     invoke(
-//         &system_instruction::transfer(
-//             member_account_info.key,
-//             rent_reserve_info.key,
-//             refund_per_member
-//         ),
-//         &[member_account_info.clone(), rent_reserve_info.clone(), system_program_info.clone()]
-//     )?;
+    msg!("Protocol fees withdrawn: {} of {} to {}", amount, mint_account_info.key.to_string(), protocol_config.multisig.to_string());
 
-//     // If fund is private
-//     if fund_data.is_private == 1 as u8 {
-//         let join_proposal_aggregator_info = next_account_info(accounts_iter)?;
-//         let vote_account_info = next_account_info(accounts_iter)?;
-//         let index = 0 as u8;
-//         let (join_proposal_pda, _join_proposal_bump) = Pubkey::find_program_address(&[b"join-proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
-//         let (vote_pda, _vote_bump) = Pubkey::find_program_address(&[b"join-vote", &[proposal_index], fund_pda.as_ref()], program_id);
-//         if *join_proposal_aggregator_info.key != join_proposal_pda {
-//             msg!("[FUND-ERROR] {} {} Wrong join proposal information.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//             return Err(FundError::InvalidProposalAccount.into());
-//         }
-//         if *vote_account_info.key != vote_pda {
-//             msg!("[FUND-ERROR] {} {} Wrong vote account information.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//             return Err(FundError::InvalidVoteAccount.into());
-//         }
+    Ok(())
+}
 
-//         let mut join_proposal_data = JoinProposalAggregator::try_from_slice(&join_proposal_aggregator_info.data.borrow())?;
-//         let (_matched_index, proposal) = join_proposal_data
-//             .join_proposals
-//             .iter()
-//             .enumerate()
-//             .find(|(_, p)| p.proposal_index == proposal_index)
-//             .ok_or(FundError::InvalidAccountData)?;
-
-//         if proposal.joiner != *member_account_info.key {
-//             msg!("[FUND-ERROR] {} {} Wrong proposer/joiner wallet.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//             return Err(FundError::InvalidAccountData.into());
-//         }
+fn process_init_remove_member_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    deadline: i64,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-//         if 2*(proposal.votes_yes) < total_voting_power {
-//             msg!("[FUND-ERROR] {} {} Not enough votes to join the fund.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//             return Err(FundError::NotEnoughVotes.into());
-//         }
+    let accounts_iter = &mut accounts.iter();
+    let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet ............................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let target_account_info = next_account_info(accounts_iter)?; // wallet of the member to remove ...............
+    let remove_proposal_info = next_account_info(accounts_iter)?; // remove member proposal ......................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let proposer_membership_info = next_account_info(accounts_iter)?; // proposer's membership PDA ...............
+    let target_membership_info = next_account_info(accounts_iter)?; // membership PDA of the member to remove ....
 
-//         join_proposal_data.join_proposals.retain(|proposal| proposal.proposal_index != proposal_index);
+    if !proposer_account_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//         let rent = Rent::get()?;
-//         let current_proposal_aggregator_size = join_proposal_aggregator_info.data_len();
-//         let new_proposal_aggregator_size = current_proposal_aggregator_size - 57;
-//         let current_aggregator_rent = join_proposal_aggregator_info.lamports();
-//         let new_aggregator_rent = rent.minimum_balance(new_proposal_aggregator_size);
-
-//         join_proposal_aggregator_info.realloc(new_proposal_aggregator_size, false)?;
-//         if new_aggregator_rent < current_aggregator_rent {
-//             **join_proposal_aggregator_info.lamports.borrow_mut() -= current_aggregator_rent - new_aggregator_rent;
-//             **member_account_info.lamports.borrow_mut() += current_aggregator_rent - new_aggregator_rent;
-//         }
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (remove_proposal_pda, remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_account_info.key.as_ref(), target_account_info.key.as_ref()], program_id);
 
-//         join_proposal_data.serialize(&mut &mut join_proposal_aggregator_info.data.borrow_mut()[..])?;
+    if *fund_account_info.key != fund_pda || *remove_proposal_info.key != remove_proposal_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//         let lamports = vote_account_info.lamports();
-//         **rent_reserve_info.lamports.borrow_mut() += lamports;
-//         **vote_account_info.lamports.borrow_mut() -= lamports;
+    if !remove_proposal_info.data_is_empty() {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
 
-//         let mut data = vote_account_info.data.borrow_mut();
-//         for byte in data.iter_mut() {
-//             *byte = 0;
-//         }
+    if deadline <= current_time {
+        return Err(FundError::DeadlineReached.into());
+    }
 
-//         if let Some(user_specific) = user_data
-//             .funds
-//             .iter_mut()
-//             .find(|entry| entry.fund == *fund_account_info.key) {
-//                 user_specific.is_pending = false;
-//                 user_specific.is_eligible = true;
-//             } else {
-//                 msg!("User is not a member in this fund");
-//                 return Err(FundError::InvalidAccountData.into());
-//             }
-
-//         user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
-//     }
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut proposer_member = load_member(program_id, proposer_membership_info, fund_account_info.key, proposer_account_info.key)?;
 
-//     // If expected number of members are achieved, refund back to creator
-//     if fund_data.expected_members == fund_data.members.len() as u32 && !fund_data.is_refunded {
-//         let fund_creator_info = next_account_info(accounts_iter)?;
+    if !has_role(&proposer_member, ROLE_PROPOSER) {
+        return Err(FundError::MissingPermission.into());
+    }
 
-//         if *fund_creator_info.key != fund_data.members[0] {
-//             msg!("[FUND-ERROR] {} {} Wrong fund creator.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-//             return Err(FundError::InvalidFundCreator.into());
-//         }
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
 
-//         let rent_paid_by_creator = rent.minimum_balance(327 + fund_name.len()) + 5760000 as u64;
+    if *target_account_info.key == *proposer_account_info.key || load_member(program_id, target_membership_info, fund_account_info.key, target_account_info.key).is_err() {
+        return Err(FundError::InvalidMemberInfo.into());
+    }
 
-//         let refund_to_creator: u64 = ((fund_data.expected_members - 1) as u64 * rent_paid_by_creator) / (fund_data.expected_members as u64);
+    let rent = Rent::get()?;
+    let proposal_space = 165;
+    let proposal_rent = rent.minimum_balance(proposal_space);
 
-//         **rent_reserve_info.lamports.borrow_mut() -= refund_to_creator;
-//         **fund_creator_info.lamports.borrow_mut() += refund_to_creator;
-//         fund_data.is_refunded = true;
-//     }
+    invoke_signed(
+        &system_instruction::create_account(
+            proposer_account_info.key,
+            remove_proposal_info.key,
+            proposal_rent,
+            proposal_space as u64,
+            program_id
+        ),
+        &[proposer_account_info.clone(), remove_proposal_info.clone(), system_program_info.clone()],
+        &[&[b"remove-member", fund_account_info.key.as_ref(), target_account_info.key.as_ref(), &[remove_proposal_bump]]]
+    )?;
 
-//     fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    // Proposer's vote is counted in favour right away
+    lock_voting_shares(&fund_data, proposer_membership_info, &mut proposer_member, deadline)?;
+    let proposal_data = RemoveMemberProposal {
+        fund: *fund_account_info.key,
+        proposer: *proposer_account_info.key,
+        target: *target_account_info.key,
+        votes_yes: vote_weight(&fund_data, &proposer_member),
+        votes_no: 0,
+        creation_time: current_time,
+        deadline,
+        voters: vec![(*proposer_account_info.key, 1)]
+    };
 
-//     msg!("[FUND-ACTIVITY] {} {} {} Member joined: {}", fund_account_info.key.to_string(), current_time, fund_name, member_account_info.key.to_string());
+    proposal_data.serialize(&mut &mut remove_proposal_info.data.borrow_mut()[..])?;
 
-//     Ok(())
+    msg!("[FUND-ACTIVITY] {} {} {} proposed to remove {}", fund_account_info.key.to_string(), current_time, proposer_account_info.key.to_string(), target_account_info.key.to_string());
 
-// }
+    Ok(())
+}
 
-fn process_init_deposit_token(
+fn process_vote_on_remove_member(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    is_unwrapped_sol: u8,
-    amount: u64,
-    mint_amount: u64,
     fund_name: String,
-    fund_type: u8,
+    vote: u8,
 ) -> ProgramResult {
     let current_time = Clock::get()?.unix_timestamp;
 
     let accounts_iter = &mut accounts.iter();
-    let member_account_info = next_account_info(accounts_iter)?; // Depositor wallet .................................
-    let member_ata_info = next_account_info(accounts_iter)?; // Depositor's ATA for the depositing mint ..............
-    let vault_account_info = next_account_info(accounts_iter)?; // Vault PDA .........................................
-    let vault_ata_info = next_account_info(accounts_iter)?; // Vault PDA's ATA for the depositing mint ...............
-    let mint_account_info = next_account_info(accounts_iter)?; // Mint account of token to be deposited ..............
-    let token_program_info = next_account_info(accounts_iter)?; // Token Progarm .....................................
-    let ata_program_info = next_account_info(accounts_iter)?; // Associated Token Program ............................
-    let fund_account_info = next_account_info(accounts_iter)?; // Fund PDA ...........................................
-    let user_account_info = next_account_info(accounts_iter)?; // user global account ................................
-    let system_program_info = next_account_info(accounts_iter)?; // System program ...................................
-    let rent_sysvar_info = next_account_info(accounts_iter)?; // Rent Sysvar Account .................................
-    // let governance_token_account_info = next_account_info(accounts_iter)?; // Governance Token Account of depositor ..
-    // let governance_mint_info = next_account_info(accounts_iter)?; // Governance Mint Account .........................
-    // let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 ...........................
+    let voter_account_info = next_account_info(accounts_iter)?; // voter wallet ..................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let remove_proposal_info = next_account_info(accounts_iter)?; // remove member proposal ......................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let voter_membership_info = next_account_info(accounts_iter)?; // voter's membership PDA .....................
 
-    // Depositor should be signer
-    if !member_account_info.is_signer {
-        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), member_account_info.key.to_string());
+    if !voter_account_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    // Derive the PDAs and check for equality with provided ones
-    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_account_info.key.as_ref()], program_id);
-    let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", member_account_info.key.as_ref()], program_id);
-    let mut seed = String::from("light-fund");
-    if fund_type == 1 {
-        seed = String::from("standard-fund");
-    } else if fund_type == 2 {
-        seed = String::from("dao-fund");
-    }
-    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[seed.as_bytes(), fund_name.as_bytes()], program_id);
-    if *vault_account_info.key != vault_pda || *fund_account_info.key != fund_pda || *user_account_info.key != user_pda {
-        msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), member_account_info.key.to_string());
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    if *fund_account_info.key != fund_pda {
         return Err(FundError::InvalidAccountData.into());
     }
 
-    // let expected_ata = spl_associated_token_account::get_associated_token_address_with_program_id(
-    //     member_account_info.key,
-    //     governance_mint_info.key,
-    //     token_program_2022_info.key
-    // );
-    // if *governance_token_account_info.key != expected_ata {
-    //     msg!("[FUND-ERROR] {} {} Wrong governance token account information.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-    //     return Err(FundError::InvalidTokenAccount.into());
-    // }
+    let mut proposal_data = RemoveMemberProposal::try_from_slice(&remove_proposal_info.data.borrow())?;
+    let (remove_proposal_pda, _remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_account_info.key.as_ref(), proposal_data.target.as_ref()], program_id);
+    if *remove_proposal_info.key != remove_proposal_pda {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
 
-    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-    let is_depositer_member = fund_data
-        .members
-        .iter()
-        .any(|member| member.0 == *member_account_info.key);
+    if proposal_data.deadline < current_time {
+        return Err(FundError::VotingCeased.into());
+    }
 
-    if !is_depositer_member {
-        msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot deposit in it.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+    // The member up for removal doesn't get a say
+    if *voter_account_info.key == proposal_data.target {
         return Err(FundError::NotAFundMember.into());
     }
 
-    // If user doesn't exist in the fund, it can't deposit
-    let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
-    if !user_data.funds.iter().any(|entry| entry.fund == *fund_account_info.key) {
-        msg!("[FUND-ERROR] {} {} User is not a member of the fund.", fund_account_info.key.to_string(), member_account_info.key.to_string());
-        return Err(FundError::InvalidAccountData.into());
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut voter_member = load_member(program_id, voter_membership_info, fund_account_info.key, voter_account_info.key)?;
+
+    if !has_role(&voter_member, ROLE_VOTER) {
+        return Err(FundError::MissingPermission.into());
     }
 
-    // If depositor's governance token account doesn't exist, create one
-    // if governance_token_account_info.data_is_empty() {
-    //     invoke(
-    //         &spl_associated_token_account::instruction::create_associated_token_account(
-    //             member_account_info.key,
-    //             member_account_info.key,
-    //             governance_mint_info.key,
-    //             token_program_2022_info.key,
-    //         ),
-    //         &[
-    //             member_account_info.clone(),
-    //             governance_token_account_info.clone(),
-    //             token_program_2022_info.clone(),
-    //             governance_mint_info.clone(),
-    //             rent_sysvar_info.clone(),
-    //         ]
-    //     )?;
-    // }
+    if proposal_data.voters.iter().any(|voter| voter.0 == *voter_account_info.key) {
+        return Err(FundError::AlreadyVoted.into());
+    }
 
-    let rent = &Rent::from_account_info(rent_sysvar_info)?;
-    let rent_req = rent.minimum_balance(TokenAccount::LEN);
+    lock_voting_shares(&fund_data, voter_membership_info, &mut voter_member, proposal_data.deadline)?;
+    proposal_data.voters.push((*voter_account_info.key, vote));
+    if vote == 0 {
+        proposal_data.votes_no += vote_weight(&fund_data, &voter_member);
+    } else {
+        proposal_data.votes_yes += vote_weight(&fund_data, &voter_member);
+    }
 
-    // If vault's token account account for the depositing mint doesn't exist, create it
-    if vault_ata_info.data_is_empty() {
+    let current_proposal_size = remove_proposal_info.data_len();
+    let new_proposal_size = current_proposal_size + VOTE_SPACE;
+    let current_proposal_rent = remove_proposal_info.lamports();
+    let new_proposal_rent = Rent::get()?.minimum_balance(new_proposal_size);
 
-        invoke_signed(
-            &create_associated_token_account(
-                member_account_info.key,
-                vault_account_info.key,
-                mint_account_info.key,
-                token_program_info.key
+    if new_proposal_rent > current_proposal_rent {
+        invoke(
+            &system_instruction::transfer(
+                voter_account_info.key,
+                remove_proposal_info.key,
+                new_proposal_rent - current_proposal_rent
             ),
-            &[
-                member_account_info.clone(),
-                vault_ata_info.clone(),
-                vault_account_info.clone(),
-                mint_account_info.clone(),
-                system_program_info.clone(),
-                token_program_info.clone(),
-                ata_program_info.clone(),
-                rent_sysvar_info.clone(),
-            ],
-            &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]]
+            &[voter_account_info.clone(), remove_proposal_info.clone(), system_program_info.clone()]
         )?;
     }
 
-    // let mint: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    remove_proposal_info.realloc(new_proposal_size, false)?;
+    proposal_data.serialize(&mut &mut remove_proposal_info.data.borrow_mut()[..])?;
 
-    if is_unwrapped_sol == 1 {
-        invoke(
-            &system_instruction::create_account(
-                member_account_info.key,
-                member_ata_info.key,
-                rent_req+amount,
-                TokenAccount::LEN as u64,
-                token_program_info.key,
-            ),
-            &[
-                member_account_info.clone(),
-                member_ata_info.clone(),
-                token_program_info.clone(),
-                system_program_info.clone(),
-                rent_sysvar_info.clone(),
-            ]
-        )?;
+    msg!("[FUND-ACTIVITY] {} {} {} Vote: {} on removing {}", fund_account_info.key.to_string(), current_time, voter_account_info.key.to_string(), vote, proposal_data.target.to_string());
 
-        invoke(
-            &spl_token::instruction::initialize_account(
-                token_program_info.key,
-                member_ata_info.key,
-                mint_account_info.key,
-                member_account_info.key,
-            )?,
-            &[
-                token_program_info.clone(),
-                member_ata_info.clone(),
-                mint_account_info.clone(),
-                member_account_info.clone(),
-                rent_sysvar_info.clone(),
-            ]
-        )?;
+    Ok(())
+}
 
-        invoke(
-            &spl_token::instruction::transfer(
-                token_program_info.key,
-                member_ata_info.key,
-                vault_ata_info.key,
-                member_account_info.key,
-                &[],
-                amount
-            )?,
-            &[
-                token_program_info.clone(),
-                member_ata_info.clone(),
-                vault_ata_info.clone(),
-                member_account_info.clone(),
-            ]
-        )?;
+fn process_execute_remove_member(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    num_of_tokens: u8,
+    num_of_stakes: u8,
+    num_of_positions: u8,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-        invoke(
-            &spl_token::instruction::close_account(
-                token_program_info.key,
-                member_ata_info.key,
-                member_account_info.key,
-                member_account_info.key,
-                &[]
-            )?,
-            &[
-                token_program_info.clone(),
-                member_account_info.clone(),
-                member_ata_info.clone()
-               ]
-        )?;
-    } else {
-        // Now transfer the required number of tokens from depositor's token account to vault's token account
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                token_program_info.key,
-                member_ata_info.key,
-                vault_ata_info.key,
-                member_account_info.key,
-                &[],
-                amount,
-            )?,
-            &[
-                member_ata_info.clone(),
-                vault_ata_info.clone(),
-                member_account_info.clone(),
-                token_program_info.clone(),
-            ],
-            &[]
-        )?;
+    let accounts_iter = &mut accounts.iter();
+    let executor_account_info = next_account_info(accounts_iter)?; // executor wallet (pays for new accounts) ....
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let remove_proposal_info = next_account_info(accounts_iter)?; // remove member proposal ......................
+    let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet ............................
+    let target_account_info = next_account_info(accounts_iter)?; // removed member's wallet ......................
+    let target_user_info = next_account_info(accounts_iter)?; // removed member's user account ..................
+    let vault_account_info = next_account_info(accounts_iter)?; // vault account .................................
+    let token_program_info = next_account_info(accounts_iter)?; // token program .................................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let ata_program_info = next_account_info(accounts_iter)?; // associated token program ........................
+    let rent_sysvar_info = next_account_info(accounts_iter)?; // rent sysvar .....................................
+    let target_membership_info = next_account_info(accounts_iter)?; // removed member's membership PDA ...........
+
+    if !executor_account_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (remove_proposal_pda, _remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_account_info.key.as_ref(), target_account_info.key.as_ref()], program_id);
+    let (target_user_pda, _target_user_bump) = Pubkey::find_program_address(&[b"user", target_account_info.key.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *remove_proposal_info.key != remove_proposal_pda || *target_user_info.key != target_user_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let proposal_data = RemoveMemberProposal::try_from_slice(&remove_proposal_info.data.borrow())?;
+    if proposal_data.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+
+    // Same bar as investment proposals: voting closed, quorum and approval reached, and the majority agreed
+    if current_time <= proposal_data.deadline {
+        msg!("The proposal is still under voting. {}, {}", current_time, proposal_data.deadline);
+        return Err(FundError::InvalidAccountData.into());
     }
 
+    if !votes_passed(proposal_data.votes_yes, proposal_data.votes_no, voting_rules(&fund_data)) {
+        msg!("Quorum or approval threshold not reached");
+        return Err(FundError::NotEnoughVotes.into());
+    }
 
-    // Now mint equivalent quantity of governance tokens to the depositor's governance token account
-    // invoke_signed(
-    //     &spl_token_2022::instruction::mint_to(
-    //         token_program_2022_info.key,
-    //         governance_mint_info.key,
-    //         governance_token_account_info.key,
-    //         fund_account_info.key,
-    //         &[],
-    //         mint_amount,
-    //     )?,
-    //     &[
-    //         governance_mint_info.clone(),
-    //         governance_token_account_info.clone(),
-    //         fund_account_info.clone(),
-    //         token_program_2022_info.clone(),
-    //     ],
-    //     &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]],
-    // )?;
+    if proposal_data.votes_yes <= proposal_data.votes_no {
+        msg!("Not enough votes favouring the removal");
+        return Err(FundError::NotEnoughVotes.into());
+    }
 
-    // In vault account, set the last deposit time
-    let mut vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
-    vault_data.last_deposit_time = current_time;
-    vault_data.serialize(&mut &mut vault_account_info.data.borrow_mut()[..])?;
+    let target_membership = load_member(program_id, target_membership_info, fund_account_info.key, target_account_info.key)?;
+
+    let payout = next_member_payout(
+        program_id,
+        accounts_iter,
+        executor_account_info,
+        target_account_info,
+        fund_account_info,
+        vault_account_info,
+        [token_program_info, system_program_info, ata_program_info, rent_sysvar_info],
+        target_membership.stake_payouts,
+        num_of_tokens,
+        num_of_stakes,
+        num_of_positions,
+        None
+    )?;
+    let governance = next_governance_accounts(accounts_iter, &fund_data, target_account_info.key)?;
 
-    // In fund account increase the deposited amount (unit lamports)
-    fund_data.total_deposit += mint_amount;
-    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    let mut target_data = UserAccount::try_from_slice(&target_user_info.data.borrow())?;
+    let member_deposit = target_membership.deposit;
 
-    if let Some(entry) = user_data.funds.iter_mut().find(|entry| entry.fund == *fund_account_info.key) {
-        entry.governance_token_balance += mint_amount;
-    } else {
-        msg!("[FUND-ERROR] {} {} Fund entry not found for user.", fund_account_info.key.to_string(), member_account_info.key.to_string());
+    let total_deposit = fund_data.total_deposit;
+    if member_deposit > total_deposit {
         return Err(FundError::InvalidAccountData.into());
     }
 
-    user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
+    if total_deposit != 0 {
+        let withdraw_percent = (((member_deposit as u128) * 100_000_000)/(total_deposit as u128)) as u64;
+        msg!("Withdraw percent overall: {}", withdraw_percent);
 
-    msg!("[FUND-ACTIVITY] {} {} {} Token deposit: {} of {} by {}", fund_account_info.key.to_string(), current_time, fund_name, amount, mint_account_info.key.to_string(), member_account_info.key.to_string());
+        if withdraw_percent != 0 {
+            pay_out_member_share(program_id, &payout, withdraw_percent)?;
+        }
+    }
+
+    if fund_data.creator_exists && fund_data.creator == *target_account_info.key {
+        fund_data.creator_exists = false;
+    }
+
+    if let Some((governance_mint_info, governance_token_account_info, token_program_2022_info)) = governance {
+        burn_governance_tokens(
+            token_program_2022_info,
+            governance_mint_info,
+            governance_token_account_info,
+            fund_account_info,
+            member_deposit,
+            &[b"light-fund", fund_name.as_bytes(), &[fund_bump]]
+        )?;
+    }
+
+    fund_data.total_deposit -= member_deposit;
+    target_data.funds_joined -= 1;
+
+    // Membership goes back to the removed member, who paid for it when joining
+    close_membership(target_membership_info, &target_membership, target_account_info, &mut fund_data);
+
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    target_data.serialize(&mut &mut target_user_info.data.borrow_mut()[..])?;
+
+    // Proposal is done with, the voters' wallets follow in order to get their rent back
+    close_voted_proposal(accounts_iter, remove_proposal_info, proposer_account_info, proposal_data.voters.get(1..).unwrap_or_default())?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} was removed from the fund", fund_account_info.key.to_string(), current_time, target_account_info.key.to_string());
 
     Ok(())
 }
 
-
-fn process_init_investment_proposal(
+/// Closes a remove member proposal that can no longer be executed: voting ended without the
+/// removal passing, the member already left, or the proposal went unexecuted past its expiry.
+/// The voters' wallets follow the fixed accounts, in voting order after the proposer.
+fn process_close_remove_member_proposal(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     fund_name: String,
-    cid: String,
-    deadline: i64,
-    merkel_bytes: MerkleRoot,
 ) -> ProgramResult {
-    let creation_time = Clock::get()?.unix_timestamp;
+    let current_time = Clock::get()?.unix_timestamp;
 
     let accounts_iter = &mut accounts.iter();
-    let proposer_account_info = next_account_info(accounts_iter)?;
-    let fund_account_info = next_account_info(accounts_iter)?;
-    let proposal_aggregator_info = next_account_info(accounts_iter)?;
-    let system_program_info = next_account_info(accounts_iter)?;
-    let new_proposal_aggregator_info = next_account_info(accounts_iter)?;
-
-    msg!("{} {} {}", cid, deadline, fund_name);
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can close ....................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let remove_proposal_info = next_account_info(accounts_iter)?; // remove member proposal ......................
+    let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet, gets the proposal rent ....
+    let target_membership_info = next_account_info(accounts_iter)?; // membership PDA of the member to remove ....
 
-    if !proposer_account_info.is_signer {
-        msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
+    if !cranker_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    msg!("Fund PDA derived");
-    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if *fund_account_info.key != fund_pda || remove_proposal_info.data_is_empty() {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-    let is_proposer_member = fund_data
-        .members
-        .iter()
-        .any(|member| member.0 == *proposer_account_info.key);
+    let proposal_data = RemoveMemberProposal::try_from_slice(&remove_proposal_info.data.borrow())?;
+    let (remove_proposal_pda, _remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_account_info.key.as_ref(), proposal_data.target.as_ref()], program_id);
+    if *remove_proposal_info.key != remove_proposal_pda {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
 
-    if !is_proposer_member {
-        msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot create a proposal.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-        return Err(FundError::NotAFundMember.into());
+    if proposal_data.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
     }
 
-    if *fund_account_info.key != fund_pda {
-        msg!("[FUND-ERROR] {} {} Wrong fund account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-        return Err(FundError::InvalidAccountData.into());
+    if current_time <= proposal_data.deadline {
+        msg!("The proposal is still under voting");
+        return Err(FundError::InvalidProposalState.into());
     }
 
-    let current_index = fund_data.current_proposal_index;
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let passed = votes_passed(proposal_data.votes_yes, proposal_data.votes_no, voting_rules(&fund_data))
+        && proposal_data.votes_yes > proposal_data.votes_no;
+    let target_gone = load_membership(program_id, target_membership_info, fund_account_info.key, &proposal_data.target)?.is_none();
 
-    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(
-        &[
-            b"proposal-aggregator",
-            &[current_index],
-            fund_pda.as_ref()
-        ],
-        program_id
-    );
+    if passed && !target_gone && current_time <= proposal_data.deadline + PROPOSAL_EXPIRY {
+        msg!("The removal passed and can still be executed");
+        return Err(FundError::InvalidProposalState.into());
+    }
 
-    msg!("Current Derived");
+    close_voted_proposal(accounts_iter, remove_proposal_info, proposer_account_info, proposal_data.voters.get(1..).unwrap_or_default())?;
 
-    let (new_proposal_aggregator_pda, new_proposal_aggregator_bump) = Pubkey::find_program_address(
-        &[
-            b"proposal-aggregator",
-            &[current_index + 1],
-            fund_pda.as_ref()
-        ],
-        program_id
-    );
+    msg!("[FUND-ACTIVITY] {} {} {} Proposal to remove {} closed", fund_account_info.key.to_string(), current_time, fund_name, proposal_data.target.to_string());
 
-    msg!("New Derived");
+    Ok(())
+}
 
-    if *proposal_aggregator_info.key != proposal_aggregator_pda || *new_proposal_aggregator_info.key != new_proposal_aggregator_pda {
-        msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-        return Err(FundError::InvalidProposalAccount.into());
+#[allow(clippy::too_many_arguments)]
+fn process_set_member_roles(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    member: [u8; 32],
+    roles: u8,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let signer_info = next_account_info(accounts_iter)?; // any signer, the proposal carries the authority .......
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let membership_info = next_account_info(accounts_iter)?; // membership PDA of the member ....................
+
+    if !signer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
     }
 
-    // Rent Calculation
-    let rent = Rent::get()?;
-    let current_proposal_space = proposal_aggregator_info.data_len();
-    let extra_proposal_space = 169 as usize;
+    if roles & !(ROLE_ADMIN | ROLE_PROPOSER | ROLE_VOTER | ROLE_INVITER | ROLE_OBSERVER) != 0 {
+        return Err(FundError::InvalidMemberInfo.into());
+    }
 
-    let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
 
-    let mut voters_bitmap: Vec<(u32, u8)> = vec![];
-    let proposer_info = fund_data
-        .members
-        .iter()
-        .find(|member| member.0 == *proposer_account_info.key)
-        .ok_or(FundError::InvalidAccountData)?;
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-    let proposer_vec_index = proposer_info.1;
+    let member = Pubkey::new_from_array(member);
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = member_roles_leaf(&member, roles);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    let mut membership = load_member(program_id, membership_info, fund_account_info.key, &member)?;
+
+    // Keep the count quorums are measured against in step
+    let was_voter = has_role(&membership, ROLE_VOTER);
+    membership.roles = roles;
+    let is_voter = has_role(&membership, ROLE_VOTER);
+    if was_voter && !is_voter {
+        fund_data.voter_count -= 1;
+    } else if !was_voter && is_voter {
+        fund_data.voter_count += 1;
+    }
 
-    voters_bitmap.push((proposer_vec_index, 1));
+    membership.serialize(&mut &mut membership_info.data.borrow_mut()[..])?;
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
-    let bytes = cid.as_bytes();
-    let mut array = [0u8; 59];
-    let len = bytes.len().min(59);
-    array[..len].copy_from_slice(&bytes[..len]);
+    msg!("[FUND-ACTIVITY] {} {} Roles of {} set to {}", fund_account_info.key.to_string(), current_time, member.to_string(), roles);
 
-    // check if new proposal aggregator is required
-    if (current_proposal_space + extra_proposal_space) > 10240 as usize {
-        msg!("New is creating");
-        // Create Proposal Account
-        invoke_signed(
-            &system_instruction::create_account(
-                proposer_account_info.key,
-                new_proposal_aggregator_info.key,
-                rent.minimum_balance(37 + extra_proposal_space),
-                (37 + extra_proposal_space) as u64,
-                program_id
-            ),
-            &[
-                new_proposal_aggregator_info.clone(),
-                proposer_account_info.clone(),
-                system_program_info.clone(),
-            ],
-            &[&[
-                b"proposal-aggregator",
-                &[current_index + 1],
-                fund_account_info.key.as_ref(),
-                &[new_proposal_aggregator_bump]
-            ]]
-        )?;
+    Ok(())
+}
 
-        let proposals_vec: Vec<Proposal> = vec![ Proposal {
-            proposer: *proposer_account_info.key,
-            cid: array,
-            merkel_root: merkel_bytes.0,
-            votes_yes: 1 as u64,
-            votes_no: 0 as u64,
-            creation_time,
-            deadline,
-            executed: 0 as u8,
-            vec_index: 0 as u16,
-            swaps_status: 0 as u16,
-            voters_bitmap,
-        }];
+fn process_request_withdrawal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    task: u8,
+    stake_percent: u64,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-        let new_proposal_data = ProposalAggregatorAccount {
-            index: current_index + 1,
-            proposals: proposals_vec
-        };
+    let accounts_iter = &mut accounts.iter();
+    let member_wallet_info = next_account_info(accounts_iter)?; // member wallet ................................
+    let membership_info = next_account_info(accounts_iter)?; // member's membership PDA ..........................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let request_account_info = next_account_info(accounts_iter)?; // withdrawal request ..........................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
 
-        new_proposal_data.serialize(&mut &mut new_proposal_aggregator_info.data.borrow_mut()[..])?;
+    if !member_wallet_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-        fund_data.current_proposal_index += 1;
-        fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    if stake_percent == 0 || stake_percent > 100_000_000 || task > 1 || (task == 1 && stake_percent != 100_000_000) {
+        return Err(FundError::InvalidStakePercent.into());
+    }
 
-        msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, 0) by {}", fund_account_info.key.to_string(), creation_time, fund_name, (current_index + 1), proposer_account_info.key.to_string());
-    } else {
-        msg!("Current is enough");
-        let new_aggregator_size = extra_proposal_space + current_proposal_space as usize;
-        let current_rent_exempt = proposal_aggregator_info.lamports();
-        let new_rent_exempt = rent.minimum_balance(new_aggregator_size);
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (request_pda, request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_account_info.key.as_ref(), member_wallet_info.key.as_ref()], program_id);
 
-        if new_rent_exempt > current_rent_exempt {
-            invoke(
-                &system_instruction::transfer(
-                    proposer_account_info.key,
-                    proposal_aggregator_info.key,
-                    new_rent_exempt-current_rent_exempt
-                ),
-                &[proposal_aggregator_info.clone(), proposer_account_info.clone(), system_program_info.clone()]
-            )?;
-        }
+    if *fund_account_info.key != fund_pda || *request_account_info.key != request_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-        let num_of_proposals = proposal_aggregator_data.proposals.len();
-        let mut vec_index = 0 as u16;
-        if num_of_proposals != 0 {
-            vec_index = proposal_aggregator_data.proposals[num_of_proposals - 1].vec_index + 1;
-        }
-        msg!("Vec Index: {}", vec_index);
+    if !request_account_info.data_is_empty() {
+        return Err(FundError::InvalidWithdrawalRequest.into());
+    }
 
-        proposal_aggregator_info.realloc(new_aggregator_size, false)?;
-        
-        proposal_aggregator_data.proposals.push( Proposal {
-            proposer: *proposer_account_info.key,
-            cid: array,
-            merkel_root: merkel_bytes.0,
-            votes_yes: 1 as u64,
-            votes_no: 0 as u64,
-            creation_time,
-            deadline,
-            executed: 0 as u8,
-            vec_index,
-            swaps_status: 0 as u16,
-            voters_bitmap,
-        });
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if fund_data.notice_period == 0 {
+        msg!("Fund has no notice period, withdraw directly");
+        return Err(FundError::InvalidInstruction.into());
+    }
 
-        proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+    let membership = load_member(program_id, membership_info, fund_account_info.key, member_wallet_info.key)?;
 
-        msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, current_index, vec_index, proposer_account_info.key.to_string());
+    if current_time < membership.last_deposit_time + fund_data.lockup_period {
+        return Err(FundError::DepositLocked.into());
     }
 
+    let claimable_at = withdrawal_claimable_at(fund_data.created_at, fund_data.notice_period, current_time);
+
+    let rent = Rent::get()?;
+    let request_space = 89;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            member_wallet_info.key,
+            request_account_info.key,
+            rent.minimum_balance(request_space),
+            request_space as u64,
+            program_id
+        ),
+        &[member_wallet_info.clone(), request_account_info.clone(), system_program_info.clone()],
+        &[&[b"withdrawal-request", fund_account_info.key.as_ref(), member_wallet_info.key.as_ref(), &[request_bump]]]
+    )?;
+
+    let request_data = WithdrawalRequest {
+        fund: *fund_account_info.key,
+        member: *member_wallet_info.key,
+        task,
+        stake_percent,
+        requested_at: current_time,
+        claimable_at,
+    };
+
+    request_data.serialize(&mut &mut request_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} {} requested a withdrawal claimable at {}", fund_account_info.key.to_string(), current_time, member_wallet_info.key.to_string(), claimable_at);
+
     Ok(())
 }
 
-
-fn process_vote_on_proposal(
+fn process_cancel_withdrawal_request(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    vote: u8,
-    proposal_index: u8,
-    vec_index: u16,
     fund_name: String,
 ) -> ProgramResult {
     let current_time = Clock::get()?.unix_timestamp;
 
     let accounts_iter = &mut accounts.iter();
-    let voter_account_info = next_account_info(accounts_iter)?; // Voter's Wallet ................................
-    let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal account ........................
-    let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
-    let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
-    let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
-
-    msg!("Vec Index: {}", vec_index);
+    let member_wallet_info = next_account_info(accounts_iter)?; // member wallet ................................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let request_account_info = next_account_info(accounts_iter)?; // withdrawal request ..........................
 
-    // Voter needs to be signer
-    if !voter_account_info.is_signer {
+    if !member_wallet_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    // Pdas derivation
     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
-
-    // Pdas verification
-    if *fund_account_info.key != fund_pda ||
-       *proposal_aggregator_info.key != proposal_aggregator_pda {
-        return Err(FundError::InvalidAccountData.into());
-    }
-
-    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-
-    let is_voter_member = fund_data
-        .members
-        .iter()
-        .any(|member| member.0 == *voter_account_info.key);
+    let (request_pda, _request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_account_info.key.as_ref(), member_wallet_info.key.as_ref()], program_id);
 
-    if !is_voter_member {
-        return Err(FundError::NotAFundMember.into());
+    if *fund_account_info.key != fund_pda || *request_account_info.key != request_pda || request_account_info.data_is_empty() {
+        return Err(FundError::InvalidWithdrawalRequest.into());
     }
 
-    let voter_info = fund_data
-        .members
-        .iter()
-        .find(|member| member.0 == *voter_account_info.key)
-        .ok_or(FundError::InvalidAccountData)?;
+    let request_lamports = request_account_info.lamports();
+    **request_account_info.lamports.borrow_mut() = 0;
+    **member_wallet_info.lamports.borrow_mut() += request_lamports;
 
-    let voter_vec_index = voter_info.1;    
+    msg!("[FUND-ACTIVITY] {} {} {} cancelled their withdrawal request", fund_account_info.key.to_string(), current_time, member_wallet_info.key.to_string());
 
-    let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
-    let (matched_index, proposal) = proposal_aggregator_data
-        .proposals
-        .iter()
-        .enumerate()
-        .find(|(_, proposal)| proposal.vec_index == vec_index)
-        .ok_or(FundError::InvalidProposalAccount)?;
+    Ok(())
+}
 
-    if proposal.proposer != *proposer_account_info.key {
-        return Err(FundError::InvalidProposerInfo.into());
-    }
+#[allow(clippy::too_many_arguments)]
+fn process_set_liquidity_terms(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    lockup_period: i64,
+    notice_period: i64,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-    if proposal.deadline < current_time {
-        return Err(FundError::VotingCeased.into());
-    }
+    let accounts_iter = &mut accounts.iter();
+    let signer_info = next_account_info(accounts_iter)?; // any signer, the proposal carries the authority .......
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
 
-    if proposal_aggregator_data.proposals[matched_index].voters_bitmap.iter().any(|voter| voter.0 == voter_vec_index) {
-        return Err(FundError::AlreadyVoted.into());
+    if !signer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
     }
 
-    proposal_aggregator_data.proposals[matched_index].voters_bitmap.push((voter_vec_index, vote));
-
-    if vote == 0 {
-        proposal_aggregator_data.proposals[matched_index].votes_no += 1;
-    } else {
-        proposal_aggregator_data.proposals[matched_index].votes_yes += 1;
+    if lockup_period < 0 || notice_period < 0 {
+        return Err(FundError::InvalidInstruction.into());
     }
 
-    let rent = Rent::get()?;
-    let current_aggregator_space = proposal_aggregator_info.data_len();
-    let new_proposal_space = current_aggregator_space + 5;
-    let current_proposal_rent = proposal_aggregator_info.lamports();
-    let new_proposal_rent = rent.minimum_balance(new_proposal_space);
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
 
-    if new_proposal_rent > current_proposal_rent {
-        invoke(
-            &system_instruction::transfer(
-                voter_account_info.key,
-                proposal_aggregator_info.key,
-                new_proposal_rent - current_proposal_rent
-            ),
-            &[voter_account_info.clone(), proposal_aggregator_info.clone(), system_program_info.clone()]
-        )?;
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
     }
 
-    proposal_aggregator_info.realloc(new_proposal_space, false)?;
-    proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = liquidity_terms_leaf(lockup_period, notice_period);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
 
-    msg!("[FUND-ACTIVITY] {} {} {} Vote: {} on proposal ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), proposal_index, vec_index);
+    fund_data.lockup_period = lockup_period;
+    fund_data.notice_period = notice_period;
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} Lockup set to {}s and notice period to {}s", fund_account_info.key.to_string(), current_time, lockup_period, notice_period);
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+fn process_set_share_transfer_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    share_transfer_policy: u8,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-// fn process_init_investment_proposal(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-//     amounts: Vec<u64>,
-//     slippages: Vec<u16>,
-//     deadline: i64,
-//     fund_name: String,
-// ) -> ProgramResult {
-//     let creation_time = Clock::get()?.unix_timestamp;
+    let accounts_iter = &mut accounts.iter();
+    let signer_info = next_account_info(accounts_iter)?; // any signer, the proposal carries the authority .......
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
 
-//     let accounts_iter = &mut accounts.iter();
-//     let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
-//     let fund_account_info = next_account_info(accounts_iter)?; // Fund Account ...................................
-//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal Account ........................
-//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
-//     let new_proposal_aggregator_info = next_account_info(accounts_iter)?; // new proposal aggregator account .....
-//     let vote_account_info = next_account_info(accounts_iter)?; // vote account 1 .................................
-//     let new_vote_account_info = next_account_info(accounts_iter)?; // vote account 2 .............................
-//     let proposer_token_account_info = next_account_info(accounts_iter)?;// 
-//     let governance_mint_info = next_account_info(accounts_iter)?;
-//     let token_program_2022_info = next_account_info(accounts_iter)?;
+    if !signer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     // Proposer needs to be signer
-//     if !proposer_account_info.is_signer {
-//         msg!("[FUND-ERROR] {} {} Wrong signer!(must be your wallet)", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    if share_transfer_policy > 2 {
+        return Err(FundError::InvalidInstruction.into());
+    }
 
-//     // Derive PDAs and check for equality with the provided ones
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let mut fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
 
-//     let token_account = get_associated_token_address_with_program_id(
-//         proposer_account_info.key,
-//         governance_mint_info.key,
-//         token_program_2022_info.key
-//     );
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if token_account != *proposer_token_account_info.key {
-//         msg!("[FUND-ERROR] {} {} Wrong governance token account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::InvalidTokenAccount.into());
-//     }
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if fund_data.governance_mint == Pubkey::default() {
+        msg!("Fund has no governance mint, its shares aren't tokens");
+        return Err(FundError::InvalidGovernanceMint.into());
+    }
 
-//     if proposer_token_account_info.data_is_empty() {
-//         msg!("[FUND-ERROR] {} {} Governance token account does not exists.", fund_account_info.key.to_string(), proposal_aggregator_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    let leaf = share_policy_leaf(share_transfer_policy);
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
 
-//     let token_account_data = proposer_token_account_info.try_borrow_data()?;
-//     let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
-//     let base_token_account = token_account.base;
-//     let balance = base_token_account.amount;
+    fund_data.share_transfer_policy = share_transfer_policy;
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
-//     if balance == 0 {
-//         msg!("[FUND-ERROR] {} {} You have no voting power(contribution) in this fund.", fund_account_info.key.to_string(), proposal_aggregator_info.key.to_string());
-//         return Err(FundError::NoVotingPower.into());
-//     }
+    msg!("[FUND-ACTIVITY] {} {} Share transfer policy set to {}", fund_account_info.key.to_string(), current_time, share_transfer_policy);
 
-//     let is_proposer_member = fund_data
-//         .members
-//         .iter()
-//         .any(|member| *member == *proposer_account_info.key);
+    Ok(())
+}
 
-//     if !is_proposer_member {
-//         msg!("[FUND-ERROR] {} {} You are not a member of this fund and so cannot create a proposal.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::NotAFundMember.into());
-//     }
+/// Applies every action of an approved config proposal once its timelock is over. Actions are
+/// checked and applied to the fund together, the fee account is only needed for a fee action.
+#[allow(clippy::too_many_arguments)]
+fn process_execute_config_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    proposal_index: u8,
+    vec_index: u16,
+    leaf_index: u8,
+    actions: Vec<ConfigAction>,
+    merkel_proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-//     let current_index = fund_data.current_proposal_index;
+    let accounts_iter = &mut accounts.iter();
+    let payer_info = next_account_info(accounts_iter)?; // payer, covers the fund account growing ...............
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ................................
 
-//     if *fund_account_info.key != fund_pda {
-//         msg!("[FUND-ERROR] {} {} Wrong fund account information.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    if !payer_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(
-//         &[
-//             b"proposal-aggregator",
-//             &[current_index],
-//             fund_pda.as_ref()
-//         ],
-//         program_id
-//     );
+    if actions.is_empty() {
+        return Err(FundError::InvalidConfigAction.into());
+    }
 
-//     let (new_proposal_aggregator_pda, new_proposal_aggregator_bump) = Pubkey::find_program_address(
-//         &[
-//             b"proposal-aggregator",
-//             &[current_index + 1],
-//             fund_pda.as_ref()
-//         ],
-//         program_id
-//     );
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
 
-//     if *proposal_aggregator_info.key != proposal_aggregator_pda || *new_proposal_aggregator_info.key != new_proposal_aggregator_pda {
-//         msg!("[FUND-ERROR] {} {} Given PDAs doesn't match with the derived ones(Wrong accounts provided).", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::InvalidProposalAccount.into());
-//     }
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     // Extract From Assets Mint
-//     let from_assets_info : Vec<&AccountInfo> = accounts_iter
-//         .take(amounts.len())
-//         .collect();
-//     if from_assets_info.len() != amounts.len() {
-//         msg!("[FUND-ERROR] {} {} Wrong information of number of assets to trade of.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
-//     let from_assets_mints: Vec<Pubkey> = from_assets_info.iter().map(|m| *m.key).collect();
+    // Members get a window after the vote to leave before the new parameters apply
+    let deadline = load_proposal(&proposal_aggregator_info.data.borrow(), vec_index)?.deadline / 1000;
+    if current_time <= deadline + CONFIG_TIMELOCK {
+        msg!("Config proposal is timelocked until {}", deadline + CONFIG_TIMELOCK);
+        return Err(FundError::TimelockActive.into());
+    }
 
-//     // Extract To Assets Mint
-//     let to_assets_info: Vec<&AccountInfo> = accounts_iter
-//         .take(amounts.len())
-//         .collect();
-//     if to_assets_info.len() != amounts.len() {
-//         msg!("[FUND-ERROR] {} {} Wrong information of number of assets to trade with.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//         return Err(FundError::InvalidAccountData.into());
-//     }
-//     let to_assets_mints: Vec<Pubkey> = to_assets_info.iter().map(|m| *m.key).collect();
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let leaf = config_actions_leaf(&actions)?;
+    consume_approved_action(proposal_aggregator_info, vec_index, leaf, leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
+
+    for action in actions.iter() {
+        match action.clone() {
+            ConfigAction::SetMaxMembers { max_members } => {
+                if max_members == 0 || max_members < fund_data.member_count {
+                    msg!("Max members can't go below the current {} members", fund_data.member_count);
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.max_members = max_members;
+            }
+            ConfigAction::SetTags { tags } => {
+                fund_data.tags = tags;
+            }
+            ConfigAction::SetVotingConfig { voting_mode, quorum_bps, approval_bps } => {
+                if voting_mode > 1 || !(1..=10_000).contains(&quorum_bps) || !(5_000..=10_000).contains(&approval_bps) {
+                    msg!("Voting configuration out of bounds");
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.voting_mode = voting_mode;
+                fund_data.quorum_bps = quorum_bps;
+                fund_data.approval_bps = approval_bps;
+            }
+            ConfigAction::SetFeeConfig { recipient, management_fee_bps, performance_fee_bps, payout_mode, price_feeds } => {
+                let fee_account_info = next_account_info(accounts_iter)?; // fee account ............................
+                write_fee_config(program_id, payer_info, fund_account_info, fee_account_info, system_program_info, recipient, management_fee_bps, performance_fee_bps, payout_mode, price_feeds, current_time)?;
+            }
+            ConfigAction::SetExecutorPolicy { executor_policy } => {
+                if executor_policy > 1 {
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.executor_policy = executor_policy;
+            }
+            ConfigAction::SetWhitelistedMints { mints } => {
+                let has_duplicates = mints.iter().enumerate().any(|(i, mint)| mints[..i].contains(mint));
+                if mints.len() > MAX_WHITELISTED_MINTS || has_duplicates {
+                    msg!("At most {} distinct mints can be whitelisted", MAX_WHITELISTED_MINTS);
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.whitelisted_mints = mints;
+            }
+            ConfigAction::SetPrivacy { privacy } => {
+                if privacy > 1 {
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.privacy = privacy;
+            }
+            ConfigAction::SetEntryConditions { min_first_deposit, allowlist_root, entry_fee_bps } => {
+                if entry_fee_bps > MAX_ENTRY_FEE_BPS {
+                    msg!("Entry fee above {} bps", MAX_ENTRY_FEE_BPS);
+                    return Err(FundError::InvalidConfigAction.into());
+                }
+                fund_data.min_first_deposit = min_first_deposit;
+                fund_data.allowlist_root = allowlist_root;
+                fund_data.entry_fee_bps = entry_fee_bps;
+            }
+        }
+    }
 
-//     // Rent Calculation
-//     let rent = Rent::get()?;
-//     let extra_proposal_space = (83 + to_assets_info.len()*74) as usize;
-//     let current_proposal_space = proposal_aggregator_info.data_len();
+    // Fund account follows the whitelist's size, the payer covers or gets back the difference
+    let fund_bytes = fund_data.try_to_vec()?;
+    if fund_bytes.len() != fund_account_info.data_len() {
+        let rent = Rent::get()?;
+        let current_fund_rent = rent.minimum_balance(fund_account_info.data_len());
+        let new_fund_rent = rent.minimum_balance(fund_bytes.len());
+        if new_fund_rent > current_fund_rent {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    fund_account_info.key,
+                    new_fund_rent - current_fund_rent
+                ),
+                &[payer_info.clone(), fund_account_info.clone(), system_program_info.clone()]
+            )?;
+        }
 
-//     let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
+        fund_account_info.realloc(fund_bytes.len(), false)?;
 
-//     // check if new proposal aggregator is required
-//     if (current_proposal_space + extra_proposal_space) > 10240 as usize {
-//         // Create Proposal Account
-//         invoke_signed(
-//             &system_instruction::create_account(
-//                 proposer_account_info.key,
-//                 new_proposal_aggregator_info.key,
-//                 rent.minimum_balance(37 + extra_proposal_space),
-//                 (37 + extra_proposal_space) as u64,
-//                 program_id
-//             ),
-//             &[
-//                 new_proposal_aggregator_info.clone(),
-//                 proposer_account_info.clone(),
-//                 system_program_info.clone(),
-//             ],
-//             &[&[
-//                 b"proposal-aggregator",
-//                 &[current_index + 1],
-//                 fund_account_info.key.as_ref(),
-//                 &[new_proposal_aggregator_bump]
-//             ]]
-//         )?;
+        if current_fund_rent > new_fund_rent {
+            **fund_account_info.lamports.borrow_mut() -= current_fund_rent - new_fund_rent;
+            **payer_info.lamports.borrow_mut() += current_fund_rent - new_fund_rent;
+        }
+    }
 
-//         let proposals_vec: Vec<Proposal> = vec![ Proposal {
-//             proposer: *proposer_account_info.key,
-//             from_assets: from_assets_mints,
-//             to_assets: to_assets_mints,
-//             amounts,
-//             slippages,
-//             votes_yes: balance,
-//             votes_no: 0 as u64,
-//             creation_time,
-//             deadline,
-//             executed: false,
-//             vec_index: 0 as u16
-//         }];
+    fund_account_info.data.borrow_mut().copy_from_slice(&fund_bytes);
 
-//         let new_proposal_data = ProposalAggregatorAccount {
-//             fund: *fund_account_info.key,
-//             index: current_index + 1,
-//             proposals: proposals_vec
-//         };
+    msg!("[FUND-ACTIVITY] {} {} {} Config proposal ({}, {}) applied with {} actions", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index, actions.len());
 
-//         new_proposal_data.serialize(&mut &mut new_proposal_aggregator_info.data.borrow_mut()[..])?;
+    Ok(())
+}
 
-//         fund_data.current_proposal_index += 1;
-//         fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
-        
-//         let new_vec_index = 0 as u16;
-//         let (new_vote_pda, new_vote_bump) = Pubkey::find_program_address(&[b"vote", &[current_index + 1], &new_vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
-//         if *new_vote_account_info.key != new_vote_pda {
-//             msg!("[FUND-ERROR] {} {} Invalid new vote account data.", fund_account_info.key.to_string(), proposer_account_info.key.to_string());
-//             return Err(FundError::InvalidVoteAccount.into());
-//         }
-//         if !new_vote_account_info.data_is_empty() {
-//             msg!("Wrong Vote Account");
-//             return Err(FundError::InvalidVoteAccount.into());
-//         }
+/// Transfer hook of light fund governance mints. Token-2022 calls it after moving the tokens,
+/// and the internal ledger follows: the sender's balance goes to the receiver, who has to be a
+/// member unless the policy lets anyone in. New members' rent and creation cost share can't be
+/// paid from inside the hook, so they have to be sent to their membership PDA ahead of the
+/// transfer. Shares backing a vote that is still open can't be transferred.
+fn process_share_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-//         let vote_space = 40 as usize;
-//         let vote_rent = rent.minimum_balance(vote_space);
+    let accounts_iter = &mut accounts.iter();
+    let source_info = next_account_info(accounts_iter)?; // sender's governance token account ....................
+    let mint_info = next_account_info(accounts_iter)?; // governance mint ........................................
+    let destination_info = next_account_info(accounts_iter)?; // receiver's governance token account .............
+    let _authority_info = next_account_info(accounts_iter)?; // sender or its delegate ...........................
+    let extra_account_metas_info = next_account_info(accounts_iter)?; // extra account metas .....................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let destination_user_info = next_account_info(accounts_iter)?; // receiver's user account ...................
+    let source_membership_info = next_account_info(accounts_iter)?; // sender's membership PDA ...................
+    let destination_membership_info = next_account_info(accounts_iter)?; // receiver's membership PDA, pre-funded with its rent and the creation cost share when not a member yet
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // rent reserve ...................................
+
+    if *mint_info.owner != spl_token_2022::id() || *fund_account_info.owner != *program_id {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let (extra_account_metas_pda, _extra_account_metas_bump) = get_extra_account_metas_address_and_bump_seed(mint_info.key, program_id);
+    if *extra_account_metas_info.key != extra_account_metas_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if fund_data.governance_mint != *mint_info.key {
+        return Err(FundError::InvalidGovernanceMint.into());
+    }
 
-//         invoke_signed(
-//             &system_instruction::create_account(
-//                 proposer_account_info.key,
-//                 new_vote_account_info.key,
-//                 vote_rent,
-//                 vote_space as u64,
-//                 program_id
-//             ),
-//             &[proposer_account_info.clone(), new_vote_account_info.clone(), system_program_info.clone()],
-//             &[&[b"vote", &[current_index + 1], &new_vec_index.to_le_bytes(), fund_account_info.key.as_ref(), &[new_vote_bump]]]
-//         )?;
+    // Only ever reached from inside a Token-2022 transfer
+    let (source_owner, destination_owner) = {
+        let source_data = source_info.try_borrow_data()?;
+        let source = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&source_data)?;
+        let transferring: bool = source.get_extension::<TransferHookAccount>()?.transferring.into();
+        if !transferring {
+            return Err(FundError::InvalidInstruction.into());
+        }
 
-//         let new_voters_vec: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1)];
-//         let new_vote_data = VoteAccount {
-//             proposal_index: current_index + 1,
-//             vec_index: new_vec_index,
-//             voters: new_voters_vec
-//         };
+        let destination_data = destination_info.try_borrow_data()?;
+        let destination = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&destination_data)?;
+        (source.base.owner, destination.base.owner)
+    };
 
-//         new_vote_data.serialize(&mut &mut new_vote_account_info.data.borrow_mut()[..])?;
+    if fund_data.share_transfer_policy == 0 {
+        return Err(FundError::SharesNotTransferable.into());
+    }
 
-//         msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, (current_index + 1), new_vec_index, proposer_account_info.key.to_string());
-//     } else {
-//         let new_aggregator_size = extra_proposal_space + current_proposal_space as usize;
-//         let current_rent_exempt = proposal_aggregator_info.lamports();
-//         let new_rent_exempt = rent.minimum_balance(new_aggregator_size);
+    // Withdrawals burn from the member's associated account, shares can't sit anywhere else
+    if *source_info.key != get_associated_token_address_with_program_id(&source_owner, mint_info.key, &spl_token_2022::id()) ||
+       *destination_info.key != get_associated_token_address_with_program_id(&destination_owner, mint_info.key, &spl_token_2022::id()) {
+        return Err(FundError::InvalidTokenAccount.into());
+    }
 
-//         if new_rent_exempt > current_rent_exempt {
-//             invoke(
-//                 &system_instruction::transfer(
-//                     proposer_account_info.key,
-//                     proposal_aggregator_info.key,
-//                     new_rent_exempt-current_rent_exempt
-//                 ),
-//                 &[proposal_aggregator_info.clone(), proposer_account_info.clone(), system_program_info.clone()]
-//             )?;
-//         }
+    if source_owner == destination_owner {
+        return Ok(());
+    }
 
-//         let num_of_proposals = proposal_aggregator_data.proposals.len();
-//         let mut vec_index = 0 as u16;
-//         if num_of_proposals != 0 {
-//             vec_index = proposal_aggregator_data.proposals[num_of_proposals - 1].vec_index + 1;
-//         }
-//         msg!("Vec Index: {}", vec_index);
+    let (destination_user_pda, _destination_user_bump) = Pubkey::find_program_address(&[b"user", destination_owner.as_ref()], program_id);
+    if *destination_user_info.key != destination_user_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//         proposal_aggregator_info.realloc(new_aggregator_size, false)?;
-        
-//         proposal_aggregator_data.proposals.push( Proposal {
-//             proposer: *proposer_account_info.key,
-//             from_assets: from_assets_mints,
-//             to_assets: to_assets_mints,
-//             amounts,
-//             slippages,
-//             votes_yes: balance,
-//             votes_no: 0 as u64,
-//             creation_time,
-//             deadline,
-//             executed: false,
-//             vec_index
-//         });
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    if *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//         proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+    let mut source_membership = load_member(program_id, source_membership_info, fund_account_info.key, &source_owner)?;
+    if current_time < source_membership.last_deposit_time + fund_data.lockup_period {
+        msg!("Deposit is locked until {}", source_membership.last_deposit_time + fund_data.lockup_period);
+        return Err(FundError::DepositLocked.into());
+    }
 
-//         let (vote_pda, vote_bump) = Pubkey::find_program_address(&[b"vote", &[current_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
-//         if *vote_account_info.key != vote_pda {
-//             return Err(FundError::InvalidVoteAccount.into());
-//         }
-//         if !vote_account_info.data_is_empty() {
-//             msg!("Wrong Vote Account");
-//             return Err(FundError::InvalidVoteAccount.into());
-//         }
+    // Shares that voted already can't vote again from the receiver's wallet
+    if current_time <= source_membership.votes_locked_until {
+        msg!("Shares back open votes until {}", source_membership.votes_locked_until);
+        return Err(FundError::DepositLocked.into());
+    }
 
-//         let vote_space = 40 as usize;
-//         let vote_rent = rent.minimum_balance(vote_space);
-        
-//         invoke_signed(
-//             &system_instruction::create_account(
-//                 proposer_account_info.key,
-//                 vote_account_info.key,
-//                 vote_rent,
-//                 vote_space as u64,
-//                 program_id
-//             ),
-//             &[vote_account_info.clone(), proposer_account_info.clone(), system_program_info.clone()],
-//             &[&[b"vote", &[current_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref(), &[vote_bump]]]
-//         )?;
+    source_membership.deposit = source_membership.deposit.checked_sub(amount).ok_or(FundError::InvalidAccountData)?;
 
-//         let voters: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1)];
+    let mut destination_membership = match load_membership(program_id, destination_membership_info, fund_account_info.key, &destination_owner)? {
+        Some(membership) if !membership.is_pending => membership,
+        Some(_) => {
+            msg!("Receiver has to accept their invitation first");
+            return Err(FundError::AlreadyInvited.into());
+        }
+        None => {
+            if fund_data.share_transfer_policy == 1 {
+                msg!("Shares can only be transferred to members");
+                return Err(FundError::NotAFundMember.into());
+            }
 
-//         let vote_data = VoteAccount {
-//             proposal_index: current_index,
-//             vec_index,
-//             voters,
-//         };
+            if fund_data.winding_down {
+                return Err(FundError::FundWindingDown.into());
+            }
 
-//         vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+            let membership_rent = Rent::get()?.minimum_balance(MEMBERSHIP_SPACE);
+            let creation_share = creation_cost_share(&fund_data);
+            if destination_membership_info.lamports() < membership_rent + creation_share {
+                msg!("Rent for the new member and their creation cost share of {} have to be sent to their membership PDA first", creation_share);
+                return Err(ProgramError::AccountNotRentExempt);
+            }
 
-//         msg!("[FUND-ACTIVITY] {} {} {} Proposal created: ({}, {}) by {}", fund_account_info.key.to_string(), creation_time, fund_name, current_index, vec_index, proposer_account_info.key.to_string());
-//     }
+            // Already funded, so the membership is allocated in place instead of created
+            let (_destination_membership_pda, destination_membership_bump) = Pubkey::find_program_address(&[b"member", fund_account_info.key.as_ref(), destination_owner.as_ref()], program_id);
+            let membership_seeds: &[&[u8]] = &[b"member", fund_account_info.key.as_ref(), destination_owner.as_ref(), &[destination_membership_bump]];
+            invoke_signed(
+                &system_instruction::allocate(destination_membership_info.key, MEMBERSHIP_SPACE as u64),
+                &[destination_membership_info.clone(), system_program_info.clone()],
+                &[membership_seeds]
+            )?;
+            invoke_signed(
+                &system_instruction::assign(destination_membership_info.key, program_id),
+                &[destination_membership_info.clone(), system_program_info.clone()],
+                &[membership_seeds]
+            )?;
 
-//     Ok(())
-// }
+            // Joiner's share of the fund's creation cost goes to the rent reserve, the creator is
+            // refunded by the next join that goes through the program
+            if creation_share != 0 {
+                **destination_membership_info.lamports.borrow_mut() -= creation_share;
+                **rent_reserve_info.lamports.borrow_mut() += creation_share;
+                fund_data.rent_collected += creation_share;
+            }
 
+            let mut membership = pending_membership(fund_account_info.key, &destination_owner, &source_membership.wallet, source_membership.member_index, current_time);
+            admit_member(&mut fund_data, &mut membership, current_time)?;
 
-// fn process_vote_on_proposal(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-//     vote: u8,
-//     proposal_index: u8,
-//     vec_index: u16,
-//     fund_name: String,
-// ) -> ProgramResult {
-//     let current_time = Clock::get()?.unix_timestamp;
+            let mut destination_data = UserAccount::try_from_slice(&destination_user_info.data.borrow())?;
+            destination_data.funds_joined += 1;
+            destination_data.serialize(&mut &mut destination_user_info.data.borrow_mut()[..])?;
 
-//     let accounts_iter = &mut accounts.iter();
-//     let voter_account_info = next_account_info(accounts_iter)?; // Voter's Wallet ................................
-//     let vote_account_info = next_account_info(accounts_iter)?; // Voter's vote account to be created .............
-//     let proposal_aggregator_info = next_account_info(accounts_iter)?; // Proposal account ........................
-//     let system_program_info = next_account_info(accounts_iter)?; // System Program ...............................
-//     let fund_account_info = next_account_info(accounts_iter)?; // fund Account ...................................
-//     let governance_token_mint_info = next_account_info(accounts_iter)?; // Governance Mint account ...............
-//     let voter_token_account_info = next_account_info(accounts_iter)?; // Voter's governance token account ........
-//     let token_program_2022_info = next_account_info(accounts_iter)?; // Token program extensions .................
-//     let proposer_account_info = next_account_info(accounts_iter)?; // Proposer Wallet ............................
+            msg!("[FUND-ACTIVITY] {} {} Member joined through a share transfer: {}", fund_account_info.key.to_string(), current_time, destination_owner.to_string());
+            membership
+        }
+    };
 
-//     msg!("Vec Index: {}", vec_index);
+    // Received shares stay locked as long as the sender's would have
+    destination_membership.deposit += amount;
+    destination_membership.last_deposit_time = destination_membership.last_deposit_time.max(source_membership.last_deposit_time);
 
-//     // Voter needs to be signer
-//     if !voter_account_info.is_signer {
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    source_membership.serialize(&mut &mut source_membership_info.data.borrow_mut()[..])?;
+    destination_membership.serialize(&mut &mut destination_membership_info.data.borrow_mut()[..])?;
 
-//     // Pdas derivation
-//     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-//     let (vote_pda, _vote_bump) = Pubkey::find_program_address(&[b"vote", &[proposal_index], &vec_index.to_le_bytes(), fund_account_info.key.as_ref()], program_id);
-//     let token_account = spl_associated_token_account::get_associated_token_address_with_program_id(
-//         voter_account_info.key,
-//         governance_token_mint_info.key,
-//         token_program_2022_info.key
-//     );
+    msg!("[FUND-ACTIVITY] {} {} Shares transferred: {} from {} to {}", fund_account_info.key.to_string(), current_time, amount, source_owner.to_string(), destination_owner.to_string());
 
-//     let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+    Ok(())
+}
 
-//     // Pdas verification
-//     if *fund_account_info.key != fund_pda ||
-//        *vote_account_info.key != vote_pda ||
-//        token_account != *voter_token_account_info.key ||
-//        *proposal_aggregator_info.key != proposal_aggregator_pda {
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+/// Moves everything tied to a wallet over to another one: the user profile, fund memberships
+/// with their member index and roles, pending invitations, governance tokens, withdrawal requests,
+/// the proposer and voter identities on the listed proposals, and the creator of the listed
+/// conditional orders and positions. One fund account comes per fund the user joined, each followed by the old and new
+/// wallet's membership PDAs, the old wallet's remove member proposal PDA, the increment proposal
+/// PDA, the old and new wallet's withdrawal request PDAs and, when the fund has a mint, by the
+/// governance mint, the old wallet's governance token account, the Token-2022 program and the new
+/// wallet's one. Then one fund account per pending invitation, each followed by the old and new
+/// wallet's membership PDAs. A vote on removing the old wallet has to be resolved first.
+#[allow(clippy::too_many_arguments)]
+fn process_migrate_member(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    num_of_aggregators: u8,
+    num_of_remove_proposals: u8,
+    num_of_join_proposals: u8,
+    num_of_orders: u8,
+    num_of_positions: u8,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-//     let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let accounts_iter = &mut accounts.iter();
+    let old_wallet_info = next_account_info(accounts_iter)?; // wallet being retired ..............................
+    let new_wallet_info = next_account_info(accounts_iter)?; // wallet taking over, pays for new space ...........
+    let old_user_info = next_account_info(accounts_iter)?; // old wallet's user account ..........................
+    let new_user_info = next_account_info(accounts_iter)?; // new wallet's user account ..........................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 .......................
+    let ata_program_info = next_account_info(accounts_iter)?; // associated token program ........................
+
+    if !old_wallet_info.is_signer || !new_wallet_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-//     let is_voter_member = fund_data
-//         .members
-//         .iter()
-//         .any(|member| *member == *voter_account_info.key);
+    if old_wallet_info.key == new_wallet_info.key || *token_program_2022_info.key != spl_token_2022::id() {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if !is_voter_member {
-//         return Err(FundError::NotAFundMember.into());
-//     }
+    let (old_user_pda, _old_user_bump) = Pubkey::find_program_address(&[b"user", old_wallet_info.key.as_ref()], program_id);
+    let (new_user_pda, new_user_bump) = Pubkey::find_program_address(&[b"user", new_wallet_info.key.as_ref()], program_id);
+    if *old_user_info.key != old_user_pda || *new_user_info.key != new_user_pda || old_user_info.data_is_empty() {
+        return Err(FundError::InvalidAccountData.into());
+    }
 
-//     if fund_data.governance_mint != *governance_token_mint_info.key {
-//         return Err(FundError::InvalidGovernanceMint.into());
-//     }
+    let old_data = UserAccount::try_from_slice(&old_user_info.data.borrow())?;
+    let rent = Rent::get()?;
 
-//     let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
-//     let (matched_index, proposal) = proposal_aggregator_data
-//         .proposals
-//         .iter()
-//         .enumerate()
-//         .find(|(_, proposal)| proposal.vec_index == vec_index)
-//         .ok_or(FundError::InvalidProposalAccount)?;
+    // A fresh user account keeps the old profile, an existing one just takes the counters
+    let mut new_data = if new_user_info.data_is_empty() {
+        let new_user_space = 59 + 4 + 4;
+        invoke_signed(
+            &system_instruction::create_account(
+                new_wallet_info.key,
+                new_user_info.key,
+                rent.minimum_balance(new_user_space),
+                new_user_space as u64,
+                program_id
+            ),
+            &[new_wallet_info.clone(), new_user_info.clone(), system_program_info.clone()],
+            &[&[b"user", new_wallet_info.key.as_ref(), &[new_user_bump]]]
+        )?;
 
-//     if proposal.proposer != *proposer_account_info.key {
-//         return Err(FundError::InvalidProposerInfo.into());
-//     }
+        UserAccount {
+            user_cid: old_data.user_cid,
+            funds_joined: 0,
+            pending_invites: 0,
+        }
+    } else {
+        UserAccount::try_from_slice(&new_user_info.data.borrow())?
+    };
 
-//     if proposal.deadline < current_time {
-//         return Err(FundError::VotingCeased.into());
-//     }
+    let mut migrated_funds: Vec<Pubkey> = vec![];
+    for _i in 0..old_data.funds_joined {
+        let fund_account_info = next_account_info(accounts_iter)?;
+        if *fund_account_info.owner != *program_id || migrated_funds.contains(fund_account_info.key) {
+            return Err(FundError::InvalidAccountData.into());
+        }
+        migrated_funds.push(*fund_account_info.key);
+
+        let old_membership_info = next_account_info(accounts_iter)?;
+        let new_membership_info = next_account_info(accounts_iter)?;
+        let old_removal_info = next_account_info(accounts_iter)?;
+        let increment_proposal_info = next_account_info(accounts_iter)?;
+        let old_request_info = next_account_info(accounts_iter)?;
+        let new_request_info = next_account_info(accounts_iter)?;
+
+        let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+        let mut membership = load_member(program_id, old_membership_info, fund_account_info.key, old_wallet_info.key)?;
+        if load_membership(program_id, new_membership_info, fund_account_info.key, new_wallet_info.key)?.is_some() {
+            msg!("New wallet is already in fund {}", fund_account_info.key.to_string());
+            return Err(FundError::AlreadyMember.into());
+        }
 
-//     let mut vote_data = VoteAccount::try_from_slice(&vote_account_info.data.borrow())?;
+        // A removal vote on the old wallet would lose its target, it has to run its course first
+        let (old_removal_pda, _old_removal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_account_info.key.as_ref(), old_wallet_info.key.as_ref()], program_id);
+        if *old_removal_info.key != old_removal_pda {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
+        if !old_removal_info.data_is_empty() {
+            msg!("A proposal to remove the old wallet is open in fund {}", fund_account_info.key.to_string());
+            return Err(FundError::InvalidProposalState.into());
+        }
 
-//     let voter_exists = vote_data
-//         .voters
-//         .iter()
-//         .any(|(key, _)| *key == *voter_account_info.key);
+        let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
+        if *increment_proposal_info.key != increment_proposal_pda {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
+        if !increment_proposal_info.data_is_empty() {
+            let mut proposal_data = IncrementProposalAccount::try_from_slice(&increment_proposal_info.data.borrow())?;
+            if proposal_data.proposer == *old_wallet_info.key {
+                proposal_data.proposer = *new_wallet_info.key;
+            }
+            migrate_voters(&mut proposal_data.voters, old_wallet_info.key, new_wallet_info.key);
+            proposal_data.serialize(&mut &mut increment_proposal_info.data.borrow_mut()[..])?;
+        }
 
-//     if voter_exists {
-//         return Err(FundError::AlreadyVoted.into());
-//     }
+        // Withdrawal requests are keyed by wallet, a pending one moves to the new wallet's PDA
+        let (old_request_pda, _old_request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_account_info.key.as_ref(), old_wallet_info.key.as_ref()], program_id);
+        let (new_request_pda, new_request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref()], program_id);
+        if *old_request_info.key != old_request_pda || *new_request_info.key != new_request_pda {
+            return Err(FundError::InvalidAccountData.into());
+        }
+        if !old_request_info.data_is_empty() {
+            if !new_request_info.data_is_empty() {
+                return Err(FundError::InvalidWithdrawalRequest.into());
+            }
 
-//     if voter_token_account_info.data_is_empty() {
-//         msg!("No Voting Power");
-//         return Err(FundError::NoVotingPower.into());
-//     }
+            let mut request = WithdrawalRequest::try_from_slice(&old_request_info.data.borrow())?;
+            let request_space = old_request_info.data_len();
+            invoke_signed(
+                &system_instruction::create_account(
+                    new_wallet_info.key,
+                    new_request_info.key,
+                    rent.minimum_balance(request_space),
+                    request_space as u64,
+                    program_id
+                ),
+                &[new_wallet_info.clone(), new_request_info.clone(), system_program_info.clone()],
+                &[&[b"withdrawal-request", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref(), &[new_request_bump]]]
+            )?;
 
-//     if vote_account_info.data_is_empty() {
-//         msg!("Vote account should be already created");
-//         return Err(FundError::InvalidVoteAccount.into());
-//     } else {
-//         let rent = Rent::get()?;
-//         let extra_vote_space = 33 as usize;
-//         let current_vote_space = vote_account_info.data_len();
-//         let new_vote_space = current_vote_space + extra_vote_space;
-//         let new_rent = rent.minimum_balance(new_vote_space);
-//         let current_rent = vote_account_info.lamports();
+            request.member = *new_wallet_info.key;
+            request.serialize(&mut &mut new_request_info.data.borrow_mut()[..])?;
 
-//         if new_rent > current_rent {
-//             invoke(
-//                 &system_instruction::transfer(
-//                     voter_account_info.key,
-//                     vote_account_info.key,
-//                     new_rent - current_rent
-//                 ),
-//                 &[system_program_info.clone(), voter_account_info.clone(), vote_account_info.clone()]
-//             )?;
-//         }
+            let old_request_lamports = old_request_info.lamports();
+            **old_request_info.lamports.borrow_mut() = 0;
+            **old_wallet_info.lamports.borrow_mut() += old_request_lamports;
+            old_request_info.data.borrow_mut().fill(0);
+        }
 
-//         vote_account_info.realloc(new_vote_space, false)?;
-        
-//         vote_data.voters.push((*voter_account_info.key, vote));
-//         vote_data.serialize(&mut &mut vote_account_info.data.borrow_mut()[..])?;
+        // Member index stays, so votes already cast keep pointing at the member
+        let (_new_membership_pda, new_membership_bump) = Pubkey::find_program_address(&[b"member", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref()], program_id);
+        invoke_signed(
+            &system_instruction::create_account(
+                new_wallet_info.key,
+                new_membership_info.key,
+                rent.minimum_balance(MEMBERSHIP_SPACE),
+                MEMBERSHIP_SPACE as u64,
+                program_id
+            ),
+            &[new_wallet_info.clone(), new_membership_info.clone(), system_program_info.clone()],
+            &[&[b"member", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref(), &[new_membership_bump]]]
+        )?;
+
+        membership.wallet = *new_wallet_info.key;
+        membership.serialize(&mut &mut new_membership_info.data.borrow_mut()[..])?;
 
-//         let token_account_data = voter_token_account_info.try_borrow_data()?;
-//         let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
-//         let base_token_account = token_account.base;
-//         let balance = base_token_account.amount;
+        let old_membership_lamports = old_membership_info.lamports();
+        **old_membership_info.lamports.borrow_mut() = 0;
+        **old_wallet_info.lamports.borrow_mut() += old_membership_lamports;
+        for byte in old_membership_info.data.borrow_mut().iter_mut() {
+            *byte = 0;
+        }
 
-//         if balance == 0 {
-//             msg!("No Voting Power");
-//             return Err(FundError::NoVotingPower.into());
-//         }
+        if fund_data.creator == *old_wallet_info.key {
+            fund_data.creator = *new_wallet_info.key;
+        }
+        if fund_data.rent_recipient == *old_wallet_info.key {
+            fund_data.rent_recipient = *new_wallet_info.key;
+        }
 
-//         if vote != 0 {
-//             proposal_aggregator_data.proposals[matched_index as usize].votes_yes += balance;
-//         } else {
-//             proposal_aggregator_data.proposals[matched_index as usize].votes_no += balance;
-//         }
+        let old_governance = next_governance_accounts(accounts_iter, &fund_data, old_wallet_info.key)?;
+        if let Some((governance_mint_info, old_governance_account_info, _token_program_2022_info)) = old_governance {
+            let new_governance_account_info = next_account_info(accounts_iter)?;
+            if *new_governance_account_info.key != get_associated_token_address_with_program_id(new_wallet_info.key, governance_mint_info.key, token_program_2022_info.key) {
+                return Err(FundError::InvalidTokenAccount.into());
+            }
 
-//         proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
-//     }
+            let fund_name = String::from_utf8(fund_data.name.to_vec()).map_err(|_| FundError::InvalidFundDetails)?;
+            let fund_name = fund_name.trim_end_matches(char::from(0));
+            let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+            if fund_pda != *fund_account_info.key {
+                return Err(FundError::InvalidFundDetails.into());
+            }
 
-//     msg!("[FUND-ACTIVITY] {} {} {} Vote: {} on proposal ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, voter_account_info.key.to_string(), proposal_index, vec_index);
+            if new_governance_account_info.data_is_empty() {
+                invoke(
+                    &create_associated_token_account(
+                        new_wallet_info.key,
+                        new_wallet_info.key,
+                        governance_mint_info.key,
+                        token_program_2022_info.key
+                    ),
+                    &[
+                        new_wallet_info.clone(),
+                        new_governance_account_info.clone(),
+                        new_wallet_info.clone(),
+                        governance_mint_info.clone(),
+                        system_program_info.clone(),
+                        token_program_2022_info.clone(),
+                        ata_program_info.clone(),
+                    ]
+                )?;
+            }
 
-//     Ok(())
-// }
+            // Fund re-issues the shares rather than transferring them, the share policy doesn't apply
+            let fund_seeds: &[&[u8]] = &[b"light-fund", fund_name.as_bytes(), &[fund_bump]];
+            let shares = token_balance(old_governance_account_info)?;
+            burn_governance_tokens(token_program_2022_info, governance_mint_info, old_governance_account_info, fund_account_info, shares, fund_seeds)?;
+            mint_governance_tokens(token_program_2022_info, governance_mint_info, new_governance_account_info, fund_account_info, shares, fund_seeds)?;
+        }
 
+        fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
+    }
 
-fn process_cancel_investment_proposal(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    fund_name: String,
-    proposal_index: u8,
-    vec_index: u16
-) -> ProgramResult {
-    let current_time = Clock::get()?.unix_timestamp;
+    // Pending invitations move to the new wallet, still to be accepted or declined from there
+    for _i in 0..old_data.pending_invites {
+        let fund_account_info = next_account_info(accounts_iter)?;
+        let old_membership_info = next_account_info(accounts_iter)?;
+        let new_membership_info = next_account_info(accounts_iter)?;
+        if *fund_account_info.owner != *program_id {
+            return Err(FundError::InvalidAccountData.into());
+        }
 
-    let accounts_iter = &mut accounts.iter();
-    let proposer_account_info = next_account_info(accounts_iter)?; // Proposer wallet ..........................
-    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator ...................
-    let fund_account_info = next_account_info(accounts_iter)?; // fund account .................................
-    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ......................
+        let Some(mut invitation) = load_membership(program_id, old_membership_info, fund_account_info.key, old_wallet_info.key)? else {
+            return Err(FundError::NotInvited.into());
+        };
+        if !invitation.is_pending {
+            return Err(FundError::AlreadyMember.into());
+        }
+        if load_membership(program_id, new_membership_info, fund_account_info.key, new_wallet_info.key)?.is_some() {
+            msg!("New wallet is already in or invited to fund {}", fund_account_info.key.to_string());
+            return Err(FundError::AlreadyInvited.into());
+        }
 
-    if !proposer_account_info.is_signer {
-        return Err(FundError::MissingRequiredSignature.into());
-    }
+        let (_new_membership_pda, new_membership_bump) = Pubkey::find_program_address(&[b"member", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref()], program_id);
+        invoke_signed(
+            &system_instruction::create_account(
+                new_wallet_info.key,
+                new_membership_info.key,
+                rent.minimum_balance(MEMBERSHIP_SPACE),
+                MEMBERSHIP_SPACE as u64,
+                program_id
+            ),
+            &[new_wallet_info.clone(), new_membership_info.clone(), system_program_info.clone()],
+            &[&[b"member", fund_account_info.key.as_ref(), new_wallet_info.key.as_ref(), &[new_membership_bump]]]
+        )?;
 
-    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
-    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+        invitation.wallet = *new_wallet_info.key;
+        invitation.serialize(&mut &mut new_membership_info.data.borrow_mut()[..])?;
 
-    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda || *rent_reserve_info.key != rent_pda {
-        return Err(FundError::InvalidAccountData.into());
+        // Inviter paid for the old PDA, its rent pays the new wallet back so the inviter's rent
+        // is what the invitation keeps holding
+        let old_membership_lamports = old_membership_info.lamports();
+        **old_membership_info.lamports.borrow_mut() = 0;
+        **new_wallet_info.lamports.borrow_mut() += old_membership_lamports;
+        old_membership_info.data.borrow_mut().fill(0);
     }
 
-    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-    if proposal_index > fund_data.current_proposal_index {
-        msg!("Yaha se aaya hai");
-        return Err(FundError::InvalidProposalAccount.into());
-    }
+    // Proposals made and aggregators opened by the old wallet, votes are kept by member index
+    for _i in 0..num_of_aggregators {
+        let proposal_aggregator_info = next_account_info(accounts_iter)?;
+        let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+        let aggregator_index = load_aggregator(&aggregator_data)?.index;
+        let is_fund_aggregator = migrated_funds.iter().any(|fund| {
+            Pubkey::find_program_address(&[b"proposal-aggregator", &[aggregator_index], fund.as_ref()], program_id).0 == *proposal_aggregator_info.key
+        });
+        if !is_fund_aggregator {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
 
-    msg!("Vec Index obtained = {}", vec_index);
+        let header: &mut ProposalAggregatorAccount = bytemuck::try_from_bytes_mut(&mut aggregator_data[..AGGREGATOR_HEADER_SPACE]).map_err(|_| FundError::InvalidProposalAccount)?;
+        if header.opener == *old_wallet_info.key {
+            header.opener = *new_wallet_info.key;
+        }
 
-    let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
+        for vec_index in 0..proposal_slots(&aggregator_data) {
+            let proposal = proposal_slot_mut(&mut aggregator_data, vec_index as u16)?;
+            if proposal.proposer == *old_wallet_info.key {
+                proposal.proposer = *new_wallet_info.key;
+            }
+        }
+    }
 
-    msg!("Number of proposals = {}", proposal_aggregator_data.proposals.len());
-    msg!("Vec Index of that proposal = {}", proposal_aggregator_data.proposals[0].vec_index);
-    let proposal = proposal_aggregator_data
-        .proposals
-        .iter()
-        .find(|proposal| proposal.vec_index == vec_index)
-        .ok_or(FundError::InvalidProposalAccount)?;
+    // Open removal votes, a removal of the old wallet itself has to run its course first
+    for _i in 0..num_of_remove_proposals {
+        let remove_proposal_info = next_account_info(accounts_iter)?;
+        let mut proposal_data = RemoveMemberProposal::try_from_slice(&remove_proposal_info.data.borrow())?;
+        let (remove_proposal_pda, _remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", proposal_data.fund.as_ref(), proposal_data.target.as_ref()], program_id);
+        if *remove_proposal_info.key != remove_proposal_pda || proposal_data.target == *old_wallet_info.key {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
 
-    if proposal.proposer != *proposer_account_info.key {
-        return Err(FundError::InvalidProposerInfo.into());
-    }
+        if proposal_data.proposer == *old_wallet_info.key {
+            proposal_data.proposer = *new_wallet_info.key;
+        }
 
-    if proposal.deadline < current_time {
-        return Err(FundError::DeadlineReached.into());
+        migrate_voters(&mut proposal_data.voters, old_wallet_info.key, new_wallet_info.key);
+
+        proposal_data.serialize(&mut &mut remove_proposal_info.data.borrow_mut()[..])?;
     }
 
-    // Remove proposal from aggregator
-    let rent = Rent::get()?;
-    let current_aggregator_size = proposal_aggregator_info.data_len();
-    msg!("Current Aggregator Size = {}", current_aggregator_size);
-    let new_aggregator_size = current_aggregator_size - (164 + proposal.voters_bitmap.len() * 5);
-    let current_aggregator_rent = proposal_aggregator_info.lamports();
-    let new_aggregator_rent = rent.minimum_balance(new_aggregator_size);
-    let new_aggregator_rent_for_proposer = rent.minimum_balance(new_aggregator_size + proposal.voters_bitmap.len() * 5);
-    msg!("New Aggregator Size = {}", new_aggregator_size);
-    msg!("New Aggregator Rent = {}", new_aggregator_rent);
-    msg!("New Aggregator Rent for Proposer = {}", new_aggregator_rent_for_proposer);
+    // Open join votes the old wallet took part in
+    for _i in 0..num_of_join_proposals {
+        let join_proposal_info = next_account_info(accounts_iter)?;
+        if *join_proposal_info.owner != *program_id {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
 
-    proposal_aggregator_info.realloc(new_aggregator_size, false)?;
+        let fund = JoinProposal::try_from_slice(&join_proposal_info.data.borrow())?.fund;
+        if !migrated_funds.contains(&fund) {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
 
-    if new_aggregator_rent < current_aggregator_rent {
-        **proposal_aggregator_info.lamports.borrow_mut() -= current_aggregator_rent - new_aggregator_rent;
-        **proposer_account_info.lamports.borrow_mut() += current_aggregator_rent - new_aggregator_rent_for_proposer;
-        **rent_reserve_info.lamports.borrow_mut() += new_aggregator_rent_for_proposer - new_aggregator_rent;
+        let mut join_proposal = load_join_proposal(program_id, join_proposal_info, &fund)?;
+        migrate_voters(&mut join_proposal.voters, old_wallet_info.key, new_wallet_info.key);
+        join_proposal.serialize(&mut &mut join_proposal_info.data.borrow_mut()[..])?;
     }
 
-    proposal_aggregator_data.proposals.retain(|p| p.vec_index != vec_index);
+    // Conditional orders the old wallet scheduled
+    for _i in 0..num_of_orders {
+        let order_info = next_account_info(accounts_iter)?;
+        if *order_info.owner != *program_id {
+            return Err(FundError::InvalidAccountData.into());
+        }
 
-    proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+        let mut order = ConditionalOrder::try_from_slice(&order_info.data.borrow())?;
+        let (order_pda, _order_bump) = Pubkey::find_program_address(
+            &[
+                b"conditional-order",
+                order.fund.as_ref(),
+                &[order.proposal_index],
+                &order.vec_index.to_le_bytes(),
+                &[order.order_index]
+            ],
+            program_id
+        );
+        if *order_info.key != order_pda || !migrated_funds.contains(&order.fund) {
+            return Err(FundError::InvalidAccountData.into());
+        }
 
-    msg!("[FUND-ACTIVITY] {} {} Investment Proposal ({}, {}) deleted by proposer ({})", fund_account_info.key.to_string(), current_time, proposal_index, vec_index, proposer_account_info.key.to_string());
+        if order.creator == *old_wallet_info.key {
+            order.creator = *new_wallet_info.key;
+            order.serialize(&mut &mut order_info.data.borrow_mut()[..])?;
+        }
+    }
 
-    Ok(())
-}
+    // Positions the old wallet opened
+    for _i in 0..num_of_positions {
+        let position_info = next_account_info(accounts_iter)?;
+        if *position_info.owner != *program_id {
+            return Err(FundError::InvalidPosition.into());
+        }
 
+        let mut position = Position::try_from_slice(&position_info.data.borrow())?;
+        let (position_pda, _position_bump) = Pubkey::find_program_address(&[b"position", position.fund.as_ref(), &[position.position_index]], program_id);
+        if *position_info.key != position_pda || !migrated_funds.contains(&position.fund) {
+            return Err(FundError::InvalidPosition.into());
+        }
 
-// fn process_init_rent_account(
-//     program_id: &Pubkey,
-//     accounts: &[AccountInfo],
-// ) -> ProgramResult {
-//     let accounts_iter = &mut accounts.iter();
-//     let rent_account_info = next_account_info(accounts_iter)?;
-//     let system_program_info = next_account_info(accounts_iter)?;
-//     let god_father_info = next_account_info(accounts_iter)?;
+        if position.opened_by == *old_wallet_info.key {
+            position.opened_by = *new_wallet_info.key;
+            position.serialize(&mut &mut position_info.data.borrow_mut()[..])?;
+        }
+    }
 
-//     if !god_father_info.is_signer {
-//         return Err(FundError::MissingRequiredSignature.into());
-//     }
+    new_data.funds_joined += old_data.funds_joined;
+    new_data.pending_invites += old_data.pending_invites;
+    new_data.serialize(&mut &mut new_user_info.data.borrow_mut()[..])?;
 
-//     let (rent_pda, rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    // Old user account is closed, its rent goes back to the old wallet
+    let old_user_lamports = old_user_info.lamports();
+    **old_user_info.lamports.borrow_mut() = 0;
+    **old_wallet_info.lamports.borrow_mut() += old_user_lamports;
 
-//     if *rent_account_info.key != rent_pda {
-//         return Err(FundError::InvalidAccountData.into());
-//     }
+    let mut data = old_user_info.data.borrow_mut();
+    for byte in data.iter_mut() {
+        *byte = 0;
+    }
 
-//     let rent = Rent::get()?;
-//     let data_len = 0 as usize;
-//     let rent_exemption_amount = rent.minimum_balance(data_len);
+    msg!("[USER-ACTIVITY] {} {} Migrated {} fund entries to {}", old_wallet_info.key.to_string(), current_time, old_data.funds_joined, new_wallet_info.key.to_string());
 
-//     invoke_signed(
-//         &system_instruction::create_account(
-//             god_father_info.key,
-//             rent_account_info.key,
-//             rent_exemption_amount,
-//             data_len as u64,
-//             program_id
-//         ),
-//         &[god_father_info.clone(), rent_account_info.clone(), system_program_info.clone()],
-//         &[&[b"rent", &[rent_bump]]]
-//     )?;
+    Ok(())
+}
 
-//     Ok(())
-// }
+/// Hands the old wallet's votes in a wallet-keyed voters list over to the new wallet.
+fn migrate_voters(voters: &mut [(Pubkey, u8)], old_wallet: &Pubkey, new_wallet: &Pubkey) {
+    for voter in voters.iter_mut() {
+        if voter.0 == *old_wallet {
+            voter.0 = *new_wallet;
+        }
+    }
+}
 
-fn process_set_executing(
+fn process_delete_fund(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    fund_name: String,
     proposal_index: u8,
     vec_index: u16,
-    fund_name: String,
+    leaf_index: u8,
+    merkel_proof: Vec<[u8; 32]>,
 ) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
     let accounts_iter = &mut accounts.iter();
-    let peerfunds_wallet_info = next_account_info(accounts_iter)?;
-    let fund_account_info = next_account_info(accounts_iter)?;
-    let proposal_aggregator_info = next_account_info(accounts_iter)?;
+    let signer_info = next_account_info(accounts_iter)?; // any signer, the proposal carries the authority .......
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposal_aggregator_info = next_account_info(accounts_iter)?; // proposal aggregator .....................
+    let vault_account_info = next_account_info(accounts_iter)?; // vault account .................................
 
-    if !peerfunds_wallet_info.is_signer {
+    if !signer_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
     let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    let (aggregator_pda, _aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
+    let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_pda.as_ref()], program_id);
+    let (vault_pda, _vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
 
-    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != aggregator_pda {
+    if *fund_account_info.key != fund_pda || *proposal_aggregator_info.key != proposal_aggregator_pda || *vault_account_info.key != vault_pda {
         return Err(FundError::InvalidAccountData.into());
     }
 
-    msg!("Fund and Aggregator are correct");
-
-    let mut proposal_aggregator_data = ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
-
-    let (matched_index, proposal) = proposal_aggregator_data
-        .proposals
-        .iter()
-        .enumerate()
-        .find(|proposal| proposal.1.vec_index == vec_index)
-        .ok_or(FundError::InvalidAccountData)?;
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
 
-    if proposal.executed != 0 {
-        return Err(FundError::InvalidProposalAccount.into());
+    // Stakes and positions are unwound through proposals first, only tokens get distributed
+    let vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+    if !vault_data.stake_accounts.is_empty() {
+        return Err(FundError::InvalidStakeAccount.into());
     }
+    if !vault_data.positions.is_empty() {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    consume_approved_action(proposal_aggregator_info, vec_index, delete_fund_leaf(), leaf_index, &merkel_proof, voting_rules(&fund_data), current_time)?;
 
-    proposal_aggregator_data.proposals[matched_index].executed = 1;
+    // Creator paid for the fund's accounts, once the joiners have refunded them or without them
+    // the rent reserve takes it
+    fund_data.winding_down = true;
+    fund_data.rent_recipient = if fund_data.creator_exists && !fund_data.is_refunded {
+        fund_data.creator
+    } else {
+        Pubkey::find_program_address(&[b"rent"], program_id).0
+    };
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
-    proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
+    msg!("[FUND-ACTIVITY] {} {} {} Fund winding down", fund_account_info.key.to_string(), current_time, fund_name);
 
     Ok(())
 }
 
-fn process_execute_proposal(
+/// Pays the listed members of a winding down fund their share of every vault holding and
+/// removes them, together with pending invites, from the fund. Each member is given by wallet,
+/// user account and membership PDA followed by their payout accounts and governance token
+/// accounts, a pending invitee by wallet, user account, membership PDA and the wallet and
+/// membership PDA of whoever paid for the invite (the rent reserve and the gone inviter's
+/// membership PDA once the inviter left), who gets the invite's membership rent back.
+fn process_distribute_fund_assets(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     fund_name: String,
-    proposal_index: u8,
-    vec_index: u16,
-    swap_index: u8,
-    no_of_swaps: u8,
-    merkel_proof: Vec<[u8; 32]>,
-    amount: u64,
-    slippage: u16,
+    num_of_members: u8,
+    num_of_tokens: u8,
 ) -> ProgramResult {
     let current_time = Clock::get()?.unix_timestamp;
 
-    let account_iter = &mut accounts.iter();
-    let payer_info = next_account_info(account_iter)?; // payer ...............................................
-    let fund_account_info = next_account_info(account_iter)?; // fund account .................................
-    let vault_account_info = next_account_info(account_iter)?; // fund's vault account ........................
-    let proposal_aggregator_info = next_account_info(account_iter)?; // proposal account ......................
-    let token_program_2022_info = next_account_info(account_iter)?; // token program 2022 .....................
-    let token_program_std_info = next_account_info(account_iter)?; // token program 2020 ......................
-    let raydium_clmm_program = next_account_info(account_iter)?; // raydium clmm program ......................
-    let amm_config = next_account_info(account_iter)?; // Amm config account ..................................
-    let pool_state = next_account_info(account_iter)?; // Pool state account ..................................
-    let input_token_account = next_account_info(account_iter)?; // Fund's vault input token account .. Should exist
-    let output_token_account = next_account_info(account_iter)?; // fund's output token account .. might need to be created
-    let input_vault_ata = next_account_info(account_iter)?; // Raydium pool's input vault ata .................
-    let output_vault_ata = next_account_info(account_iter)?; // Raysium pool's output vault ata ...............
-    let observation_state = next_account_info(account_iter)?; // Observation state account ....................
-    let input_token_mint = next_account_info(account_iter)?; // Input token mint account ......................
-    let output_token_mint = next_account_info(account_iter)?; // Output token mint account ....................
-    let memo_program = next_account_info(account_iter)?; // memo program ......................................
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can distribute, pays for new token accounts ..
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // vault account .................................
+    let token_program_info = next_account_info(accounts_iter)?; // token program .................................
+    let system_program_info = next_account_info(accounts_iter)?; // system program ...............................
+    let ata_program_info = next_account_info(accounts_iter)?; // associated token program ........................
+    let rent_sysvar_info = next_account_info(accounts_iter)?; // rent sysvar .....................................
 
-    if !payer_info.is_signer {
+    if !cranker_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    const EXPECTED_PAYER: &str = "BA19YT7ryTxJY14J2CaY7xzAZWyR9afwDRUXgB7fMXEh";
-    let expected_payer_key = Pubkey::from_str(EXPECTED_PAYER).unwrap();
-    if *payer_info.key != expected_payer_key {
-        return Err(FundError::InvalidSigner.into());
+    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    if *fund_account_info.key != fund_pda {
+        return Err(FundError::InvalidAccountData.into());
     }
 
-    let (proposal_pda, _proposal_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[proposal_index], fund_account_info.key.as_ref()], program_id);
-    if *proposal_aggregator_info.key != proposal_pda {
-        msg!("Wrong proposal aggregator account");
-        return Err(FundError::InvalidProposalAccount.into());
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if !fund_data.winding_down {
+        msg!("Fund deletion hasn't been approved");
+        return Err(FundError::InvalidAccountData.into());
     }
 
-    // check fund account
-    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
-    msg!("Fund Name: {}", fund_name);
-    msg!("{} {}", fund_pda, fund_account_info.key);
-    if *fund_account_info.key != fund_pda {
-        msg!("Wrong Fund details");
-        return Err(FundError::InvalidFundDetails.into());
-    }
+    for _i in 0..num_of_members {
+        let wallet_info = next_account_info(accounts_iter)?;
+        let user_account_info = next_account_info(accounts_iter)?;
+        let membership_info = next_account_info(accounts_iter)?;
+        let (user_pda, _user_bump) = Pubkey::find_program_address(&[b"user", wallet_info.key.as_ref()], program_id);
+        if *user_account_info.key != user_pda {
+            return Err(FundError::InvalidAccountData.into());
+        }
 
-    let mut proposal_aggregator_data= ProposalAggregatorAccount::try_from_slice(&proposal_aggregator_info.data.borrow())?;
-    let proposal = proposal_aggregator_data
-        .proposals
-        .iter()
-        .find(|proposal| proposal.vec_index == vec_index)
-        .ok_or(FundError::InvalidAccountData)?;
+        let mut user_data = UserAccount::try_from_slice(&user_account_info.data.borrow())?;
+        let membership = load_membership(program_id, membership_info, fund_account_info.key, wallet_info.key)?
+            .ok_or(FundError::NotAFundMember)?;
+
+        if !membership.is_pending {
+            let payout = next_member_payout(
+                program_id,
+                accounts_iter,
+                cranker_info,
+                wallet_info,
+                fund_account_info,
+                vault_account_info,
+                [token_program_info, system_program_info, ata_program_info, rent_sysvar_info],
+                membership.stake_payouts,
+                num_of_tokens,
+                0,
+                0,
+                None
+            )?;
+            let governance = next_governance_accounts(accounts_iter, &fund_data, wallet_info.key)?;
 
+            let member_deposit = membership.deposit;
+            let total_deposit = fund_data.total_deposit;
+            if member_deposit > total_deposit {
+                return Err(FundError::InvalidAccountData.into());
+            }
 
-    // let is_executed = proposal.executed;
+            if total_deposit != 0 {
+                let withdraw_percent = (((member_deposit as u128) * 100_000_000)/(total_deposit as u128)) as u64;
+                msg!("Withdraw percent overall: {}", withdraw_percent);
 
-    // // if proposal is executed/not initiated then return
-    // if is_executed != 1 {
-    //     msg!("Proposal execution isn't initiated yet or it is already executed");
-    //     return Err(FundError::InvalidAccountData.into());
-    // }
+                if withdraw_percent != 0 {
+                    pay_out_member_share(program_id, &payout, withdraw_percent)?;
+                }
+            }
+
+            if let Some((governance_mint_info, governance_token_account_info, token_program_2022_info)) = governance {
+                burn_governance_tokens(
+                    token_program_2022_info,
+                    governance_mint_info,
+                    governance_token_account_info,
+                    fund_account_info,
+                    member_deposit,
+                    &[b"light-fund", fund_name.as_bytes(), &[fund_bump]]
+                )?;
+            }
+
+            if fund_data.creator_exists && fund_data.creator == *wallet_info.key {
+                fund_data.creator_exists = false;
+            }
 
-    let deadline = proposal.deadline / 1000;
+            // Membership goes back to the member, who paid for it when joining
+            fund_data.total_deposit -= member_deposit;
+            close_membership(membership_info, &membership, wallet_info, &mut fund_data);
+            user_data.funds_joined -= 1;
 
-    // if voting deadline hasn't reached yet, return
-    if current_time <= deadline {
-        msg!("The proposal is still under voting. {}, {}", current_time, deadline);
-        return Err(FundError::InvalidAccountData.into());
+            msg!("[FUND-ACTIVITY] {} {} {} Member paid out: {}", fund_account_info.key.to_string(), current_time, fund_name, wallet_info.key.to_string());
+        } else {
+            let inviter_wallet_info = next_account_info(accounts_iter)?;
+            let inviter_membership_info = next_account_info(accounts_iter)?;
+            if *inviter_wallet_info.key != rent_pda {
+                let inviter_correct = load_member(program_id, inviter_membership_info, fund_account_info.key, inviter_wallet_info.key)
+                    .is_ok_and(|inviter| inviter.member_index == membership.inviter_index);
+
+                if !inviter_correct {
+                    return Err(FundError::InvalidInviter.into());
+                }
+            } else {
+                check_inviter_gone(program_id, inviter_membership_info, fund_account_info.key, &membership)?;
+            }
+
+            // Invite was paid for by the inviter, or the rent reserve once they're gone
+            close_membership(membership_info, &membership, inviter_wallet_info, &mut fund_data);
+            user_data.pending_invites -= 1;
+        }
+
+        user_data.serialize(&mut &mut user_account_info.data.borrow_mut()[..])?;
     }
 
-    // --- Merkle Root Verification Start ---
+    fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
-    // let mut swap_hasher = Sha256::new();
+    Ok(())
+}
 
-    // swap_hasher.update(input_token_mint.key.as_ref());
-    // swap_hasher.update(output_token_mint.key.as_ref());
-    // swap_hasher.update(&amount.to_le_bytes());
-    // swap_hasher.update(&slippage.to_le_bytes());
+/// Closes a fully distributed fund: the vault's token accounts (burning any dust), the proposal
+/// aggregators with the proposals still in them and their voter bitmaps, the fee account, the
+/// holdings registry, the transfer hook's account metas, the vault and the fund itself. Rent of
+/// an aggregator opened by a proposer goes back to them, everything else to the fund's rent
+/// recipient. Each aggregator comes with its opener's wallet and, per proposal left in it, its
+/// voter bitmap and proposer's wallet. The increment proposal, then the listed join and remove
+/// member proposals, withdrawal requests and conditional orders are closed too, each followed
+/// by the wallet that paid for it and, for proposals, by their voters' wallets.
+#[allow(clippy::too_many_arguments)]
+fn process_close_fund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String,
+    num_of_tokens: u8,
+    num_of_join_proposals: u8,
+    num_of_remove_proposals: u8,
+    num_of_requests: u8,
+    num_of_orders: u8,
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
 
-    // let mut calculated_merkel_root: [u8; 32] = swap_hasher.finalize().into();
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can close a distributed fund ...............
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let vault_account_info = next_account_info(accounts_iter)?; // vault account .................................
+    let holdings_info = next_account_info(accounts_iter)?; // vault holdings registry ............................
+    let fee_account_info = next_account_info(accounts_iter)?; // fee account .....................................
+    let rent_recipient_info = next_account_info(accounts_iter)?; // fund's rent recipient ........................
+    let token_program_info = next_account_info(accounts_iter)?; // token program .................................
+    let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 .......................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // peerfund's rent reserve ........................
+    let increment_proposal_info = next_account_info(accounts_iter)?; // increment proposal account ...............
+
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
 
-    let mut calculated_merkel_root = hashv(&[
-        input_token_mint.key.as_ref(),
-        output_token_mint.key.as_ref(),
-        &amount.to_le_bytes(),
-        &slippage.to_le_bytes(),
-    ]).to_bytes();
+    if *token_program_info.key != spl_token::id() || *token_program_2022_info.key != spl_token_2022::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    // let mut merkle_hasher = Sha256::new();
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
+    let (fee_pda, _fee_bump) = Pubkey::find_program_address(&[b"fees", fund_pda.as_ref()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+    let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_pda.as_ref()], program_id);
+
+    if *fund_account_info.key != fund_pda || *vault_account_info.key != vault_pda || *fee_account_info.key != fee_pda ||
+       *rent_reserve_info.key != rent_pda || *increment_proposal_info.key != increment_proposal_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    if !fund_data.winding_down || fund_data.member_count != 0 {
+        msg!("Fund has to be wound down and fully distributed first");
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    if *rent_recipient_info.key != fund_data.rent_recipient {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let vault_data = VaultAccount::try_from_slice(&vault_account_info.data.borrow())?;
+    if !vault_data.stake_accounts.is_empty() || !vault_data.positions.is_empty() {
+        return Err(FundError::InvalidStakeAccount.into());
+    }
+
+    let vault_seeds: &[&[u8]] = &[b"vault", fund_account_info.key.as_ref(), &[vault_bump]];
+    let holdings_data = load_vault_holdings(program_id, holdings_info, &fund_pda)?;
+    if num_of_tokens as usize != holdings_data.holdings.len() {
+        msg!("All of the vault's {} token accounts must be provided", holdings_data.holdings.len());
+        return Err(FundError::InvalidMints.into());
+    }
 
-    let mut index = swap_index as usize;
+    for holding in holdings_data.holdings.iter() {
+        let vault_ata_info = next_account_info(accounts_iter)?;
+        let mint_account_info = next_account_info(accounts_iter)?;
+        if *vault_ata_info.key != holding.ata || *mint_account_info.key != holding.mint {
+            return Err(FundError::InvalidMints.into());
+        }
 
-    for node in merkel_proof.iter() {
-        calculated_merkel_root = if index % 2 == 0 {
-            hashv(&[&calculated_merkel_root, node]).to_bytes()
+        let holding_token_program = if holding.token_program == spl_token_2022::id() {
+            token_program_2022_info
         } else {
-            hashv(&[node, &calculated_merkel_root]).to_bytes()
+            token_program_info
         };
-        index /= 2;
+
+        // Rounding leaves dust behind, which can't be split any further
+        let dust = token_balance(vault_ata_info)?;
+        if dust != 0 && *mint_account_info.key != spl_token::native_mint::id() {
+            invoke_signed(
+                &spl_token_2022::instruction::burn_checked(
+                    holding_token_program.key,
+                    vault_ata_info.key,
+                    mint_account_info.key,
+                    vault_account_info.key,
+                    &[],
+                    dust,
+                    supported_mint_decimals(mint_account_info)?
+                )?,
+                &[vault_ata_info.clone(), mint_account_info.clone(), vault_account_info.clone(), holding_token_program.clone()],
+                &[vault_seeds]
+            )?;
+        }
+
+        invoke_signed(
+            &spl_token_2022::instruction::close_account(
+                holding_token_program.key,
+                vault_ata_info.key,
+                rent_recipient_info.key,
+                vault_account_info.key,
+                &[]
+            )?,
+            &[vault_ata_info.clone(), rent_recipient_info.clone(), vault_account_info.clone(), holding_token_program.clone()],
+            &[vault_seeds]
+        )?;
     }
 
-    // for node in merkel_proof.iter() {
-    //     if index % 2 == 0 {
-    //         // Current hash is left child
-    //         merkle_hasher.update(&calculated_merkel_root);
-    //         merkle_hasher.update(&node[..]);
-    //     } else {
-    //         // Current hash is right child
-    //         merkle_hasher.update(&node[..]);
-    //         merkle_hasher.update(&calculated_merkel_root);
-    //     }
-    //     calculated_merkel_root = merkle_hasher.finalize().into();
-    //     merkle_hasher = Sha256::new();
-    //     index /= 2;
-    // }
+    let mut closed_accounts: Vec<(&AccountInfo, &AccountInfo)> = vec![];
+    for index in 0..=fund_data.current_proposal_index {
+        let proposal_aggregator_info = next_account_info(accounts_iter)?;
+        let (proposal_aggregator_pda, _proposal_aggregator_bump) = Pubkey::find_program_address(&[b"proposal-aggregator", &[index], fund_pda.as_ref()], program_id);
+        if *proposal_aggregator_info.key != proposal_aggregator_pda {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
 
-    let merkel_root = proposal.merkel_root;
+        // Every aggregator after the first was opened by a proposer, the first came with the fund
+        let opener_info = next_account_info(accounts_iter)?;
+        let opener = load_aggregator(&proposal_aggregator_info.data.borrow())?.opener;
+        let opener = if opener == Pubkey::default() { fund_data.rent_recipient } else { opener };
+        if *opener_info.key != opener {
+            return Err(FundError::InvalidProposerInfo.into());
+        }
 
-    if calculated_merkel_root != merkel_root {
-        msg!("The merkel root calculated don't match with the one in the proposal.");
-        return Err(FundError::InvalidSwapDetails.into());
-    }
+        // Proposals still in it are released like closed ones, voter bitmaps included
+        for vec_index in 0..proposal_slots(&proposal_aggregator_info.data.borrow()) {
+            if vec_index >= proposal_slots(&proposal_aggregator_info.data.borrow()) {
+                break;
+            }
 
-    // --- Merkle Root Verification Done ---
+            let proposer = load_proposal(&proposal_aggregator_info.data.borrow(), vec_index as u16)?.proposer;
+            if proposer == Pubkey::default() {
+                continue;
+            }
 
-    let vote_yes = proposal.votes_yes;
-    let vote_no = proposal.votes_no;
+            let voters_info = next_account_info(accounts_iter)?;
+            let proposer_account_info = next_account_info(accounts_iter)?;
+            let (voters_pda, _voters_bump) = Pubkey::find_program_address(&[b"proposal-voters", fund_pda.as_ref(), &[index], &(vec_index as u16).to_le_bytes()], program_id);
+            if *voters_info.key != voters_pda {
+                return Err(FundError::InvalidProposalAccount.into());
+            }
+            if *proposer_account_info.key != proposer {
+                return Err(FundError::InvalidProposerInfo.into());
+            }
 
-    let fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-    let strength = fund_data.members.len();
+            release_proposal(proposal_aggregator_info, voters_info, proposer_account_info, rent_reserve_info, vec_index as u16)?;
+        }
 
-    // if quorum not reached, error
-    if (vote_yes + vote_no) < (strength as u64) * 1 / 2 {
-        msg!("Quorum not reached");
-        return Err(FundError::InvalidInstruction.into());
+        closed_accounts.push((proposal_aggregator_info, opener_info));
     }
 
-    // if proposal not in majority, error
-    if vote_yes < vote_no {
-        msg!("Not enough votes favouring the trades");
-        return Err(FundError::InvalidInstruction.into());
+    if fund_data.governance_mint != Pubkey::default() {
+        let extra_account_metas_info = next_account_info(accounts_iter)?;
+        let (extra_account_metas_pda, _extra_account_metas_bump) = get_extra_account_metas_address_and_bump_seed(&fund_data.governance_mint, program_id);
+        if *extra_account_metas_info.key != extra_account_metas_pda {
+            return Err(FundError::InvalidAccountData.into());
+        }
+        closed_accounts.push((extra_account_metas_info, rent_recipient_info));
     }
 
-    // verify vault account
-    let (vault_pda, vault_bump) = Pubkey::find_program_address(&[b"vault", fund_pda.as_ref()], program_id);
-    if *vault_account_info.key != vault_pda {
-        msg!("Wrong vault account");
-        return Err(FundError::InvaildVaultAccount.into());
+    if !increment_proposal_info.data_is_empty() {
+        let proposer_account_info = next_account_info(accounts_iter)?;
+        let proposal_data = IncrementProposalAccount::try_from_slice(&increment_proposal_info.data.borrow())?;
+        if proposal_data.proposer != *proposer_account_info.key {
+            return Err(FundError::InvalidProposerInfo.into());
+        }
+        close_increment_proposal(accounts_iter, increment_proposal_info, proposer_account_info, rent_reserve_info, &proposal_data)?;
     }
 
-    // verify the vault's token accounts
-    let input_vault_token_account = spl_associated_token_account::get_associated_token_address(
-        &vault_pda,
-        input_token_mint.key
-    );
-    if *input_token_account.key != input_vault_token_account || input_token_account.data_is_empty() {
-        return Err(FundError::InvalidTokenAccount.into());
+    // Joiners get their escrow back
+    for _i in 0..num_of_join_proposals {
+        let join_proposal_info = next_account_info(accounts_iter)?;
+        let joiner_wallet_info = next_account_info(accounts_iter)?;
+        let join_proposal = load_join_proposal(program_id, join_proposal_info, &fund_pda)?;
+        if join_proposal.joiner != *joiner_wallet_info.key {
+            return Err(FundError::InvalidAccountData.into());
+        }
+        close_voted_proposal(accounts_iter, join_proposal_info, joiner_wallet_info, &join_proposal.voters)?;
     }
 
-    let output_vault_token_account = spl_associated_token_account::get_associated_token_address(
-        &vault_pda,
-        output_token_mint.key
-    );
-    if *output_token_account.key != output_vault_token_account {
-        return Err(FundError::InvalidTokenAccount.into());
+    for _i in 0..num_of_remove_proposals {
+        let remove_proposal_info = next_account_info(accounts_iter)?;
+        let proposer_account_info = next_account_info(accounts_iter)?;
+        if *remove_proposal_info.owner != *program_id {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
+
+        let proposal_data = RemoveMemberProposal::try_from_slice(&remove_proposal_info.data.borrow())?;
+        let (remove_proposal_pda, _remove_proposal_bump) = Pubkey::find_program_address(&[b"remove-member", fund_pda.as_ref(), proposal_data.target.as_ref()], program_id);
+        if *remove_proposal_info.key != remove_proposal_pda {
+            return Err(FundError::InvalidProposalAccount.into());
+        }
+        if proposal_data.proposer != *proposer_account_info.key {
+            return Err(FundError::InvalidProposerInfo.into());
+        }
+        close_voted_proposal(accounts_iter, remove_proposal_info, proposer_account_info, proposal_data.voters.get(1..).unwrap_or_default())?;
     }
 
-    let discriminator: &[u8] = &[0x2b, 0x04, 0xed, 0x0b, 0x1a, 0xc9, 0x1e, 0x62];
-    let min_amount_out = amount
-        .checked_mul(10000u64 - (slippage as u64))
-        .unwrap()
-        / 10000u64;
-    let other_amount_threshold = min_amount_out as u64;
-    let sqrt_price_limit_x64 = 0 as u128;
-    let is_base_input = true;
-
-    let mut args_buf = Vec::with_capacity(33);
-    args_buf.write_all(&amount.to_le_bytes()).unwrap();
-    args_buf.write_all(&other_amount_threshold.to_le_bytes()).unwrap();
-    args_buf.write_all(&(sqrt_price_limit_x64 as u64).to_le_bytes()).unwrap();
-    args_buf.write_all(&((sqrt_price_limit_x64 >> 64) as u64).to_le_bytes()).unwrap();
-    args_buf.write_all(&[is_base_input as u8]).unwrap();
-
-    let mut instruction_data = discriminator.to_vec();
-    instruction_data.extend(args_buf);
-
-    let mut accounts_needed: Vec<AccountMeta> = vec![
-        AccountMeta::new(vault_account_info.key.clone(), true),
-        AccountMeta::new_readonly(amm_config.key.clone(), false),
-        AccountMeta::new(pool_state.key.clone(), false),
-        AccountMeta::new(input_token_account.key.clone(), false),
-        AccountMeta::new(output_token_account.key.clone(), false),
-        AccountMeta::new(input_vault_ata.key.clone(), false),
-        AccountMeta::new(output_vault_ata.key.clone(), false),
-        AccountMeta::new(observation_state.key.clone(), false),
-        AccountMeta::new_readonly(token_program_std_info.key.clone(), false),
-        AccountMeta::new_readonly(token_program_2022_info.key.clone(), false),
-        AccountMeta::new_readonly(memo_program.key.clone(), false),
-        AccountMeta::new(input_token_mint.key.clone(), false),
-        AccountMeta::new(output_token_mint.key.clone(), false),
-    ];
+    for _i in 0..num_of_requests {
+        let request_account_info = next_account_info(accounts_iter)?;
+        let member_wallet_info = next_account_info(accounts_iter)?;
+        let (request_pda, _request_bump) = Pubkey::find_program_address(&[b"withdrawal-request", fund_pda.as_ref(), member_wallet_info.key.as_ref()], program_id);
+        if *request_account_info.key != request_pda || request_account_info.data_is_empty() {
+            return Err(FundError::InvalidWithdrawalRequest.into());
+        }
+        closed_accounts.push((request_account_info, member_wallet_info));
+    }
 
-    let mut account_infos = vec![
-        vault_account_info.clone(),
-        amm_config.clone(),
-        pool_state.clone(),
-        input_token_account.clone(),
-        output_token_account.clone(),
-        input_vault_ata.clone(),
-        output_vault_ata.clone(),
-        observation_state.clone(),
-        token_program_std_info.clone(),
-        token_program_2022_info.clone(),
-        memo_program.clone(),
-        input_token_mint.clone(),
-        output_token_mint.clone(),
-    ];
+    for _i in 0..num_of_orders {
+        let order_account_info = next_account_info(accounts_iter)?;
+        let creator_wallet_info = next_account_info(accounts_iter)?;
+        if *order_account_info.owner != *program_id {
+            return Err(FundError::InvalidAccountData.into());
+        }
 
-    for acc in account_iter {
-        accounts_needed.push(AccountMeta::new(acc.key.clone(), false));
-        account_infos.push(acc.clone());
+        let order_data = ConditionalOrder::try_from_slice(&order_account_info.data.borrow())?;
+        let (order_pda, _order_bump) = Pubkey::find_program_address(
+            &[
+                b"conditional-order",
+                fund_pda.as_ref(),
+                &[order_data.proposal_index],
+                &order_data.vec_index.to_le_bytes(),
+                &[order_data.order_index]
+            ],
+            program_id
+        );
+        if *order_account_info.key != order_pda || order_data.creator != *creator_wallet_info.key {
+            return Err(FundError::InvalidAccountData.into());
+        }
+        closed_accounts.push((order_account_info, creator_wallet_info));
     }
 
-    let swap_cpi_instruction = Instruction {
-        program_id: *raydium_clmm_program.key,
-        accounts: accounts_needed,
-        data: instruction_data
-    };
-
-    invoke_signed(&swap_cpi_instruction, &account_infos, &[&[b"vault", fund_account_info.key.as_ref(), &[vault_bump]]])?;
+    closed_accounts.push((fee_account_info, rent_recipient_info));
+    closed_accounts.push((holdings_info, rent_recipient_info));
+    closed_accounts.push((vault_account_info, rent_recipient_info));
+    closed_accounts.push((fund_account_info, rent_recipient_info));
 
-    let (matched_index, _proposal) = proposal_aggregator_data
-        .proposals
-        .iter()
-        .enumerate()
-        .find(|proposal| proposal.1.vec_index == vec_index)
-        .ok_or(FundError::InvalidProposalAccount)?;
+    for (account_info, recipient_info) in closed_accounts.iter() {
+        let lamports = account_info.lamports();
+        **account_info.lamports.borrow_mut() = 0;
+        **recipient_info.lamports.borrow_mut() += lamports;
 
-    if swap_index + 1 == no_of_swaps {
-        proposal_aggregator_data.proposals[matched_index].executed = 2;
+        let mut data = account_info.data.borrow_mut();
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
     }
 
-    let mut swap_status = proposal_aggregator_data.proposals[matched_index].swaps_status;
-    swap_status = swap_status | (1 << swap_index);
-    proposal_aggregator_data.proposals[matched_index].swaps_status = swap_status;
-
-    proposal_aggregator_data.serialize(&mut &mut proposal_aggregator_info.data.borrow_mut()[..])?;
-
-    msg!("[FUND-ACTIVITY] {} {} {} Proposal executed: ({}, {})", fund_account_info.key.to_string(), current_time, fund_name, proposal_index, vec_index);
+    msg!("[FUND-ACTIVITY] {} {} {} Fund deleted", fund_account_info.key.to_string(), current_time, fund_name);
 
     Ok(())
 }
@@ -3334,6 +7684,7 @@ fn process_init_increment_proposal(
     fund_name: String,
     new_size: u32,
     refund_type: u8,
+    deadline: i64,
 ) -> ProgramResult {
     let current_time = Clock::get()?.unix_timestamp;
 
@@ -3342,133 +7693,115 @@ fn process_init_increment_proposal(
     let increment_proposal_account_info = next_account_info(accounts_iter)?; // proposal account ................
     let system_program_info = next_account_info(accounts_iter)?; // system program ..............................
     let fund_account_info = next_account_info(accounts_iter)?; // fund account ..................................
-    let governance_mint_info = next_account_info(accounts_iter)?; // governance mint ............................
-    let proposer_token_account_info = next_account_info(accounts_iter)?; // token account .......................
-    let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 ......................
-    let rent_reserve_info = next_account_info(accounts_iter)?; // rent reserve ..................................
+    let proposer_membership_info = next_account_info(accounts_iter)?; // proposer's membership PDA ..............
 
     if !proposer_account_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
-    let (increment_proposal_pda, increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
-    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
-
-    if *fund_account_info.key != fund_pda || *increment_proposal_account_info.key != increment_proposal_pda || *rent_reserve_info.key != rent_pda {
-        return Err(FundError::InvalidAccountData.into());
+    if refund_type > 1 {
+        return Err(FundError::InvalidInstruction.into());
     }
 
-    let token_account = get_associated_token_address_with_program_id(
-        proposer_account_info.key,
-        governance_mint_info.key,
-        token_program_2022_info.key
-    );
-
-    if token_account != *proposer_token_account_info.key {
-        return Err(FundError::InvalidTokenAccount.into());
+    if deadline <= current_time {
+        return Err(FundError::DeadlineReached.into());
     }
 
-    let token_account_data = proposer_token_account_info.try_borrow_data()?;
-    let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
-    let base_token_account = token_account.base;
-    let balance = base_token_account.amount;
-    drop(token_account_data);
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (increment_proposal_pda, increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
 
-    if balance == 0 {
-        return Err(FundError::NoVotingPower.into());
+    if *fund_account_info.key != fund_pda || *increment_proposal_account_info.key != increment_proposal_pda {
+        return Err(FundError::InvalidAccountData.into());
     }
 
     if !increment_proposal_account_info.data_is_empty() {
         return Err(FundError::IncrementProposalExists.into());
     }
 
-    let total_deposit: u64 = {
-        let fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-        if new_size <= fund_data.expected_members {
-            return Err(FundError::InvalidNewSize.into());
-        }
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut proposer_member = load_member(program_id, proposer_membership_info, fund_account_info.key, proposer_account_info.key)?;
 
-        if fund_data.expected_members as usize > fund_data.members.len() && !fund_data.is_refunded {
-            return Err(FundError::InvalidInstruction.into());
-        }
+    if !has_role(&proposer_member, ROLE_PROPOSER) || !has_role(&proposer_member, ROLE_VOTER) {
+        return Err(FundError::MissingPermission.into());
+    }
 
-        fund_data.total_deposit
-    };
+    if fund_data.winding_down {
+        return Err(FundError::FundWindingDown.into());
+    }
 
+    if new_size <= fund_data.max_members {
+        return Err(FundError::InvalidNewSize.into());
+    }
 
-    if 2*balance >= total_deposit {
+    // Creation costs are shared among the expected joiners, the size is fixed until they are in
+    if fund_data.expected_members > fund_data.member_count && !fund_data.is_refunded {
+        return Err(FundError::InvalidInstruction.into());
+    }
 
-        if refund_type == 0 {
-            invoke(
-                &system_instruction::transfer(
-                    proposer_account_info.key,
-                    rent_reserve_info.key,
-                    1_510_000
-                ),
-                &[proposer_account_info.clone(), rent_reserve_info.clone(), system_program_info.clone()]
-            )?;
-        }
-        let mut fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-        fund_data.expected_members = new_size;
+    let weight = vote_weight(&fund_data, &proposer_member);
+    if weight == 0 {
+        return Err(FundError::NoVotingPower.into());
+    }
+
+    // The proposer alone can carry the vote, no proposal account is needed then
+    if votes_decisive(weight, voting_rules(&fund_data)) {
+        fund_data.max_members = new_size;
         fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
 
         msg!("[FUND-ACTIVITY] {} {} Fund's max size increased to {}", fund_account_info.key.to_string(), current_time, new_size);
-    } else {
-        let rent = Rent::get()?;
-        let proposal_space = 90 as usize;
-        let proposal_rent = rent.minimum_balance(proposal_space);
+        return Ok(());
+    }
 
-        invoke_signed(
-            &system_instruction::create_account(
-                proposer_account_info.key,
-                increment_proposal_account_info.key,
-                proposal_rent,
-                proposal_space as u64,
-                program_id
-            ),
-            &[proposer_account_info.clone(), increment_proposal_account_info.clone(), system_program_info.clone()],
-            &[&[b"increment-proposal-account", fund_account_info.key.as_ref(), &[increment_proposal_bump]]]
-        )?;
+    lock_voting_shares(&fund_data, proposer_membership_info, &mut proposer_member, deadline)?;
 
-        let voters: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1 as u8)];
-        let proposal_data = IncrementProposalAccount {
-            proposer: *proposer_account_info.key,
-            new_size,
-            refund_type,
-            votes_yes: balance,
-            votes_no: 0 as u64,
-            voters
-        };
+    let rent = Rent::get()?;
+    let proposal_space = 98;
+    let proposal_rent = rent.minimum_balance(proposal_space);
 
-        proposal_data.serialize(&mut &mut increment_proposal_account_info.data.borrow_mut()[..])?;
+    invoke_signed(
+        &system_instruction::create_account(
+            proposer_account_info.key,
+            increment_proposal_account_info.key,
+            proposal_rent,
+            proposal_space as u64,
+            program_id
+        ),
+        &[proposer_account_info.clone(), increment_proposal_account_info.clone(), system_program_info.clone()],
+        &[&[b"increment-proposal-account", fund_account_info.key.as_ref(), &[increment_proposal_bump]]]
+    )?;
 
-        msg!("[FUND-ACTIVITY] {} {} Increment Proposal created with new size: {}", fund_account_info.key.to_string(), current_time, new_size);
-    }
+    let voters: Vec<(Pubkey, u8)> = vec![(*proposer_account_info.key, 1)];
+    let proposal_data = IncrementProposalAccount {
+        proposer: *proposer_account_info.key,
+        new_size,
+        refund_type,
+        votes_yes: weight,
+        votes_no: 0,
+        deadline,
+        voters
+    };
 
-    if refund_type == 0 {
-        invoke_signed(
-            &spl_token_2022::instruction::mint_to(
-                token_program_2022_info.key,
-                governance_mint_info.key,
-                proposer_token_account_info.key,
-                fund_account_info.key,
-                &[],
-                1_510_000,
-            )?,
-            &[
-                governance_mint_info.clone(),
-                proposer_token_account_info.clone(),
-                fund_account_info.clone(),
-                token_program_2022_info.clone(),
-            ],
-            &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]],
-        )?;
-    }
+    proposal_data.serialize(&mut &mut increment_proposal_account_info.data.borrow_mut()[..])?;
+
+    msg!("[FUND-ACTIVITY] {} {} Increment Proposal created with new size: {}", fund_account_info.key.to_string(), current_time, new_size);
 
     Ok(())
 }
 
+/// Closes the increment proposal. Voters get back what their votes cost, the wallets following
+/// the fixed accounts, and the rest goes to the proposer or, when they gave it up, to the rent
+/// reserve.
+fn close_increment_proposal<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    increment_proposal_account_info: &AccountInfo<'a>,
+    proposer_account_info: &AccountInfo<'a>,
+    rent_reserve_info: &AccountInfo<'a>,
+    proposal_data: &IncrementProposalAccount,
+) -> ProgramResult {
+    let recipient_info = if proposal_data.refund_type == 1 { proposer_account_info } else { rent_reserve_info };
+    close_voted_proposal(accounts_iter, increment_proposal_account_info, recipient_info, proposal_data.voters.get(1..).unwrap_or_default())
+}
+
 fn process_toggle_refund_type(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -3479,15 +7812,16 @@ fn process_toggle_refund_type(
     let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet ........................
     let fund_account_info = next_account_info(accounts_iter)?; // fund account ...............................
     let increment_proposal_account_info = next_account_info(accounts_iter)?; // proposal account .............
-    let token_program_2022_info = next_account_info(accounts_iter)?;
-    let governance_mint_info = next_account_info(accounts_iter)?;
-    let proposer_token_account_info = next_account_info(accounts_iter)?;
 
     if !proposer_account_info.is_signer {
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+    if refund_type > 1 {
+        return Err(FundError::InvalidInstruction.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
     let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
 
     if *fund_account_info.key != fund_pda || *increment_proposal_account_info.key != increment_proposal_pda {
@@ -3499,33 +7833,6 @@ fn process_toggle_refund_type(
         return Err(FundError::InvalidProposerInfo.into());
     }
 
-    if proposal_data.refund_type == 0 && refund_type == 1 {
-        invoke(
-            &spl_token_2022::instruction::burn(
-                &spl_token_2022::id(),
-                proposer_token_account_info.key,
-                governance_mint_info.key,
-                proposer_account_info.key,
-                &[], 1_510_000
-            )?,
-            &[proposer_token_account_info.clone(), governance_mint_info.clone(), proposer_account_info.clone(), token_program_2022_info.clone()],
-            // &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
-        )?;
-    } else if proposal_data.refund_type == 1 && refund_type == 0 {
-        invoke_signed(
-            &spl_token_2022::instruction::mint_to(
-                &spl_token_2022::id(),
-                governance_mint_info.key,
-                proposer_token_account_info.key,
-                fund_account_info.key,
-                &[],
-                1_510_000
-            )?,
-            &[governance_mint_info.clone(), proposer_token_account_info.clone(), fund_account_info.clone(), token_program_2022_info.clone()],
-            &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]]
-        )?;
-    }
-
     proposal_data.refund_type = refund_type;
     proposal_data.serialize(&mut &mut increment_proposal_account_info.data.borrow_mut()[..])?;
 
@@ -3545,9 +7852,7 @@ fn process_vote_increment_proposal(
     let increment_proposal_account_info = next_account_info(accounts_iter)?; // proposal account ..................
     let system_program_info = next_account_info(accounts_iter)?; // system program ................................
     let fund_account_info = next_account_info(accounts_iter)?; // fund account ....................................
-    let governance_mint_info = next_account_info(accounts_iter)?; // governance mint ..............................
-    let voter_token_account_info = next_account_info(accounts_iter)?; // voter token account ......................
-    let token_program_2022_info = next_account_info(accounts_iter)?; // token program 2022 ........................
+    let voter_membership_info = next_account_info(accounts_iter)?; // voter's membership PDA .......................
     let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet .............................
     let rent_reserve_info = next_account_info(accounts_iter)?; // rent reserve ....................................
 
@@ -3555,7 +7860,7 @@ fn process_vote_increment_proposal(
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    let (fund_pda, fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
     let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
     let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
 
@@ -3563,26 +7868,15 @@ fn process_vote_increment_proposal(
         return Err(FundError::InvalidAccountData.into());
     }
 
-    let token_account = get_associated_token_address_with_program_id(
-        voter_account_info.key,
-        governance_mint_info.key,
-        token_program_2022_info.key
-    );
-
-    if token_account != *voter_token_account_info.key {
-        return Err(FundError::InvalidTokenAccount.into());
-    }
+    let mut fund_data = LightFundAccount::try_from_slice(&fund_account_info.data.borrow())?;
+    let mut voter_member = load_member(program_id, voter_membership_info, fund_account_info.key, voter_account_info.key)?;
 
-    if voter_token_account_info.data_is_empty() {
-        return Err(FundError::NoVotingPower.into());
+    if !has_role(&voter_member, ROLE_VOTER) {
+        return Err(FundError::MissingPermission.into());
     }
 
-    let token_account_data = voter_token_account_info.try_borrow_data()?;
-    let token_account = spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(&token_account_data)?;
-    let base_token_account = token_account.base;
-    let balance = base_token_account.amount;
-
-    if balance == 0 {
+    let weight = vote_weight(&fund_data, &voter_member);
+    if weight == 0 {
         return Err(FundError::NoVotingPower.into());
     }
 
@@ -3600,59 +7894,28 @@ fn process_vote_increment_proposal(
         return Err(FundError::InvalidProposerInfo.into());
     }
 
+    if proposal_data.deadline < current_time {
+        return Err(FundError::VotingCeased.into());
+    }
+
     if vote == 1 {
-        proposal_data.votes_yes += balance;
+        proposal_data.votes_yes += weight;
     } else {
-        proposal_data.votes_no += balance;
+        proposal_data.votes_no += weight;
     }
 
-    let mut fund_data = FundAccount::try_from_slice(&fund_account_info.data.borrow())?;
-
-    if 2 * proposal_data.votes_yes >= fund_data.total_deposit {
-        fund_data.expected_members = proposal_data.new_size;
+    if votes_decisive(proposal_data.votes_yes, voting_rules(&fund_data)) {
+        fund_data.max_members = proposal_data.new_size;
         fund_data.serialize(&mut &mut fund_account_info.data.borrow_mut()[..])?;
-        let lamports = increment_proposal_account_info.lamports();
-        **increment_proposal_account_info.lamports.borrow_mut() = 0;
-        if proposal_data.refund_type == 1 {
-            **proposer_account_info.lamports.borrow_mut() += 1_510_000;
-            if lamports - 1_510_000 > 0 {
-                **rent_reserve_info.lamports.borrow_mut() += lamports - 1_510_000;
-            }
-        } else {
-            let proposer_token_account_info = next_account_info(accounts_iter)?;
-            let token_account = get_associated_token_address_with_program_id(
-                proposer_account_info.key,
-                governance_mint_info.key,
-                token_program_2022_info.key
-            );
-            if token_account != *proposer_token_account_info.key {
-                return Err(FundError::InvalidTokenAccount.into());
-            }
-            **rent_reserve_info.lamports.borrow_mut() += lamports;
-            invoke_signed(
-                &spl_token_2022::instruction::mint_to(
-                    token_program_2022_info.key,
-                    governance_mint_info.key,
-                    proposer_token_account_info.key,
-                    fund_account_info.key,
-                    &[],
-                    1_510_000,
-                )?,
-                &[
-                    governance_mint_info.clone(),
-                    proposer_token_account_info.clone(),
-                    fund_account_info.clone(),
-                    token_program_2022_info.clone(),
-                ],
-                &[&[b"fund", fund_name.as_bytes(), &[fund_bump]]],
-            )?;
-        }
+
+        close_increment_proposal(accounts_iter, increment_proposal_account_info, proposer_account_info, rent_reserve_info, &proposal_data)?;
 
         msg!("[FUND-ACTIVITY] {} {} Fund's max size increased to {}", fund_account_info.key.to_string(), current_time, proposal_data.new_size);
     } else {
+        lock_voting_shares(&fund_data, voter_membership_info, &mut voter_member, proposal_data.deadline)?;
         proposal_data.voters.push((*voter_account_info.key, vote));
         let current_proposal_size = increment_proposal_account_info.data_len();
-        let new_proposal_size = current_proposal_size + 33;
+        let new_proposal_size = current_proposal_size + VOTE_SPACE;
         let current_proposal_rent = increment_proposal_account_info.lamports();
         let new_proposal_rent = Rent::get()?.minimum_balance(new_proposal_size);
 
@@ -3694,7 +7957,7 @@ fn process_cancel_increment_proposal(
         return Err(FundError::MissingRequiredSignature.into());
     }
 
-    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"fund", fund_name.as_bytes()], program_id);
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
     let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
     let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
 
@@ -3707,18 +7970,57 @@ fn process_cancel_increment_proposal(
         return Err(FundError::InvalidProposerInfo.into());
     }
 
-    let lamports = increment_proposal_account_info.lamports();
-    **increment_proposal_account_info.lamports.borrow_mut() = 0;
-    if proposal_data.refund_type == 1 {
-        **proposer_account_info.lamports.borrow_mut() += 1_510_000;
-        if lamports - 1_510_000 > 0 {
-            **rent_reserve_info.lamports.borrow_mut() += lamports - 1_510_000;
-        }
-    } else {
-        **rent_reserve_info.lamports.borrow_mut() += lamports;
-    }
+    close_increment_proposal(accounts_iter, increment_proposal_account_info, proposer_account_info, rent_reserve_info, &proposal_data)?;
 
     msg!("[FUND-ACTIVITY] {} {} Increment proposal for new size {} deleted", fund_account_info.key.to_string(), current_time, proposal_data.new_size);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Closes an increment proposal nobody is going to see through: its voting ended, or the proposer
+/// is no longer a member to cancel it. Anyone can close it, the voters' wallets following the
+/// fixed accounts in voting order after the proposer.
+fn process_close_increment_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fund_name: String
+) -> ProgramResult {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let accounts_iter = &mut accounts.iter();
+    let cranker_info = next_account_info(accounts_iter)?; // anyone can close ....................................
+    let increment_proposal_account_info = next_account_info(accounts_iter)?; // proposal account .................
+    let fund_account_info = next_account_info(accounts_iter)?; // fund account ...................................
+    let proposer_account_info = next_account_info(accounts_iter)?; // proposer wallet ............................
+    let proposer_membership_info = next_account_info(accounts_iter)?; // proposer's membership PDA ................
+    let rent_reserve_info = next_account_info(accounts_iter)?; // rent reserve ...................................
+
+    if !cranker_info.is_signer {
+        return Err(FundError::MissingRequiredSignature.into());
+    }
+
+    let (fund_pda, _fund_bump) = Pubkey::find_program_address(&[b"light-fund", fund_name.as_bytes()], program_id);
+    let (increment_proposal_pda, _increment_proposal_bump) = Pubkey::find_program_address(&[b"increment-proposal-account", fund_account_info.key.as_ref()], program_id);
+    let (rent_pda, _rent_bump) = Pubkey::find_program_address(&[b"rent"], program_id);
+
+    if *fund_account_info.key != fund_pda || *increment_proposal_account_info.key != increment_proposal_pda || *rent_reserve_info.key != rent_pda {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let proposal_data = IncrementProposalAccount::try_from_slice(&increment_proposal_account_info.data.borrow())?;
+    if proposal_data.proposer != *proposer_account_info.key {
+        return Err(FundError::InvalidProposerInfo.into());
+    }
+
+    let proposer_gone = load_member(program_id, proposer_membership_info, fund_account_info.key, proposer_account_info.key).is_err();
+    if current_time <= proposal_data.deadline && !proposer_gone {
+        msg!("The proposal is still under voting");
+        return Err(FundError::InvalidProposalState.into());
+    }
+
+    close_increment_proposal(accounts_iter, increment_proposal_account_info, proposer_account_info, rent_reserve_info, &proposal_data)?;
+
+    msg!("[FUND-ACTIVITY] {} {} Increment proposal for new size {} closed", fund_account_info.key.to_string(), current_time, proposal_data.new_size);
+
+    Ok(())
+}
@@ -1,5 +1,14 @@
 use solana_program::pubkey::Pubkey;
 use borsh::{BorshSerialize, BorshDeserialize};
+use bytemuck::{Pod, Zeroable};
+
+// Member role bits of a light fund, an admin holds every permission
+pub const ROLE_ADMIN: u8 = 1 << 0;
+pub const ROLE_PROPOSER: u8 = 1 << 1;
+pub const ROLE_VOTER: u8 = 1 << 2;
+pub const ROLE_INVITER: u8 = 1 << 3;
+pub const ROLE_OBSERVER: u8 = 1 << 4;
+pub const DEFAULT_MEMBER_ROLES: u8 = ROLE_PROPOSER | ROLE_VOTER | ROLE_INVITER;
 
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct LightFundAccount {
@@ -8,11 +17,50 @@ pub struct LightFundAccount {
     pub creator_exists: bool,
     pub total_deposit: u64,
     pub vault: Pubkey,
+    pub governance_mint: Pubkey, // Pubkey::default() -> no governance mint
+    pub share_transfer_policy: u8, // 0 -> non-transferable, 1 -> members only, 2 -> anyone, who becomes a member
     pub current_proposal_index: u8,
     pub created_at: i64,
     pub tags: u32,
-    pub max_members: u8,
-    pub members: Vec<(Pubkey, u32)>,
+    pub max_members: u32,
+    pub expected_members: u32,
+    pub is_refunded: bool,
+    pub creation_cost: u64,
+    pub rent_collected: u64,
+    pub lockup_period: i64, // seconds a deposit stays locked
+    pub notice_period: i64, // seconds between a withdrawal request and its claim, 0 -> no notice
+    pub winding_down: bool, // approved for deletion, no new deposits, members or proposals
+    pub rent_recipient: Pubkey, // set on wind-down, gets back the rent of the fund's shared accounts
+    pub creator: Pubkey,
+    pub member_count: u32,
+    pub voter_count: u32, // members holding ROLE_VOTER, quorums are measured against it
+    pub next_member_index: u32,
+    pub privacy: u8, // 0 -> private, members join by invitation, 1 -> public
+    pub voting_mode: u8, // 0 -> one vote per member, 1 -> votes weighted by shares
+    pub quorum_bps: u16, // share of the voting power that has to vote
+    pub approval_bps: u16, // share of the votes cast that has to be in favour
+    pub executor_policy: u8, // 0 -> only the peerfunds operator executes proposals, 1 -> anyone
+    pub min_first_deposit: u64, // shares a public fund's joiner has to buy in with, in lamports
+    pub allowlist_root: [u8; 32], // merkle root of the wallets allowed into a public fund, zero -> anyone
+    pub entry_fee_bps: u16, // cut of a public fund joiner's first deposit left to the members
+    pub whitelisted_mints: Vec<Pubkey>, // empty -> any supported mint
+}
+
+// One per member or invitee of a light fund, seeded by ("member", fund, wallet)
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct FundMembership {
+    pub fund: Pubkey,
+    pub wallet: Pubkey,
+    pub is_pending: bool, // invited, not yet accepted
+    pub inviter_index: u32,
+    pub inviter: Pubkey, // wallet that paid for the invite, default when the member wasn't invited
+    pub member_index: u32,
+    pub roles: u8,
+    pub deposit: u64, // shares held, in lamports
+    pub join_time: i64,
+    pub last_deposit_time: i64,
+    pub stake_payouts: u32, // withdrawals paid out so far, seeds the member's next split stake accounts
+    pub votes_locked_until: i64, // end of the latest voting the member's shares count in, share-weighted funds only
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -30,39 +78,100 @@ pub struct FundAccount {
     pub members: Vec<Pubkey>,
 }
 
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub multisig: Pubkey,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+    pub swap_fee_bps: u16,
+}
+
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct VaultAccount {
     pub last_deposit_time: i64,
+    pub staked_lamports: u64,
+    pub stake_accounts: Vec<(u8, u64)>, // (stake index, principal delegated to it in lamports)
+    pub positions: Vec<u8>,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct UserAccount {
-    pub user_cid: [u8; 59],
-    pub funds: Vec<UserSpecific>,
+pub struct VaultHoldings {
+    pub fund: Pubkey,
+    pub holdings: Vec<Holding>,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, PartialEq)]
-pub struct UserSpecific {
-    pub fund: Pubkey,
-    pub fund_type: u8,
-    pub governance_token_balance: u64,
-    pub is_pending: bool,
-    pub is_eligible: u8,
-    pub inviter_index: u32,
-    pub join_time: i64
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Holding {
+    pub mint: Pubkey,
+    pub ata: Pubkey,
+    pub token_program: Pubkey, // SPL Token or Token-2022
+    pub last_balance: u64,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
+pub struct UserAccount {
+    pub user_cid: [u8; 59],
+    pub funds_joined: u32,
+    pub pending_invites: u32,
+}
+
+// Zero-copy aggregator, the header is followed by one fixed-size slot per proposal
+// and each proposal's votes live in its own bitmap account, seeded by
+// ("proposal-voters", fund, aggregator index, vec_index), one bit per member index
+pub const PROPOSALS_PER_AGGREGATOR: usize = 50;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ProposalAggregatorAccount {
     pub index: u8,
-    pub proposals: Vec<Proposal>,
+    pub _padding: [u8; 7],
+    pub opener: Pubkey, // proposer that paid for the aggregator, default for the fund's first one
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct MerkleRoot(pub [u8; 32]);
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProposalState {
+    Draft,
+    Voting,
+    Succeeded,
+    Defeated,
+    Queued, // scheduled for execution by the operator
+    Executing,
+    Executed,
+    Failed, // execution started but didn't finish before the proposal expired
+    Expired,
+    Cancelled,
+}
+
+// Slot index is the proposal's vec_index, a slot with no proposer is free
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Proposal {
+    pub votes_yes: u64,
+    pub votes_no: u64,
+    pub creation_time: i64,
+    pub deadline: i64,
+    pub voters_rent: u64, // paid by the proposer for the voter bitmap, growth is on the rent reserve
+    pub proposer: Pubkey,
+    pub merkel_root: [u8; 32],
+    pub cid: [u8; 59],
+    pub state: u8, // ProposalState
+    pub vec_index: u16,
+    pub swaps_status: u16,
+    pub actions: u8, // leaves in the merkle tree, the proposal is Executed once all of them ran
+    pub _padding: [u8; 7],
+}
+
+// Logged when a finalized proposal is closed, what's left of it once its slot is freed
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ProposalArchive {
+    pub fund: Pubkey,
+    pub proposal_index: u8,
+    pub vec_index: u16,
     pub proposer: Pubkey,
     pub cid: [u8; 59],
     pub merkel_root: [u8; 32],
@@ -70,26 +179,89 @@ pub struct Proposal {
     pub votes_no: u64,
     pub creation_time: i64,
     pub deadline: i64,
-    pub executed: u8,
-    pub vec_index: u16,
+    pub state: u8,
     pub swaps_status: u16,
-    pub voters_bitmap: Vec<(u32, u8)>,
+    pub closed_at: i64,
+}
+
+// Fund parameter change carried by a config proposal, all of a proposal's actions are applied together
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
+pub enum ConfigAction {
+    SetMaxMembers { max_members: u32 },
+    SetTags { tags: u32 },
+    SetVotingConfig { voting_mode: u8, quorum_bps: u16, approval_bps: u16 },
+    SetFeeConfig { recipient: Pubkey, management_fee_bps: u16, performance_fee_bps: u16, payout_mode: u8, price_feeds: Vec<PriceFeed> },
+    SetExecutorPolicy { executor_policy: u8 },
+    SetWhitelistedMints { mints: Vec<Pubkey> },
+    SetPrivacy { privacy: u8 },
+    SetEntryConditions { min_first_deposit: u64, allowlist_root: [u8; 32], entry_fee_bps: u16 },
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct JoinProposalAggregator {
+pub struct ConditionalOrder {
     pub fund: Pubkey,
-    pub index: u8,
-    pub join_proposals: Vec<JoinProposal>
+    pub creator: Pubkey,
+    pub proposal_index: u8,
+    pub vec_index: u16,
+    pub order_index: u8,
+    pub trigger_mint: Pubkey,
+    pub feed_id: [u8; 32],
+    pub comparison: u8,
+    pub threshold_price: u64,
+    pub expiry: i64,
+    pub swap_leaf: [u8; 32],
+    pub created_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Position {
+    pub fund: Pubkey,
+    pub opened_by: Pubkey,
+    pub position_index: u8,
+    pub protocol: u8, // 0 -> SPL stake-pool, 1 -> lending reserve
+    pub protocol_program: Pubkey,
+    pub venue: Pubkey, // stake pool or lending reserve
+    pub deposited_mint: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub deposited_amount: u64,
+    pub receipt_amount: u64,
+    pub opened_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct FeeAccount {
+    pub fund: Pubkey,
+    pub recipient: Pubkey,
+    pub management_fee_bps: u16,
+    pub performance_fee_bps: u16,
+    pub payout_mode: u8, // 0 -> fee shares, 1 -> vault assets
+    pub high_water_mark: u64, // NAV per share
+    pub last_nav: u64,
+    pub last_crystallized: i64,
+    pub management_fees_accrued: u64,
+    pub performance_fees_accrued: u64,
+    pub fees_paid: u64,
+    pub fee_shares_minted: u64,
+    pub price_feeds: Vec<PriceFeed>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy)]
+pub struct PriceFeed {
+    pub mint: Pubkey,
+    pub feed_id: [u8; 32],
 }
 
+// One per outsider asking to join a private light fund, seeded by ("join-proposal", fund, joiner)
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct JoinProposal {
+    pub fund: Pubkey,
     pub joiner: Pubkey,
     pub votes_yes: u64,
     pub votes_no: u64,
     pub creation_time: i64,
-    pub proposal_index: u8
+    pub deadline: i64,
+    pub escrow: u64, // joiner's lamports covering the membership rent and the creation cost share
+    pub voters: Vec<(Pubkey, u8)>
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -112,5 +284,27 @@ pub struct IncrementProposalAccount {
     pub refund_type: u8,
     pub votes_yes: u64,
     pub votes_no: u64,
+    pub deadline: i64,
     pub voters: Vec<(Pubkey, u8)>
-}
\ No newline at end of file
+}
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RemoveMemberProposal {
+    pub fund: Pubkey,
+    pub proposer: Pubkey,
+    pub target: Pubkey,
+    pub votes_yes: u64,
+    pub votes_no: u64,
+    pub creation_time: i64,
+    pub deadline: i64,
+    pub voters: Vec<(Pubkey, u8)>
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalRequest {
+    pub fund: Pubkey,
+    pub member: Pubkey,
+    pub task: u8,
+    pub stake_percent: u64,
+    pub requested_at: i64,
+    pub claimable_at: i64,
+}
@@ -0,0 +1,1446 @@
+use std::io::Write;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, hash::hashv, instruction::{AccountMeta, Instruction}, msg, program::invoke_signed, program_error::ProgramError, program_pack::Pack, pubkey, pubkey::Pubkey
+};
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use crate::{errors::FundError, state::{ConfigAction, FundMembership, LightFundAccount, PriceFeed, Proposal, ProposalAggregatorAccount, ProposalState, ProtocolConfig, ROLE_ADMIN}};
+
+// Pyth pull-oracle receiver, owner of every PriceUpdateV2 account
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+pub const MAX_PRICE_AGE: i64 = 60;
+
+// All thresholds and on-chain valuations are kept with 8 decimals
+pub const PRICE_EXPO: i32 = -8;
+
+// Seconds a passed proposal stays executable after its deadline
+pub const PROPOSAL_EXPIRY: i64 = 7 * 24 * 60 * 60;
+
+// Seconds between the end of voting and the first moment a config proposal can be applied
+pub const CONFIG_TIMELOCK: i64 = 24 * 60 * 60;
+
+// Raydium concentrated liquidity program, the only venue vault swaps are routed through
+pub const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+// Operator wallet executing proposals of funds that don't open execution to anyone
+pub const PEERFUNDS_OPERATOR: Pubkey = pubkey!("BA19YT7ryTxJY14J2CaY7xzAZWyR9afwDRUXgB7fMXEh");
+
+// Fixed sizes of the zero-copy proposal aggregator layout
+pub const AGGREGATOR_HEADER_SPACE: usize = std::mem::size_of::<ProposalAggregatorAccount>();
+pub const PROPOSAL_SPACE: usize = std::mem::size_of::<Proposal>();
+
+// NAV per share is kept with 9 extra decimals
+pub const SHARE_PRICE_SCALE: u128 = 1_000_000_000;
+pub const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+
+// Governance tokens mirror light fund shares, which are counted in lamports
+pub const GOVERNANCE_DECIMALS: u8 = 9;
+
+#[derive(BorshDeserialize)]
+enum VerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
+
+#[derive(BorshDeserialize)]
+struct PriceFeedMessage {
+    feed_id: [u8; 32],
+    price: i64,
+    _conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    _prev_publish_time: i64,
+    _ema_price: i64,
+    _ema_conf: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct PriceUpdateV2 {
+    _write_authority: Pubkey,
+    verification_level: VerificationLevel,
+    price_message: PriceFeedMessage,
+    _posted_slot: u64,
+}
+
+/// Leaf of a proposal's merkle tree describing a single swap.
+pub fn swap_leaf(input_mint: &Pubkey, output_mint: &Pubkey, amount: u64, slippage: u16) -> [u8; 32] {
+    hashv(&[
+        input_mint.as_ref(),
+        output_mint.as_ref(),
+        &amount.to_le_bytes(),
+        &slippage.to_le_bytes(),
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving a conditional order on `swap_leaf`.
+pub fn conditional_order_leaf(
+    trigger_mint: &Pubkey,
+    feed_id: &[u8; 32],
+    comparison: u8,
+    threshold_price: u64,
+    expiry: i64,
+    swap_leaf: &[u8; 32],
+) -> [u8; 32] {
+    hashv(&[
+        b"conditional-order",
+        trigger_mint.as_ref(),
+        feed_id,
+        &[comparison],
+        &threshold_price.to_le_bytes(),
+        &expiry.to_le_bytes(),
+        swap_leaf,
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving a native stake action on vault SOL.
+/// Deactivate and withdraw leaves use the default vote account and a zero amount.
+pub fn stake_leaf(action: u8, stake_index: u8, vote_account: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        b"stake",
+        &[action],
+        &[stake_index],
+        vote_account.as_ref(),
+        &amount.to_le_bytes(),
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving a deposit (action 0) into or a withdrawal
+/// (action 1) from a yield venue. The amount is in deposited tokens for deposits and in
+/// receipt tokens for withdrawals.
+pub fn position_leaf(action: u8, protocol: u8, protocol_program: &Pubkey, venue: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        b"position",
+        &[action],
+        &[protocol],
+        protocol_program.as_ref(),
+        venue.as_ref(),
+        &amount.to_le_bytes(),
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving a new set of roles for a member.
+pub fn member_roles_leaf(member: &Pubkey, roles: u8) -> [u8; 32] {
+    hashv(&[
+        b"member-roles",
+        member.as_ref(),
+        &[roles],
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving the fund's lockup and notice periods.
+pub fn liquidity_terms_leaf(lockup_period: i64, notice_period: i64) -> [u8; 32] {
+    hashv(&[
+        b"liquidity-terms",
+        &lockup_period.to_le_bytes(),
+        &notice_period.to_le_bytes(),
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving who the fund's shares can be transferred to.
+pub fn share_policy_leaf(share_transfer_policy: u8) -> [u8; 32] {
+    hashv(&[
+        b"share-policy",
+        &[share_transfer_policy],
+    ]).to_bytes()
+}
+
+/// Leaf of a proposal's merkle tree approving the fund's wind-down and deletion.
+pub fn delete_fund_leaf() -> [u8; 32] {
+    hashv(&[b"delete-fund"]).to_bytes()
+}
+
+/// Whether a light fund membership holds `role`, admins holding all of them.
+pub fn has_role(membership: &FundMembership, role: u8) -> bool {
+    membership.roles & (ROLE_ADMIN | role) != 0
+}
+
+// What a fund's proposals are decided by, taken from its voting config
+#[derive(Clone, Copy)]
+pub struct VotingRules {
+    pub strength: u64, // total voting power quorums are measured against
+    pub quorum_bps: u16,
+    pub approval_bps: u16,
+}
+
+/// Voting rules of a light fund. One vote per member measures quorums against the members
+/// allowed to vote, share weighted voting against the fund's total shares.
+pub fn voting_rules(fund_data: &LightFundAccount) -> VotingRules {
+    let strength = if fund_data.voting_mode == 1 {
+        fund_data.total_deposit
+    } else {
+        fund_data.voter_count as u64
+    };
+
+    VotingRules { strength, quorum_bps: fund_data.quorum_bps, approval_bps: fund_data.approval_bps }
+}
+
+/// Weight of a member's vote under the fund's voting mode.
+pub fn vote_weight(fund_data: &LightFundAccount, membership: &FundMembership) -> u64 {
+    if fund_data.voting_mode == 1 {
+        membership.deposit
+    } else {
+        1
+    }
+}
+
+/// Turnout reached the quorum and the votes in favour reached the approval threshold.
+pub fn votes_passed(votes_yes: u64, votes_no: u64, rules: VotingRules) -> bool {
+    let cast = votes_yes as u128 + votes_no as u128;
+    cast * 10_000 >= rules.strength as u128 * rules.quorum_bps as u128
+        && votes_yes as u128 * 10_000 >= cast * rules.approval_bps as u128
+}
+
+/// Yes votes alone settle the vote, whatever the voting power yet to vote decides.
+pub fn votes_decisive(votes_yes: u64, rules: VotingRules) -> bool {
+    let threshold_bps = rules.quorum_bps.max(rules.approval_bps);
+    votes_yes as u128 * 10_000 >= rules.strength as u128 * threshold_bps as u128
+}
+
+/// Only the peerfunds operator executes proposals, unless the fund opened execution to anyone.
+pub fn check_executor(fund_data: &LightFundAccount, executor: &Pubkey) -> ProgramResult {
+    if fund_data.executor_policy == 0 && *executor != PEERFUNDS_OPERATOR {
+        return Err(FundError::InvalidSigner.into());
+    }
+
+    Ok(())
+}
+
+/// Mints the fund's vault may take in, any supported mint while the whitelist is empty.
+pub fn check_mint_whitelisted(fund_data: &LightFundAccount, mint: &Pubkey) -> ProgramResult {
+    if !fund_data.whitelisted_mints.is_empty() && !fund_data.whitelisted_mints.contains(mint) {
+        msg!("Mint {} is not whitelisted by the fund", mint.to_string());
+        return Err(FundError::MintNotWhitelisted.into());
+    }
+
+    Ok(())
+}
+
+/// Leaf of a public fund's allowlist tree for `wallet`.
+pub fn allowlist_leaf(wallet: &Pubkey) -> [u8; 32] {
+    hashv(&[b"allowlist", wallet.as_ref()]).to_bytes()
+}
+
+/// Checks an allowlist proof, pairs being hashed in sorted order so no leaf index is needed.
+pub fn verify_allowlist_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    let mut calculated_root = leaf;
+    for node in proof.iter() {
+        calculated_root = if calculated_root <= *node {
+            hashv(&[&calculated_root, node]).to_bytes()
+        } else {
+            hashv(&[node, &calculated_root]).to_bytes()
+        };
+    }
+
+    calculated_root == root
+}
+
+/// Leaf of a proposal's merkle tree approving a list of config actions, applied as a whole.
+pub fn config_actions_leaf(actions: &[ConfigAction]) -> Result<[u8; 32], ProgramError> {
+    Ok(hashv(&[b"config", &actions.try_to_vec()?]).to_bytes())
+}
+
+/// Leaf of a proposal's merkle tree approving a fee configuration, price feeds included.
+pub fn fee_config_leaf(
+    recipient: &Pubkey,
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    payout_mode: u8,
+    price_feeds: &[PriceFeed],
+) -> [u8; 32] {
+    let mut feeds_bytes: Vec<u8> = Vec::with_capacity(price_feeds.len() * 64);
+    for price_feed in price_feeds.iter() {
+        feeds_bytes.extend_from_slice(price_feed.mint.as_ref());
+        feeds_bytes.extend_from_slice(&price_feed.feed_id);
+    }
+
+    hashv(&[
+        b"fee-config",
+        recipient.as_ref(),
+        &management_fee_bps.to_le_bytes(),
+        &performance_fee_bps.to_le_bytes(),
+        &[payout_mode],
+        &feeds_bytes,
+    ]).to_bytes()
+}
+
+pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[[u8; 32]], leaf_index: u8, root: [u8; 32]) -> bool {
+    let mut calculated_merkel_root = leaf;
+    let mut index = leaf_index as usize;
+
+    for node in proof.iter() {
+        calculated_merkel_root = if index & 1 == 0 {
+            hashv(&[&calculated_merkel_root, node]).to_bytes()
+        } else {
+            hashv(&[node, &calculated_merkel_root]).to_bytes()
+        };
+        index /= 2;
+    }
+
+    calculated_merkel_root == root
+}
+
+/// Lifecycle state stored in a proposal slot.
+pub fn proposal_state(proposal: &Proposal) -> Result<ProposalState, ProgramError> {
+    Ok(match proposal.state {
+        0 => ProposalState::Draft,
+        1 => ProposalState::Voting,
+        2 => ProposalState::Succeeded,
+        3 => ProposalState::Defeated,
+        4 => ProposalState::Queued,
+        5 => ProposalState::Executing,
+        6 => ProposalState::Executed,
+        7 => ProposalState::Failed,
+        8 => ProposalState::Expired,
+        9 => ProposalState::Cancelled,
+        _ => return Err(FundError::InvalidProposalState.into()),
+    })
+}
+
+/// Applies the transitions that only depend on votes and time (deadline is stored in ms):
+/// voting ends in Succeeded or Defeated, and once the expiry window is over a Succeeded or
+/// Queued proposal Expires while an unfinished Executing one Fails.
+pub fn finalize_proposal(proposal: &mut Proposal, rules: VotingRules, current_time: i64) -> Result<ProposalState, ProgramError> {
+    let deadline = proposal.deadline / 1000;
+
+    let mut state = proposal_state(proposal)?;
+    if state == ProposalState::Voting && current_time > deadline {
+        state = if votes_passed(proposal.votes_yes, proposal.votes_no, rules) { ProposalState::Succeeded } else { ProposalState::Defeated };
+    }
+
+    if current_time > deadline + PROPOSAL_EXPIRY {
+        state = match state {
+            ProposalState::Succeeded | ProposalState::Queued => ProposalState::Expired,
+            ProposalState::Executing => ProposalState::Failed,
+            state => state,
+        };
+    }
+
+    proposal.state = state as u8;
+    Ok(state)
+}
+
+/// Moves a proposal to `next`, the one place every handler checks proposal transitions.
+/// Vote and time driven transitions are applied first, then the requested one has to be one of
+/// the edges below. Executed is final, a proposal stays Executing until its last action ran.
+pub fn transition_proposal(proposal: &mut Proposal, next: ProposalState, rules: VotingRules, current_time: i64) -> ProgramResult {
+    let current = finalize_proposal(proposal, rules, current_time)?;
+
+    let allowed = match (current, next) {
+        (ProposalState::Draft, ProposalState::Voting) => current_time <= proposal.deadline / 1000,
+        (ProposalState::Voting, ProposalState::Cancelled) => true,
+        (ProposalState::Succeeded | ProposalState::Queued, ProposalState::Queued) => true,
+        (ProposalState::Succeeded | ProposalState::Queued | ProposalState::Executing, ProposalState::Executing) => true,
+        (ProposalState::Succeeded | ProposalState::Queued | ProposalState::Executing, ProposalState::Executed) => true,
+        _ => false,
+    };
+
+    if !allowed {
+        msg!("Proposal can't move from {:?} to {:?}", current, next);
+        return Err(FundError::InvalidProposalState.into());
+    }
+
+    proposal.state = next as u8;
+    Ok(())
+}
+
+/// Proposal is over for good and its slot can be released.
+pub fn proposal_closable(state: ProposalState) -> bool {
+    matches!(
+        state,
+        ProposalState::Defeated | ProposalState::Executed | ProposalState::Failed | ProposalState::Expired | ProposalState::Cancelled
+    )
+}
+
+/// Aggregator header of a proposal aggregator account.
+pub fn load_aggregator(aggregator_data: &[u8]) -> Result<&ProposalAggregatorAccount, ProgramError> {
+    let header = aggregator_data.get(..AGGREGATOR_HEADER_SPACE).ok_or(FundError::InvalidProposalAccount)?;
+    bytemuck::try_from_bytes(header).map_err(|_| FundError::InvalidProposalAccount.into())
+}
+
+/// Number of proposal slots an aggregator account holds, freed ones included.
+pub fn proposal_slots(aggregator_data: &[u8]) -> usize {
+    aggregator_data.len().saturating_sub(AGGREGATOR_HEADER_SPACE) / PROPOSAL_SPACE
+}
+
+/// Proposal slot `vec_index` of an aggregator, read in place whether it is in use or not.
+pub fn proposal_slot_mut(aggregator_data: &mut [u8], vec_index: u16) -> Result<&mut Proposal, ProgramError> {
+    let start = AGGREGATOR_HEADER_SPACE + (vec_index as usize) * PROPOSAL_SPACE;
+    let slot = aggregator_data.get_mut(start..start + PROPOSAL_SPACE).ok_or(FundError::InvalidProposalAccount)?;
+    bytemuck::try_from_bytes_mut(slot).map_err(|_| FundError::InvalidProposalAccount.into())
+}
+
+/// Proposal `vec_index` of an aggregator, failing for a freed slot.
+pub fn load_proposal(aggregator_data: &[u8], vec_index: u16) -> Result<&Proposal, ProgramError> {
+    let start = AGGREGATOR_HEADER_SPACE + (vec_index as usize) * PROPOSAL_SPACE;
+    let slot = aggregator_data.get(start..start + PROPOSAL_SPACE).ok_or(FundError::InvalidProposalAccount)?;
+    let proposal: &Proposal = bytemuck::try_from_bytes(slot).map_err(|_| FundError::InvalidProposalAccount)?;
+    if proposal.proposer == Pubkey::default() {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    Ok(proposal)
+}
+
+/// Same as `load_proposal`, for updating the proposal in place.
+pub fn load_proposal_mut(aggregator_data: &mut [u8], vec_index: u16) -> Result<&mut Proposal, ProgramError> {
+    let proposal = proposal_slot_mut(aggregator_data, vec_index)?;
+    if proposal.proposer == Pubkey::default() {
+        return Err(FundError::InvalidProposalAccount.into());
+    }
+
+    Ok(proposal)
+}
+
+/// Bytes a proposal's voter bitmap needs to hold `member_index`.
+pub fn voters_bitmap_space(member_index: u32) -> usize {
+    (member_index as usize) / 8 + 1
+}
+
+/// Sets `member_index`'s bit in a proposal's voter bitmap, false if it was already set.
+pub fn mark_voted(voters_data: &mut [u8], member_index: u32) -> Result<bool, ProgramError> {
+    let byte = voters_data.get_mut((member_index as usize) / 8).ok_or(FundError::InvalidAccountData)?;
+    let bit = 1u8 << (member_index % 8);
+    if *byte & bit != 0 {
+        return Ok(false);
+    }
+
+    *byte |= bit;
+    Ok(true)
+}
+
+/// Marks action `leaf_index` as executed in the proposal's swaps_status bitmap, failing if it
+/// already ran. The proposal is Executing until the bit of its last action is set, then Executed.
+pub fn record_executed_action(proposal: &mut Proposal, leaf_index: u8, rules: VotingRules, current_time: i64) -> ProgramResult {
+    if leaf_index >= proposal.actions || leaf_index >= 16 {
+        return Err(FundError::InvalidInstruction.into());
+    }
+
+    let bit = 1u16 << leaf_index;
+    if proposal.swaps_status & bit != 0 {
+        return Err(FundError::ActionAlreadyExecuted.into());
+    }
+
+    let swaps_status = proposal.swaps_status | bit;
+    let next = if swaps_status.count_ones() == proposal.actions as u32 {
+        ProposalState::Executed
+    } else {
+        ProposalState::Executing
+    };
+
+    transition_proposal(proposal, next, rules, current_time)?;
+    proposal.swaps_status = swaps_status;
+
+    Ok(())
+}
+
+/// Checks that `leaf` belongs to a passed proposal and records it as executed, so every
+/// approved action runs at most once.
+pub fn consume_approved_action(
+    proposal_aggregator_info: &AccountInfo,
+    vec_index: u16,
+    leaf: [u8; 32],
+    leaf_index: u8,
+    merkel_proof: &[[u8; 32]],
+    rules: VotingRules,
+    current_time: i64,
+) -> ProgramResult {
+    let mut aggregator_data = proposal_aggregator_info.data.borrow_mut();
+    let proposal = load_proposal_mut(&mut aggregator_data, vec_index)?;
+
+    if !verify_merkle_proof(leaf, merkel_proof, leaf_index, proposal.merkel_root) {
+        msg!("The action is not part of the approved proposal.");
+        return Err(FundError::InvalidSwapDetails.into());
+    }
+
+    record_executed_action(proposal, leaf_index, rules, current_time)
+}
+
+/// Minimum out of a swap of `amount` allowed to slip by `slippage` bps.
+pub fn slippage_min_amount_out(amount: u64, slippage: u16) -> Result<u64, ProgramError> {
+    if slippage > 10_000 {
+        return Err(FundError::InvalidSwapDetails.into());
+    }
+
+    Ok(((amount as u128) * (10_000 - slippage as u128) / 10_000) as u64)
+}
+
+/// Swap through Raydium CLMM with the vault PDA as the signer, failing below `min_amount_out`.
+///
+/// `swap_accounts` must be ordered as: vault, amm config, pool state, input token account,
+/// output token account, pool input vault, pool output vault, observation state, token program,
+/// token program 2022, memo program, input mint, output mint, followed by the tick arrays.
+pub fn raydium_clmm_swap<'a>(
+    raydium_clmm_program: &AccountInfo<'a>,
+    swap_accounts: &[AccountInfo<'a>],
+    amount: u64,
+    min_amount_out: u64,
+    vault_seeds: &[&[u8]],
+) -> ProgramResult {
+    if swap_accounts.len() < 13 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    // The CPI is signed with the vault seeds, so it may only ever reach Raydium's own pools
+    if *raydium_clmm_program.key != RAYDIUM_CLMM_PROGRAM_ID
+        || *swap_accounts[1].owner != RAYDIUM_CLMM_PROGRAM_ID
+        || *swap_accounts[2].owner != RAYDIUM_CLMM_PROGRAM_ID
+    {
+        msg!("Swaps must go through a Raydium CLMM pool");
+        return Err(FundError::InvalidSwapProgram.into());
+    }
+
+    let discriminator: &[u8] = &[0x2b, 0x04, 0xed, 0x0b, 0x1a, 0xc9, 0x1e, 0x62];
+    let other_amount_threshold = min_amount_out;
+    let sqrt_price_limit_x64: u128 = 0;
+    let is_base_input = true;
+
+    let mut args_buf = Vec::with_capacity(33);
+    args_buf.write_all(&amount.to_le_bytes()).unwrap();
+    args_buf.write_all(&other_amount_threshold.to_le_bytes()).unwrap();
+    args_buf.write_all(&(sqrt_price_limit_x64 as u64).to_le_bytes()).unwrap();
+    args_buf.write_all(&((sqrt_price_limit_x64 >> 64) as u64).to_le_bytes()).unwrap();
+    args_buf.write_all(&[is_base_input as u8]).unwrap();
+
+    let mut instruction_data = discriminator.to_vec();
+    instruction_data.extend(args_buf);
+
+    let accounts_needed: Vec<AccountMeta> = swap_accounts
+        .iter()
+        .enumerate()
+        .map(|(i, acc)| match i {
+            0 => AccountMeta::new(*acc.key, true),
+            1 | 8 | 9 | 10 => AccountMeta::new_readonly(*acc.key, false),
+            _ => AccountMeta::new(*acc.key, false),
+        })
+        .collect();
+
+    let swap_cpi_instruction = Instruction {
+        program_id: *raydium_clmm_program.key,
+        accounts: accounts_needed,
+        data: instruction_data
+    };
+
+    invoke_signed(&swap_cpi_instruction, swap_accounts, &[vault_seeds])
+}
+
+/// Loads the program-wide protocol config after checking its address.
+pub fn load_protocol_config(protocol_config_info: &AccountInfo, program_id: &Pubkey) -> Result<ProtocolConfig, ProgramError> {
+    let (protocol_config_pda, _protocol_config_bump) = Pubkey::find_program_address(&[b"protocol-config"], program_id);
+    if *protocol_config_info.key != protocol_config_pda || protocol_config_info.data_is_empty() {
+        return Err(FundError::InvalidProtocolConfig.into());
+    }
+
+    Ok(ProtocolConfig::try_from_slice(&protocol_config_info.data.borrow())?)
+}
+
+/// Checks that a mint belongs to SPL Token or Token-2022 and has no extension the vault
+/// can't safely hold, returning its decimals.
+pub fn supported_mint_decimals(mint_info: &AccountInfo) -> Result<u8, ProgramError> {
+    if *mint_info.owner == spl_token::id() {
+        return Ok(spl_token::state::Mint::unpack(&mint_info.data.borrow())?.decimals);
+    }
+
+    if *mint_info.owner != spl_token_2022::id() {
+        return Err(FundError::InvalidMints.into());
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    for extension in mint.get_extension_types()? {
+        // Non-transferable tokens can't leave the vault, a permanent delegate can drain it
+        if matches!(extension, ExtensionType::NonTransferable | ExtensionType::PermanentDelegate) {
+            msg!("Mint extension {:?} is not supported", extension);
+            return Err(FundError::UnsupportedMint.into());
+        }
+    }
+
+    Ok(mint.base.decimals)
+}
+
+/// Moves tokens with transfer_checked, understood by both token programs. Token-2022
+/// transfer fees are withheld from what the destination receives.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            source.key,
+            mint.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            decimals
+        )?,
+        &[source.clone(), mint.clone(), destination.clone(), authority.clone(), token_program.clone()],
+        signer_seeds
+    )
+}
+
+/// Moves `fee_bps` of `amount` from a vault token account into the protocol treasury's
+/// token account and returns the fee taken.
+#[allow(clippy::too_many_arguments)]
+pub fn collect_protocol_fee<'a>(
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    vault_ata: &AccountInfo<'a>,
+    treasury_ata: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    amount: u64,
+    fee_bps: u16,
+    vault_seeds: &[&[u8]],
+) -> Result<u64, ProgramError> {
+    let fee = ((amount as u128) * (fee_bps as u128) / 10_000) as u64;
+
+    if fee != 0 {
+        if treasury_ata.data_is_empty() {
+            msg!("Treasury token account doesn't exist");
+            return Err(FundError::InvalidTokenAccount.into());
+        }
+
+        let decimals = supported_mint_decimals(mint)?;
+        transfer_tokens(token_program, vault_ata, mint, treasury_ata, vault, fee, decimals, &[vault_seeds])?;
+    }
+
+    Ok(fee)
+}
+
+/// Mints a light fund's governance tokens, the fund PDA being the mint authority.
+pub fn mint_governance_tokens<'a>(
+    token_program_2022: &AccountInfo<'a>,
+    governance_mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    fund: &AccountInfo<'a>,
+    amount: u64,
+    fund_seeds: &[&[u8]],
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    invoke_signed(
+        &spl_token_2022::instruction::mint_to_checked(
+            token_program_2022.key,
+            governance_mint.key,
+            destination.key,
+            fund.key,
+            &[],
+            amount,
+            GOVERNANCE_DECIMALS
+        )?,
+        &[governance_mint.clone(), destination.clone(), fund.clone(), token_program_2022.clone()],
+        &[fund_seeds]
+    )
+}
+
+/// Burns a light fund's governance tokens from a member's account, the fund PDA being the
+/// mint's permanent delegate so no member signature is needed.
+pub fn burn_governance_tokens<'a>(
+    token_program_2022: &AccountInfo<'a>,
+    governance_mint: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    fund: &AccountInfo<'a>,
+    amount: u64,
+    fund_seeds: &[&[u8]],
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    invoke_signed(
+        &spl_token_2022::instruction::burn_checked(
+            token_program_2022.key,
+            source.key,
+            governance_mint.key,
+            fund.key,
+            &[],
+            amount,
+            GOVERNANCE_DECIMALS
+        )?,
+        &[source.clone(), governance_mint.clone(), fund.clone(), token_program_2022.clone()],
+        &[fund_seeds]
+    )
+}
+
+/// Reads a fully verified Pyth price update and returns (price, expo) after checking
+/// the feed id and staleness.
+pub fn read_oracle_price(price_update_info: &AccountInfo, feed_id: &[u8; 32], current_time: i64) -> Result<(i64, i32), ProgramError> {
+    if *price_update_info.owner != PYTH_RECEIVER_PROGRAM_ID {
+        msg!("Price account is not owned by the Pyth receiver");
+        return Err(FundError::InvalidOracleAccount.into());
+    }
+
+    let data = price_update_info.data.borrow();
+    if data.len() < 8 || data[..8] != PRICE_UPDATE_V2_DISCRIMINATOR {
+        return Err(FundError::InvalidOracleAccount.into());
+    }
+
+    let price_update = PriceUpdateV2::deserialize(&mut &data[8..])?;
+
+    if !matches!(price_update.verification_level, VerificationLevel::Full) {
+        msg!("Price update is only partially verified");
+        return Err(FundError::InvalidOracleAccount.into());
+    }
+
+    let message = price_update.price_message;
+    if message.feed_id != *feed_id {
+        msg!("Price feed doesn't match");
+        return Err(FundError::InvalidOracleAccount.into());
+    }
+
+    if message.publish_time + MAX_PRICE_AGE < current_time {
+        msg!("Stale price: published at {}", message.publish_time);
+        return Err(FundError::StalePrice.into());
+    }
+
+    if message.price <= 0 {
+        return Err(FundError::InvalidOracleAccount.into());
+    }
+
+    Ok((message.price, message.exponent))
+}
+
+/// Value of `amount` base units of a token with `decimals`, at a `PRICE_EXPO` price.
+pub fn token_value(amount: u64, decimals: u8, price: u64) -> Result<u128, ProgramError> {
+    let factor = 10u128.checked_pow(decimals as u32).ok_or(FundError::MathOverflow)?;
+    let value = (amount as u128).checked_mul(price as u128).ok_or(FundError::MathOverflow)?;
+    Ok(value / factor)
+}
+
+/// Fees owed at a crystallization, in NAV terms: the management fee prorated over `elapsed`
+/// seconds, and the performance fee on the gain of the NAV per share above the high-water mark.
+/// The first crystallization only sets the mark, and fees that would take the whole NAV are dropped.
+pub fn crystallization_fees(
+    nav: u128,
+    total_shares: u128,
+    elapsed: u128,
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    high_water_mark: u64,
+) -> (u128, u128) {
+    if total_shares == 0 || nav == 0 {
+        return (0, 0);
+    }
+
+    let management_fee = nav * (management_fee_bps as u128) * elapsed / (10_000 * SECONDS_PER_YEAR);
+
+    let nav_per_share = nav * SHARE_PRICE_SCALE / total_shares;
+    let high_water_mark = high_water_mark as u128;
+    let performance_fee = if high_water_mark != 0 && nav_per_share > high_water_mark {
+        let gain = (nav_per_share - high_water_mark) * total_shares / SHARE_PRICE_SCALE;
+        gain * (performance_fee_bps as u128) / 10_000
+    } else {
+        0
+    };
+
+    if management_fee + performance_fee >= nav {
+        return (0, 0);
+    }
+
+    (management_fee, performance_fee)
+}
+
+/// Shares minted for a fee, diluting everyone else by exactly its value: `fee * S / (nav - fee)`.
+pub fn fee_shares(fee_value: u128, total_shares: u128, nav: u128) -> Result<u64, ProgramError> {
+    let remaining = nav.checked_sub(fee_value).filter(|remaining| *remaining != 0).ok_or(FundError::MathOverflow)?;
+    u64::try_from(fee_value * total_shares / remaining).map_err(|_| FundError::MathOverflow.into())
+}
+
+/// Shares `value` buys at the NAV per share, the NAV taken before the value lands in the vault.
+/// None while the fund has no shares, there is no price to buy at yet. Shares left with no value
+/// behind them have no price either, and nothing can be bought.
+pub fn shares_for_value(value: u128, nav: u128, total_shares: u128) -> Result<Option<u64>, ProgramError> {
+    if total_shares == 0 {
+        return Ok(None);
+    }
+
+    if nav == 0 {
+        msg!("Fund's shares have no value behind them, there is no price to buy at");
+        return Err(FundError::FundHasNoValue.into());
+    }
+
+    let shares = value.checked_mul(total_shares).ok_or(FundError::MathOverflow)? / nav;
+    Ok(Some(u64::try_from(shares).map_err(|_| FundError::MathOverflow)?))
+}
+
+/// When a withdrawal requested at `current_time` can be claimed. Claims settle in batches, one every
+/// notice period counted from the fund's creation, so exits that pile up can be met together instead
+/// of one by one: a request waits out the full notice and then for the next batch boundary. With no
+/// notice period it can be claimed right away.
+pub fn withdrawal_claimable_at(created_at: i64, notice_period: i64, current_time: i64) -> i64 {
+    if notice_period <= 0 {
+        return current_time;
+    }
+
+    let ready_in = current_time + notice_period - created_at;
+    created_at + ((ready_in + notice_period - 1) / notice_period) * notice_period
+}
+
+/// Joiner's part of the fund's creation cost, owed until the creator has been refunded.
+pub fn creation_cost_share(fund_data: &LightFundAccount) -> u64 {
+    if fund_data.is_refunded {
+        0
+    } else {
+        fund_data.creation_cost / (fund_data.expected_members as u64)
+    }
+}
+
+/// Rescales an oracle price to `PRICE_EXPO` decimals.
+pub fn normalize_price(price: i64, expo: i32) -> Result<u64, ProgramError> {
+    let price = price as u128;
+    let shift = expo - PRICE_EXPO;
+    let factor = 10u128.checked_pow(shift.unsigned_abs()).ok_or(FundError::MathOverflow)?;
+    let normalized = if shift >= 0 {
+        price.checked_mul(factor).ok_or(FundError::MathOverflow)?
+    } else {
+        price / factor
+    };
+
+    u64::try_from(normalized).map_err(|_| FundError::MathOverflow.into())
+}
+
+fn adapter_cpi<'a>(
+    program: &AccountInfo<'a>,
+    adapter_accounts: &[AccountInfo<'a>],
+    signer: usize,
+    readonly: &[usize],
+    data: Vec<u8>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let accounts_needed: Vec<AccountMeta> = adapter_accounts
+        .iter()
+        .enumerate()
+        .map(|(i, acc)| {
+            if readonly.contains(&i) {
+                AccountMeta::new_readonly(*acc.key, i == signer)
+            } else {
+                AccountMeta::new(*acc.key, i == signer)
+            }
+        })
+        .collect();
+
+    let adapter_instruction = Instruction {
+        program_id: *program.key,
+        accounts: accounts_needed,
+        data
+    };
+
+    invoke_signed(&adapter_instruction, adapter_accounts, &[signer_seeds])
+}
+
+fn pubkey_at(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    let bytes = data.get(offset..offset + 32).ok_or(FundError::InvalidPosition)?;
+    Ok(Pubkey::new_from_array(bytes.try_into().map_err(|_| FundError::InvalidPosition)?))
+}
+
+/// Checks the accounts handed to a SPL stake pool against the pool's own state: withdraw
+/// authority, reserve stake, manager fee account, pool mint and the pool mint's token program.
+pub fn check_stake_pool_accounts(
+    stake_pool_program: &AccountInfo,
+    stake_pool: &AccountInfo,
+    withdraw_authority: &Pubkey,
+    reserve_stake: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> ProgramResult {
+    if stake_pool.owner != stake_pool_program.key {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    // StakePool layout: account type, manager, staker, deposit authority, withdraw bump seed,
+    // validator list, reserve stake, pool mint, manager fee account, token program
+    let data = stake_pool.data.borrow();
+    if data.first() != Some(&1) || data.len() < 258 {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    let expected_withdraw_authority = Pubkey::create_program_address(
+        &[stake_pool.key.as_ref(), b"withdraw", &[data[97]]],
+        stake_pool_program.key
+    ).map_err(|_| FundError::InvalidPosition)?;
+
+    if *withdraw_authority != expected_withdraw_authority ||
+       *reserve_stake != pubkey_at(&data, 130)? ||
+       *pool_mint != pubkey_at(&data, 162)? ||
+       *manager_fee_account != pubkey_at(&data, 194)? ||
+       *token_program != pubkey_at(&data, 226)? {
+        msg!("Accounts don't match the stake pool");
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    Ok(())
+}
+
+/// Checks the accounts handed to a SPL token-lending reserve against the reserve's own state:
+/// lending market, liquidity mint, liquidity supply and collateral mint.
+pub fn check_lending_reserve_accounts(
+    lending_program: &AccountInfo,
+    reserve: &AccountInfo,
+    lending_market: &AccountInfo,
+    liquidity_mint: &Pubkey,
+    liquidity_supply: &Pubkey,
+    collateral_mint: &Pubkey,
+) -> ProgramResult {
+    if reserve.owner != lending_program.key || lending_market.owner != lending_program.key {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    // Reserve layout: version, last update slot and stale flag, lending market, liquidity mint,
+    // decimals, liquidity supply, fee receiver, oracle, amounts and wads, then collateral mint
+    let data = reserve.data.borrow();
+    if data.first() != Some(&1) || data.len() < 259 {
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    if *lending_market.key != pubkey_at(&data, 10)? ||
+       *liquidity_mint != pubkey_at(&data, 42)? ||
+       *liquidity_supply != pubkey_at(&data, 75)? ||
+       *collateral_mint != pubkey_at(&data, 227)? {
+        msg!("Accounts don't match the lending reserve");
+        return Err(FundError::InvalidPosition.into());
+    }
+
+    Ok(())
+}
+
+/// SPL stake-pool `DepositSol`.
+///
+/// `deposit_accounts` must be ordered as: stake pool, withdraw authority, reserve stake,
+/// lamports source (signer), pool token destination, manager fee account, referrer fee account,
+/// pool mint, system program, token program.
+pub fn stake_pool_deposit_sol<'a>(
+    stake_pool_program: &AccountInfo<'a>,
+    deposit_accounts: &[AccountInfo<'a>],
+    lamports: u64,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if deposit_accounts.len() != 10 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut data = vec![14u8];
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    adapter_cpi(stake_pool_program, deposit_accounts, 3, &[1, 8, 9], data, signer_seeds)
+}
+
+/// SPL stake-pool `WithdrawSol`.
+///
+/// `withdraw_accounts` must be ordered as: stake pool, withdraw authority, pool token owner (signer),
+/// pool token source, reserve stake, lamports destination, manager fee account, pool mint,
+/// clock sysvar, stake history sysvar, stake program, token program.
+pub fn stake_pool_withdraw_sol<'a>(
+    stake_pool_program: &AccountInfo<'a>,
+    withdraw_accounts: &[AccountInfo<'a>],
+    pool_tokens: u64,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if withdraw_accounts.len() != 12 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut data = vec![16u8];
+    data.extend_from_slice(&pool_tokens.to_le_bytes());
+
+    adapter_cpi(stake_pool_program, withdraw_accounts, 2, &[1, 2, 8, 9, 10, 11], data, signer_seeds)
+}
+
+/// SPL token-lending `DepositReserveLiquidity`.
+///
+/// `deposit_accounts` must be ordered as: liquidity source, collateral destination, reserve,
+/// reserve liquidity supply, reserve collateral mint, lending market, lending market authority,
+/// transfer authority (signer), clock sysvar, token program.
+pub fn lending_deposit_liquidity<'a>(
+    lending_program: &AccountInfo<'a>,
+    deposit_accounts: &[AccountInfo<'a>],
+    liquidity_amount: u64,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if deposit_accounts.len() != 10 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut data = vec![4u8];
+    data.extend_from_slice(&liquidity_amount.to_le_bytes());
+
+    adapter_cpi(lending_program, deposit_accounts, 7, &[5, 6, 7, 8, 9], data, signer_seeds)
+}
+
+/// SPL token-lending `RedeemReserveCollateral`.
+///
+/// `redeem_accounts` must be ordered as: collateral source, liquidity destination, reserve,
+/// reserve collateral mint, reserve liquidity supply, lending market, lending market authority,
+/// transfer authority (signer), clock sysvar, token program.
+pub fn lending_redeem_collateral<'a>(
+    lending_program: &AccountInfo<'a>,
+    redeem_accounts: &[AccountInfo<'a>],
+    collateral_amount: u64,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if redeem_accounts.len() != 10 {
+        return Err(FundError::InvalidAccountData.into());
+    }
+
+    let mut data = vec![5u8];
+    data.extend_from_slice(&collateral_amount.to_le_bytes());
+
+    adapter_cpi(lending_program, redeem_accounts, 7, &[5, 6, 7, 8, 9], data, signer_seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        hashv(&[left, right]).to_bytes()
+    }
+
+    fn leaves(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| hashv(&[b"leaf", &[i]]).to_bytes()).collect()
+    }
+
+    #[test]
+    fn merkle_proof_verifies_every_leaf_at_its_index() {
+        let leaves = leaves(4);
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hash_pair(&left, &right);
+
+        assert!(verify_merkle_proof(leaves[0], &[leaves[1], right], 0, root));
+        assert!(verify_merkle_proof(leaves[1], &[leaves[0], right], 1, root));
+        assert!(verify_merkle_proof(leaves[2], &[leaves[3], left], 2, root));
+        assert!(verify_merkle_proof(leaves[3], &[leaves[2], left], 3, root));
+    }
+
+    #[test]
+    fn merkle_proof_rejects_wrong_index_leaf_or_proof() {
+        let leaves = leaves(4);
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hash_pair(&left, &right);
+
+        assert!(!verify_merkle_proof(leaves[0], &[leaves[1], right], 1, root));
+        assert!(!verify_merkle_proof(leaves[2], &[leaves[1], right], 0, root));
+        assert!(!verify_merkle_proof(leaves[0], &[leaves[1]], 0, root));
+        assert!(!verify_merkle_proof(leaves[0], &[leaves[1], left], 0, root));
+    }
+
+    #[test]
+    fn merkle_proof_of_a_single_leaf_tree_is_empty() {
+        let leaves = leaves(1);
+        assert!(verify_merkle_proof(leaves[0], &[], 0, leaves[0]));
+        assert!(!verify_merkle_proof(leaves[0], &[], 0, [0u8; 32]));
+    }
+
+    fn rules(strength: u64, quorum_bps: u16, approval_bps: u16) -> VotingRules {
+        VotingRules { strength, quorum_bps, approval_bps }
+    }
+
+    #[test]
+    fn votes_pass_once_quorum_and_approval_are_both_reached() {
+        let rules = rules(10, 5_000, 6_000);
+
+        // Quorum of 5 reached, 3 out of 5 is exactly the approval threshold
+        assert!(votes_passed(3, 2, rules));
+        // Quorum reached, approval one short
+        assert!(!votes_passed(2, 3, rules));
+        // Unanimous, turnout one short of quorum
+        assert!(!votes_passed(4, 0, rules));
+        // Both thresholds met on the boundary
+        assert!(votes_passed(5, 0, rules));
+    }
+
+    #[test]
+    fn votes_pass_with_zero_thresholds_or_strength() {
+        assert!(votes_passed(0, 0, rules(10, 0, 0)));
+        assert!(votes_passed(0, 0, rules(0, 5_000, 0)));
+        assert!(!votes_passed(0, 1, rules(0, 5_000, 6_000)));
+        assert!(votes_passed(1, 0, rules(0, 5_000, 6_000)));
+    }
+
+    #[test]
+    fn votes_pass_without_overflow_on_share_weights() {
+        let rules = rules(u64::MAX, 10_000, 10_000);
+        assert!(votes_passed(u64::MAX, 0, rules));
+        assert!(!votes_passed(u64::MAX - 1, 1, rules));
+    }
+
+    #[test]
+    fn yes_votes_are_decisive_past_the_higher_threshold() {
+        // Approval is the higher threshold here: 6 of 10 can't be outvoted nor miss quorum
+        let approval_led = rules(10, 5_000, 6_000);
+        assert!(votes_decisive(6, approval_led));
+        assert!(!votes_decisive(5, approval_led));
+
+        // Quorum is the higher threshold here
+        let quorum_led = rules(10, 8_000, 5_000);
+        assert!(votes_decisive(8, quorum_led));
+        assert!(!votes_decisive(7, quorum_led));
+    }
+
+    #[test]
+    fn decisive_votes_always_pass() {
+        for (strength, quorum_bps, approval_bps) in [(10, 5_000, 6_000), (7, 3_333, 5_001), (3, 10_000, 10_000), (100, 0, 5_000)] {
+            let rules = rules(strength, quorum_bps, approval_bps);
+            for votes_yes in 0..=strength {
+                if votes_decisive(votes_yes, rules) {
+                    assert!(votes_passed(votes_yes, strength - votes_yes, rules));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_is_decisive_until_someone_votes_with_thresholds_set() {
+        assert!(!votes_decisive(0, rules(10, 5_000, 6_000)));
+        assert!(votes_decisive(0, rules(10, 0, 0)));
+        assert!(votes_decisive(u64::MAX, rules(u64::MAX, 10_000, 10_000)));
+    }
+
+    const ALL_STATES: [ProposalState; 10] = [
+        ProposalState::Draft,
+        ProposalState::Voting,
+        ProposalState::Succeeded,
+        ProposalState::Defeated,
+        ProposalState::Queued,
+        ProposalState::Executing,
+        ProposalState::Executed,
+        ProposalState::Failed,
+        ProposalState::Expired,
+        ProposalState::Cancelled,
+    ];
+
+    // Voting ends at 1000s, the deadline being stored in ms
+    const DEADLINE: i64 = 1_000;
+
+    fn proposal_in(state: ProposalState, votes_yes: u64, actions: u8) -> Proposal {
+        let mut proposal = <Proposal as bytemuck::Zeroable>::zeroed();
+        proposal.deadline = DEADLINE * 1000;
+        proposal.proposer = Pubkey::new_unique();
+        proposal.state = state as u8;
+        proposal.votes_yes = votes_yes;
+        proposal.actions = actions;
+        proposal
+    }
+
+    #[test]
+    fn transitions_follow_the_lifecycle_edges_only() {
+        let allowed = |current: ProposalState, next: ProposalState| {
+            use ProposalState::*;
+            matches!(
+                (current, next),
+                (Draft, Voting)
+                    | (Voting, Cancelled)
+                    | (Succeeded | Queued, Queued)
+                    | (Succeeded | Queued | Executing, Executing)
+                    | (Succeeded | Queued | Executing, Executed)
+            )
+        };
+
+        // Before the deadline no state is moved by votes or time
+        for current in ALL_STATES {
+            for next in ALL_STATES {
+                let mut proposal = proposal_in(current, 0, 1);
+                let result = transition_proposal(&mut proposal, next, rules(10, 5_000, 5_000), DEADLINE - 1);
+                if allowed(current, next) {
+                    assert_eq!(result, Ok(()), "{:?} -> {:?}", current, next);
+                    assert_eq!(proposal_state(&proposal).unwrap(), next);
+                } else {
+                    assert_eq!(result, Err(FundError::InvalidProposalState.into()), "{:?} -> {:?}", current, next);
+                    assert_eq!(proposal_state(&proposal).unwrap(), current);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn voting_ends_on_the_tally_once_the_deadline_passed() {
+        let rules = rules(10, 5_000, 5_000);
+
+        let mut passed = proposal_in(ProposalState::Voting, 6, 1);
+        assert!(transition_proposal(&mut passed, ProposalState::Cancelled, rules, DEADLINE + 1).is_err());
+        assert_eq!(proposal_state(&passed).unwrap(), ProposalState::Succeeded);
+        assert_eq!(transition_proposal(&mut passed, ProposalState::Queued, rules, DEADLINE + 1), Ok(()));
+
+        let mut defeated = proposal_in(ProposalState::Voting, 4, 1);
+        assert!(transition_proposal(&mut defeated, ProposalState::Executing, rules, DEADLINE + 1).is_err());
+        assert_eq!(proposal_state(&defeated).unwrap(), ProposalState::Defeated);
+
+        // Voting still runs on the deadline itself
+        let mut voting = proposal_in(ProposalState::Voting, 6, 1);
+        assert_eq!(transition_proposal(&mut voting, ProposalState::Cancelled, rules, DEADLINE), Ok(()));
+
+        let mut draft = proposal_in(ProposalState::Draft, 0, 1);
+        assert!(transition_proposal(&mut draft, ProposalState::Voting, rules, DEADLINE + 1).is_err());
+    }
+
+    #[test]
+    fn unexecuted_proposals_expire_and_unfinished_ones_fail() {
+        let rules = rules(10, 5_000, 5_000);
+        let after_expiry = DEADLINE + PROPOSAL_EXPIRY + 1;
+
+        for state in [ProposalState::Succeeded, ProposalState::Queued] {
+            let mut proposal = proposal_in(state, 6, 1);
+            assert!(transition_proposal(&mut proposal, ProposalState::Executing, rules, after_expiry).is_err());
+            assert_eq!(proposal_state(&proposal).unwrap(), ProposalState::Expired);
+        }
+
+        let mut executing = proposal_in(ProposalState::Executing, 6, 2);
+        assert!(transition_proposal(&mut executing, ProposalState::Executed, rules, after_expiry).is_err());
+        assert_eq!(proposal_state(&executing).unwrap(), ProposalState::Failed);
+
+        // Still inside the window on its last second
+        let mut queued = proposal_in(ProposalState::Queued, 6, 1);
+        assert_eq!(transition_proposal(&mut queued, ProposalState::Executed, rules, DEADLINE + PROPOSAL_EXPIRY), Ok(()));
+    }
+
+    #[test]
+    fn executed_actions_complete_the_proposal_once_each() {
+        let rules = rules(10, 5_000, 5_000);
+        let mut proposal = proposal_in(ProposalState::Succeeded, 6, 3);
+
+        assert_eq!(record_executed_action(&mut proposal, 1, rules, DEADLINE + 1), Ok(()));
+        assert_eq!(proposal_state(&proposal).unwrap(), ProposalState::Executing);
+        assert_eq!(proposal.swaps_status, 0b010);
+
+        assert_eq!(record_executed_action(&mut proposal, 1, rules, DEADLINE + 1), Err(FundError::ActionAlreadyExecuted.into()));
+        assert_eq!(record_executed_action(&mut proposal, 3, rules, DEADLINE + 1), Err(FundError::InvalidInstruction.into()));
+
+        assert_eq!(record_executed_action(&mut proposal, 0, rules, DEADLINE + 1), Ok(()));
+        assert_eq!(proposal_state(&proposal).unwrap(), ProposalState::Executing);
+
+        assert_eq!(record_executed_action(&mut proposal, 2, rules, DEADLINE + 1), Ok(()));
+        assert_eq!(proposal_state(&proposal).unwrap(), ProposalState::Executed);
+        assert_eq!(proposal.swaps_status, 0b111);
+    }
+
+    #[test]
+    fn executed_actions_need_an_approved_proposal() {
+        let rules = rules(10, 5_000, 5_000);
+
+        // Still voting, then defeated: the bit is left untouched either way
+        let mut voting = proposal_in(ProposalState::Voting, 6, 1);
+        assert!(record_executed_action(&mut voting, 0, rules, DEADLINE - 1).is_err());
+        let mut defeated = proposal_in(ProposalState::Voting, 4, 1);
+        assert!(record_executed_action(&mut defeated, 0, rules, DEADLINE + 1).is_err());
+        assert_eq!(defeated.swaps_status, 0);
+
+        // Sixteen actions at most fit the bitmap
+        let mut full = proposal_in(ProposalState::Succeeded, 6, 17);
+        assert_eq!(record_executed_action(&mut full, 16, rules, DEADLINE + 1), Err(FundError::InvalidInstruction.into()));
+        assert_eq!(record_executed_action(&mut full, 15, rules, DEADLINE + 1), Ok(()));
+    }
+
+    #[test]
+    fn withdrawals_wait_the_notice_then_the_next_batch() {
+        let created_at = 1_000;
+        let notice = 100;
+
+        // Requested on a batch boundary: claimable exactly one notice later
+        assert_eq!(withdrawal_claimable_at(created_at, notice, 1_000), 1_100);
+        assert_eq!(withdrawal_claimable_at(created_at, notice, 1_300), 1_400);
+
+        // Requested mid-batch: rounded up to the boundary after the notice ran out
+        assert_eq!(withdrawal_claimable_at(created_at, notice, 1_001), 1_200);
+        assert_eq!(withdrawal_claimable_at(created_at, notice, 1_299), 1_400);
+
+        // Every request of one batch settles together, never before its notice
+        for current_time in 1_201..=1_300 {
+            let claimable_at = withdrawal_claimable_at(created_at, notice, current_time);
+            assert_eq!(claimable_at, 1_400);
+            assert!(claimable_at >= current_time + notice);
+        }
+
+        // A one second notice batches nothing, and no notice at all doesn't wait
+        assert_eq!(withdrawal_claimable_at(created_at, 1, 1_234), 1_235);
+        assert_eq!(withdrawal_claimable_at(created_at, 0, 1_234), 1_234);
+        assert_eq!(withdrawal_claimable_at(created_at, -5, 1_234), 1_234);
+    }
+
+    fn light_fund(expected_members: u32, creation_cost: u64, is_refunded: bool) -> LightFundAccount {
+        let mut fund_data = LightFundAccount::deserialize(&mut &[0u8; 291][..]).unwrap();
+        fund_data.expected_members = expected_members;
+        fund_data.creation_cost = creation_cost;
+        fund_data.is_refunded = is_refunded;
+        fund_data
+    }
+
+    #[test]
+    fn joiners_split_the_creation_cost_until_the_creator_is_refunded() {
+        assert_eq!(creation_cost_share(&light_fund(4, 10_000, false)), 2_500);
+        // Rounded down, the creator carries the remainder
+        assert_eq!(creation_cost_share(&light_fund(3, 10_000, false)), 3_333);
+        assert_eq!(creation_cost_share(&light_fund(1, 10_000, false)), 10_000);
+
+        assert_eq!(creation_cost_share(&light_fund(4, 10_000, true)), 0);
+        // Opting out with no expected members starts refunded, nothing is divided by zero
+        assert_eq!(creation_cost_share(&light_fund(0, 10_000, true)), 0);
+    }
+
+    fn token_2022_mint(extensions: &[ExtensionType]) -> Vec<u8> {
+        use spl_token_2022::extension::{
+            mint_close_authority::MintCloseAuthority, non_transferable::NonTransferable, permanent_delegate::PermanentDelegate,
+            transfer_fee::TransferFeeConfig, StateWithExtensionsMut,
+        };
+
+        let mut data = vec![0u8; ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions).unwrap()];
+        let mut mint = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::MintCloseAuthority => { mint.init_extension::<MintCloseAuthority>(true).unwrap(); }
+                ExtensionType::TransferFeeConfig => { mint.init_extension::<TransferFeeConfig>(true).unwrap(); }
+                ExtensionType::NonTransferable => { mint.init_extension::<NonTransferable>(true).unwrap(); }
+                ExtensionType::PermanentDelegate => { mint.init_extension::<PermanentDelegate>(true).unwrap(); }
+                _ => unreachable!(),
+            }
+        }
+        mint.base.decimals = 9;
+        mint.base.is_initialized = true;
+        mint.pack_base();
+        if !extensions.is_empty() {
+            mint.init_account_type().unwrap();
+        }
+        data
+    }
+
+    fn mint_decimals(owner: Pubkey, mut data: Vec<u8>) -> Result<u8, ProgramError> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mint_info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        supported_mint_decimals(&mint_info)
+    }
+
+    #[test]
+    fn mints_of_both_token_programs_are_supported() {
+        let mut data = vec![0u8; spl_token::state::Mint::LEN];
+        let mint = spl_token::state::Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+        assert_eq!(mint_decimals(spl_token::id(), data), Ok(6));
+
+        assert_eq!(mint_decimals(spl_token_2022::id(), token_2022_mint(&[])), Ok(9));
+        let harmless = [ExtensionType::MintCloseAuthority, ExtensionType::TransferFeeConfig];
+        assert_eq!(mint_decimals(spl_token_2022::id(), token_2022_mint(&harmless)), Ok(9));
+
+        // Mint layout owned by some other program
+        assert_eq!(mint_decimals(Pubkey::new_unique(), token_2022_mint(&[])), Err(FundError::InvalidMints.into()));
+    }
+
+    #[test]
+    fn mints_with_unsupported_extensions_are_rejected() {
+        for unsupported in [ExtensionType::NonTransferable, ExtensionType::PermanentDelegate] {
+            assert_eq!(mint_decimals(spl_token_2022::id(), token_2022_mint(&[unsupported])), Err(FundError::UnsupportedMint.into()));
+            // Alongside supported extensions too
+            let extensions = [ExtensionType::TransferFeeConfig, unsupported];
+            assert_eq!(mint_decimals(spl_token_2022::id(), token_2022_mint(&extensions)), Err(FundError::UnsupportedMint.into()));
+        }
+    }
+
+    fn sorted_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b { hash_pair(a, b) } else { hash_pair(b, a) }
+    }
+
+    #[test]
+    fn allowlist_proof_verifies_listed_wallets_in_any_order() {
+        let wallets: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = wallets.iter().map(allowlist_leaf).collect();
+        let left = sorted_pair(&leaves[0], &leaves[1]);
+        let right = sorted_pair(&leaves[2], &leaves[3]);
+        let root = sorted_pair(&right, &left);
+
+        assert!(verify_allowlist_proof(leaves[0], &[leaves[1], right], root));
+        assert!(verify_allowlist_proof(leaves[1], &[leaves[0], right], root));
+        assert!(verify_allowlist_proof(leaves[2], &[leaves[3], left], root));
+        assert!(verify_allowlist_proof(leaves[3], &[leaves[2], left], root));
+    }
+
+    #[test]
+    fn allowlist_proof_rejects_unlisted_wallets_and_bad_proofs() {
+        let wallets: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = wallets.iter().map(allowlist_leaf).collect();
+        let root = sorted_pair(&leaves[0], &leaves[1]);
+
+        let outsider = allowlist_leaf(&Pubkey::new_unique());
+        assert!(!verify_allowlist_proof(outsider, &[leaves[1]], root));
+        assert!(!verify_allowlist_proof(leaves[0], &[], root));
+        assert!(!verify_allowlist_proof(leaves[0], &[leaves[0]], root));
+
+        // A wallet's own leaf is the root of a one-wallet allowlist
+        assert!(verify_allowlist_proof(leaves[0], &[], leaves[0]));
+    }
+
+    #[test]
+    fn management_fee_is_prorated_over_the_year() {
+        let nav = 1_000_000_000_000;
+        let (management_fee, performance_fee) = crystallization_fees(nav, 1_000, SECONDS_PER_YEAR, 200, 0, 0);
+        assert_eq!(management_fee, nav * 2 / 100);
+        assert_eq!(performance_fee, 0);
+
+        let (half_year_fee, _) = crystallization_fees(nav, 1_000, SECONDS_PER_YEAR / 2, 200, 0, 0);
+        assert_eq!(half_year_fee, nav / 100);
+
+        assert_eq!(crystallization_fees(nav, 1_000, 0, 200, 0, 0), (0, 0));
+    }
+
+    #[test]
+    fn performance_fee_only_above_the_high_water_mark() {
+        let total_shares = 1_000;
+        let nav = 2_000_000;
+        let nav_per_share = (nav * SHARE_PRICE_SCALE / total_shares) as u64;
+
+        // First crystallization only sets the mark
+        assert_eq!(crystallization_fees(nav, total_shares, 0, 0, 2_000, 0), (0, 0));
+
+        // Sitting exactly on the mark is no gain
+        assert_eq!(crystallization_fees(nav, total_shares, 0, 0, 2_000, nav_per_share), (0, 0));
+
+        // Below the mark there is no gain either
+        assert_eq!(crystallization_fees(nav, total_shares, 0, 0, 2_000, nav_per_share + 1), (0, 0));
+
+        // Gain above the mark is charged at the performance rate
+        let high_water_mark = nav_per_share / 2;
+        let (_, performance_fee) = crystallization_fees(nav, total_shares, 0, 0, 2_000, high_water_mark);
+        let gain = (nav_per_share as u128 - high_water_mark as u128) * total_shares / SHARE_PRICE_SCALE;
+        assert_eq!(performance_fee, gain * 2_000 / 10_000);
+    }
+
+    #[test]
+    fn fees_taking_the_whole_nav_are_dropped() {
+        assert_eq!(crystallization_fees(1_000, 1_000, SECONDS_PER_YEAR, 10_000, 0, 0), (0, 0));
+        assert_eq!(crystallization_fees(0, 1_000, SECONDS_PER_YEAR, 200, 2_000, 1), (0, 0));
+        assert_eq!(crystallization_fees(1_000, 0, SECONDS_PER_YEAR, 200, 2_000, 1), (0, 0));
+    }
+
+    #[test]
+    fn fee_shares_dilute_by_the_fee_value() {
+        // A 100 fee out of a 1000 NAV over 900 shares: the 100 new shares are worth 1000 * 100 / 1000
+        assert_eq!(fee_shares(100, 900, 1_000).unwrap(), 100);
+        assert_eq!(fee_shares(0, 900, 1_000).unwrap(), 0);
+
+        let shares = fee_shares(200, 800, 1_000).unwrap() as u128;
+        assert_eq!(1_000 * shares / (800 + shares), 200);
+
+        assert!(fee_shares(1_000, 900, 1_000).is_err());
+        assert!(fee_shares(1_001, 900, 1_000).is_err());
+    }
+
+    #[test]
+    fn deposits_buy_shares_at_the_nav_per_share() {
+        assert_eq!(shares_for_value(500, 2_000, 1_000).unwrap(), Some(250));
+        assert_eq!(shares_for_value(1, 2_000, 1_000).unwrap(), Some(0));
+
+        // Nothing to price against in an empty fund, worthless shares can't be bought into
+        assert_eq!(shares_for_value(500, 2_000, 0).unwrap(), None);
+        assert_eq!(shares_for_value(500, 0, 1_000), Err(FundError::FundHasNoValue.into()));
+    }
+}